@@ -1,3 +1,5 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+
 use crate::lexer::{lex, KeywordType, SymbolType, Token, TokenKind};
 
 #[derive(Debug)]
@@ -14,6 +16,54 @@ enum ErrorKind {
     ExpectedColumnType,
     ExpectedCommaOrRightParen,
     InvalidType,
+    ExpectedExpression,
+    ExpectedColumnName,
+    ExpectedPrimaryKeyword,
+    ExpectedKeyKeyword,
+    MissingSetKeyword,
+    ExpectedEq,
+    UnexpectedToken,
+    ExpectedNullAfterNot,
+    ExpectedByKeyword,
+    ExpectedFirstOrLast,
+    ExpectedFirstOrNext,
+    ExpectedRowsKeyword,
+    ExpectedOnlyKeyword,
+    ExpectedJoinKeyword,
+    ExpectedJoinConstraint,
+    ExpectedSelect,
+    ExpectedAlias,
+    ExpectedAsKeyword,
+    ExpectedWhenKeyword,
+    ExpectedThenKeyword,
+    ExpectedEndKeyword,
+    ExpectedType,
+    ExpectedToKeyword,
+    ExpectedAndKeyword,
+    ExpectedNullOrDistinct,
+    ExpectedFromKeyword,
+    ExpectedTableOrView,
+    ExpectedFrameBound,
+    InvalidFrameBound,
+    ExpectedConflictKeyword,
+    ExpectedDoKeyword,
+    ExpectedNothingOrUpdate,
+    MissingConflictTarget,
+    ExpectedExplainOption,
+    ExpectedExplainFormat,
+    ExpectedExplainableStatement,
+    ExpectedNotKeyword,
+    ExpectedExistsKeyword,
+    InvalidParameter,
+    ExpectedStatementName,
+    ExpectedPreparableStatement,
+    ValuesLengthMismatch,
+    ExpectedReferencesKeyword,
+    ExpectedDeleteOrUpdate,
+    ExpectedReferentialAction,
+    ExpectedAlwaysOrByDefault,
+    ExpectedIdentityKeyword,
+    ExpectedSettingName,
 }
 
 impl std::fmt::Display for ErrorKind {
@@ -41,40 +91,725 @@ impl std::fmt::Display for ParseError {
     }
 }
 
-struct Ast {
-    pub statements: StatementType,
-}
-
-enum StatementType {
-    Select,
+#[derive(Debug, Clone)]
+pub enum Statement {
+    Select(Box<SelectStatement>),
     Create(CreateStatement),
-    Drop,
     Insert(InsertStatement),
+    Update(UpdateStatement),
+    CreateView(CreateViewStatement),
+    Drop(DropStatement),
+    Delete(DeleteStatement),
+    Explain(Box<ExplainStatement>),
+    Prepare(PrepareStatement),
+    Execute(ExecuteStatement),
+    Deallocate(DeallocateStatement),
+    Set(SetStatement),
 }
 
-#[derive(Debug)]
+// `prepare name as ...`, the statement can read the values of `$1`, `$2`... given when it is executed.
+#[derive(Debug, Clone)]
+pub struct PrepareStatement {
+    pub name: Token,
+    // the types of the first parameters, the others get the type of what they are compared to or stored in
+    pub parameter_types: Vec<Token>,
+    // a select, insert, update or delete
+    pub statement: Box<Statement>,
+}
+
+#[derive(Debug, Clone)]
+pub struct ExecuteStatement {
+    pub name: Token,
+    pub parameters: Vec<Expression>,
+}
+
+#[derive(Debug, Clone)]
+pub struct DeallocateStatement {
+    // `None` for `deallocate all`
+    pub name: Option<Token>,
+}
+
+// `set name = value`, changes a setting of the database, e.g. how deep a recursive query can go.
+#[derive(Debug, Clone)]
+pub struct SetStatement {
+    pub name: Token,
+    pub value: Expression,
+}
+
+#[derive(Debug, Clone)]
+pub struct ExplainStatement {
+    // runs the query and shows the rows and time of each operator too
+    pub is_analyze: bool,
+    pub format: ExplainFormat,
+    // a select, insert, update or delete
+    pub statement: Box<Statement>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ExplainFormat {
+    Text,
+    Json,
+}
+
+#[derive(Debug, Clone)]
 pub struct InsertStatement {
     pub table: Token,
-    pub values: Vec<Token>,
+    // empty when the values are given for every column
+    pub columns: Vec<Token>,
+    pub source: InsertSource,
+    pub on_conflict: Option<OnConflict>,
+    pub returning: Option<Returning>,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
+pub struct DeleteStatement {
+    pub table: Token,
+    pub where_clause: Option<Expression>,
+    pub returning: Option<Returning>,
+}
+
+// what a write statement returns for each row it inserted, updated or deleted.
+#[derive(Debug, Clone)]
+pub enum Returning {
+    // `returning *`
+    AllColumns,
+    Items(Vec<SelectItem>),
+}
+
+// `on conflict [ ( [...$name] ) ] do ...`, what an insert does with rows that duplicate a key.
+#[derive(Debug, Clone)]
+pub struct OnConflict {
+    // the columns of the conflicting primary key or unique constraint, empty for any of them
+    pub columns: Vec<Token>,
+    pub action: ConflictAction,
+}
+
+#[derive(Debug, Clone)]
+pub enum ConflictAction {
+    Nothing,
+    // the assignments can read the row that was not inserted as `excluded`
+    Update {
+        assignments: Vec<Assignment>,
+        where_clause: Option<Expression>,
+    },
+}
+
+#[derive(Debug, Clone)]
+pub struct CreateViewStatement {
+    pub name: Token,
+    // names for the columns of the query, the ones that aren't named keep their name
+    pub columns: Vec<Token>,
+    pub query: Box<SelectStatement>,
+    // `if not exists`, the statement is skipped when the name is taken
+    pub is_if_not_exists: bool,
+}
+
+#[derive(Debug, Clone)]
+pub struct DropStatement {
+    pub kind: DropKind,
+    pub name: Token,
+    // drops the views that depend on the dropped table or view too
+    pub is_cascade: bool,
+    // `if exists`, the statement is skipped when there is nothing to drop
+    pub is_if_exists: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DropKind {
+    Table,
+    View,
+}
+
+#[derive(Debug, Clone)]
+pub enum InsertSource {
+    // `values (...)`, a single row
+    Values(Vec<Expression>),
+    // `select ...`, a row for each row of the query
+    Query(Box<SelectStatement>),
+}
+
+#[derive(Debug, Clone)]
 pub struct CreateStatement {
     pub name: Token,
     pub cols: Vec<Column>,
+    pub constraints: Vec<TableConstraint>,
+    // `create table ... as select ...`, the columns are the ones of the query
+    pub query: Option<Box<SelectStatement>>,
+    // `if not exists`, the statement is skipped when the name is taken
+    pub is_if_not_exists: bool,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Column {
     pub name: Token,
     pub data_type: Token,
     pub is_primary_key: bool,
+    pub is_not_null: bool,
+    pub is_unique: bool,
+    pub default: Option<Expression>,
+    pub checks: Vec<Expression>,
+    pub references: Option<References>,
+    pub identity: Option<Identity>,
 }
 
-#[derive(Debug)]
+// `generated { always | by default } as identity`, an insert leaving the column out gives it the
+// next value of the table's counter for it. `serial` is an int generated by default.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Identity {
+    // the values can't be given by inserts or updates
+    Always,
+    ByDefault,
+}
+
+#[derive(Debug, Clone)]
+pub enum TableConstraint {
+    // the tokens are the column names
+    PrimaryKey(Vec<Token>),
+    Unique(Vec<Token>),
+    Check(Expression),
+    // `foreign key ( [...$name] ) references ...`, the tokens are the referencing columns
+    ForeignKey(Vec<Token>, References),
+}
+
+// `references $table [ ( [...$name] ) ]`, the rows of the referenced table a foreign key points to.
+#[derive(Debug, Clone)]
+pub struct References {
+    pub table: Token,
+    // the referenced columns, empty for the primary key of the table
+    pub columns: Vec<Token>,
+    pub on_delete: ReferentialAction,
+    pub on_update: ReferentialAction,
+}
+
+// what happens to the referencing rows when the row they reference is deleted or its key is updated.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum ReferentialAction {
+    // fails unless another row has the key, e.g. when keys are swapped
+    #[default]
+    NoAction,
+    // always fails
+    Restrict,
+    // deletes the referencing rows, or updates them to the new key
+    Cascade,
+    SetNull,
+    SetDefault,
+}
+
+#[derive(Debug, Clone, PartialEq)]
 pub struct SelectStatement {
-    pub table_name: Token,
+    pub id: QueryId,
+    pub with: Vec<CommonTableExpression>,
+    pub distinct: Option<Distinct>,
+    // a select without a from clause reads a single row without columns
+    pub from: Option<TableReference>,
     pub items: Vec<SelectItem>,
+    pub where_clause: Option<Expression>,
+    pub group_by: Vec<Expression>,
+    pub having: Option<Expression>,
+    // combine the rows of this select with the ones of other queries, the order by,
+    // limit and offset then apply to the combined rows.
+    pub set_operations: Vec<SetOperation>,
+    pub order_by: Vec<OrderByItem>,
+    pub limit: Option<Expression>,
+    pub offset: Option<Expression>,
+}
+
+// tells apart the selects of a statement, copies of a select keep its id. `explain analyze` keys
+// the stats of each select on it.
+#[derive(Debug, Clone, Copy)]
+pub struct QueryId(pub usize);
+
+impl QueryId {
+    fn next() -> Self {
+        static NEXT_ID: AtomicUsize = AtomicUsize::new(0);
+        QueryId(NEXT_ID.fetch_add(1, Ordering::Relaxed))
+    }
+}
+
+// selects are equal when they have the same contents, wherever they are written.
+impl PartialEq for QueryId {
+    fn eq(&self, _other: &Self) -> bool {
+        true
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Distinct {
+    // `select distinct`, removes duplicate rows
+    Row,
+    // `select distinct on ( [...$expression] )`, keeps the first row for each value of the expressions
+    On(Vec<Expression>),
+}
+
+// `union [ all ] $select`, the operations of a select are applied from left to right.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SetOperation {
+    pub operator: SetOperator,
+    pub is_all: bool,
+    pub query: SelectStatement,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SetOperator {
+    Union,
+    Intersect,
+    Except,
+}
+
+// `name [ ( [...$column] ) ] as ( $select )` in a with clause
+#[derive(Debug, Clone, PartialEq)]
+pub struct CommonTableExpression {
+    pub name: Token,
+    // renames the columns of the query, empty to keep its names
+    pub columns: Vec<Token>,
+    pub query: SelectStatement,
+    // in a `with recursive` the query after the last union can read the rows added last
+    pub is_recursive: bool,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum TableReference {
+    Table {
+        name: Token,
+        alias: Option<Token>,
+    },
+    // a derived table, `(select ...) as alias`
+    Subquery {
+        query: Box<SelectStatement>,
+        alias: Token,
+    },
+    // `(values (...), (...)) as alias [ ( [...$column] ) ]`, the columns are named column1, column2...
+    // unless renamed. Only the values of a standalone `values` query have no alias.
+    Values {
+        rows: Vec<Vec<Expression>>,
+        alias: Option<Token>,
+        columns: Vec<Token>,
+    },
+    Join {
+        left: Box<TableReference>,
+        right: Box<TableReference>,
+        kind: JoinKind,
+        constraint: JoinConstraint,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum JoinKind {
+    Inner,
+    Left,
+    Right,
+    Full,
+    Cross,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum JoinConstraint {
+    On(Expression),
+    // the tokens are the column names
+    Using(Vec<Token>),
+    // cross joins have no constraint
+    None,
+}
+
+#[derive(Debug, Clone)]
+pub struct UpdateStatement {
+    pub table: Token,
+    pub assignments: Vec<Assignment>,
+    pub where_clause: Option<Expression>,
+    pub returning: Option<Returning>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Assignment {
+    pub column: Token,
+    pub value: Expression,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Expression {
+    // a numeric, string or null token
+    Literal(Token),
+    // `$1`, `$2`... the values of a prepared statement, numbered from 1
+    Parameter(usize),
+    // `name` or `table.name`
+    Column {
+        table: Option<Token>,
+        name: Token,
+    },
+    Function(FunctionCall),
+    // `function(...) over (...)`
+    Window {
+        function: FunctionCall,
+        window: WindowSpec,
+    },
+    Unary {
+        operator: UnaryOperator,
+        operand: Box<Expression>,
+    },
+    Binary {
+        left: Box<Expression>,
+        operator: BinaryOperator,
+        right: Box<Expression>,
+    },
+    // a scalar subquery, `(select ...)`
+    Subquery(Box<SelectStatement>),
+    // `exists (select ...)`
+    Exists(Box<SelectStatement>),
+    // `expression [ not ] in (select ...)`
+    InSubquery {
+        expression: Box<Expression>,
+        subquery: Box<SelectStatement>,
+        is_negated: bool,
+    },
+    // `expression [ not ] in (value, ...)`
+    InList {
+        expression: Box<Expression>,
+        list: Vec<Expression>,
+        is_negated: bool,
+    },
+    // `expression [ not ] between low and high`
+    Between {
+        expression: Box<Expression>,
+        low: Box<Expression>,
+        high: Box<Expression>,
+        is_negated: bool,
+    },
+    // `expression is [ not ] null`
+    IsNull {
+        expression: Box<Expression>,
+        is_negated: bool,
+    },
+    // `left is [ not ] distinct from right`, where nulls are compared like other values
+    IsDistinctFrom {
+        left: Box<Expression>,
+        right: Box<Expression>,
+        is_negated: bool,
+    },
+    // `case [ operand ] when ... then ... [ else ... ] end`, with an operand the
+    // conditions are values it is compared to
+    Case {
+        operand: Option<Box<Expression>>,
+        branches: Vec<WhenClause>,
+        else_result: Option<Box<Expression>>,
+    },
+    // `expression [ not ] like pattern [ escape escape ]`, or ilike or similar to
+    Like {
+        expression: Box<Expression>,
+        operator: LikeOperator,
+        pattern: Box<Expression>,
+        escape: Option<Box<Expression>>,
+        is_negated: bool,
+    },
+    // `cast(expression as type)` or `expression::type`
+    Cast {
+        expression: Box<Expression>,
+        data_type: Token,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct WindowSpec {
+    pub partition_by: Vec<Expression>,
+    pub order_by: Vec<OrderByItem>,
+    // `None` when not given, the frame then ends with the last row sorting like the current one
+    pub frame: Option<WindowFrame>,
+}
+
+// `rows between start and end`, the rows of the partition a window function reads.
+#[derive(Debug, Clone, PartialEq)]
+pub struct WindowFrame {
+    pub start: FrameBound,
+    pub end: FrameBound,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum FrameBound {
+    UnboundedPreceding,
+    // the expression is the number of rows
+    Preceding(Box<Expression>),
+    CurrentRow,
+    Following(Box<Expression>),
+    UnboundedFollowing,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct WhenClause {
+    pub condition: Expression,
+    pub result: Expression,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct FunctionCall {
+    pub name: Token,
+    pub args: Vec<Expression>,
+    // `count(distinct x)`
+    pub is_distinct: bool,
+    // `count(*)`
+    pub is_star: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum LikeOperator {
+    Like,
+    ILike,
+    SimilarTo,
+}
+
+impl LikeOperator {
+    pub fn name(&self) -> &'static str {
+        match self {
+            LikeOperator::Like => "like",
+            LikeOperator::ILike => "ilike",
+            LikeOperator::SimilarTo => "similar to",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum UnaryOperator {
+    Minus,
+    Not,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BinaryOperator {
+    Or,
+    And,
+    Eq,
+    Neq,
+    Lt,
+    Lte,
+    Gt,
+    Gte,
+    Plus,
+    Minus,
+    Multiply,
+    Divide,
+}
+
+// precedence of the prefix operators and `in`, binary operators are in `BinaryOperator::precedence`.
+const NOT_PRECEDENCE: u8 = 3;
+const IN_PRECEDENCE: u8 = 4;
+const UNARY_MINUS_PRECEDENCE: u8 = 7;
+
+impl BinaryOperator {
+    fn from_token(token: &Token) -> Option<Self> {
+        match &token.kind {
+            TokenKind::Keyword(KeywordType::Or) => Some(BinaryOperator::Or),
+            TokenKind::Keyword(KeywordType::And) => Some(BinaryOperator::And),
+            TokenKind::Symbol(SymbolType::Eq) => Some(BinaryOperator::Eq),
+            TokenKind::Symbol(SymbolType::Neq) => Some(BinaryOperator::Neq),
+            TokenKind::Symbol(SymbolType::Lt) => Some(BinaryOperator::Lt),
+            TokenKind::Symbol(SymbolType::Lte) => Some(BinaryOperator::Lte),
+            TokenKind::Symbol(SymbolType::Gt) => Some(BinaryOperator::Gt),
+            TokenKind::Symbol(SymbolType::Gte) => Some(BinaryOperator::Gte),
+            TokenKind::Symbol(SymbolType::Plus) => Some(BinaryOperator::Plus),
+            TokenKind::Symbol(SymbolType::Minus) => Some(BinaryOperator::Minus),
+            TokenKind::Symbol(SymbolType::Star) => Some(BinaryOperator::Multiply),
+            TokenKind::Symbol(SymbolType::Slash) => Some(BinaryOperator::Divide),
+            _ => None,
+        }
+    }
+
+    fn precedence(self) -> u8 {
+        match self {
+            BinaryOperator::Or => 1,
+            BinaryOperator::And => 2,
+            BinaryOperator::Eq
+            | BinaryOperator::Neq
+            | BinaryOperator::Lt
+            | BinaryOperator::Lte
+            | BinaryOperator::Gt
+            | BinaryOperator::Gte => 4,
+            BinaryOperator::Plus | BinaryOperator::Minus => 5,
+            BinaryOperator::Multiply | BinaryOperator::Divide => 6,
+        }
+    }
+}
+
+impl BinaryOperator {
+    pub fn symbol(self) -> &'static str {
+        match self {
+            BinaryOperator::Or => "or",
+            BinaryOperator::And => "and",
+            BinaryOperator::Eq => "=",
+            BinaryOperator::Neq => "<>",
+            BinaryOperator::Lt => "<",
+            BinaryOperator::Lte => "<=",
+            BinaryOperator::Gt => ">",
+            BinaryOperator::Gte => ">=",
+            BinaryOperator::Plus => "+",
+            BinaryOperator::Minus => "-",
+            BinaryOperator::Multiply => "*",
+            BinaryOperator::Divide => "/",
+        }
+    }
+}
+
+fn negation(is_negated: bool) -> &'static str {
+    if is_negated {
+        "not "
+    } else {
+        ""
+    }
+}
+
+fn write_list<T: std::fmt::Display>(f: &mut std::fmt::Formatter<'_>, items: &[T]) -> std::fmt::Result {
+    for (index, item) in items.iter().enumerate() {
+        if index > 0 {
+            f.write_str(", ")?;
+        }
+        write!(f, "{}", item)?;
+    }
+    Ok(())
+}
+
+// the expression written back as sql, with binary operations in parentheses so the grouping is
+// visible. subqueries are only shown as `(subquery)`.
+impl std::fmt::Display for Expression {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Expression::Literal(token) => f.write_str(&token.value),
+            Expression::Parameter(index) => write!(f, "${}", index),
+            Expression::Column { table: Some(table), name } => write!(f, "{}.{}", table.value, name.value),
+            Expression::Column { table: None, name } => f.write_str(&name.value),
+            Expression::Function(call) => write!(f, "{}", call),
+            Expression::Window { function, window } => write!(f, "{} over ({})", function, window),
+            Expression::Unary {
+                operator: UnaryOperator::Minus,
+                operand,
+            } => write!(f, "-{}", operand),
+            Expression::Unary {
+                operator: UnaryOperator::Not,
+                operand,
+            } => write!(f, "not {}", operand),
+            Expression::Binary {
+                left,
+                operator,
+                right,
+            } => write!(f, "({} {} {})", left, operator.symbol(), right),
+            Expression::Subquery(_) => f.write_str("(subquery)"),
+            Expression::Exists(_) => f.write_str("exists (subquery)"),
+            Expression::InSubquery {
+                expression,
+                is_negated,
+                ..
+            } => write!(f, "{} {}in (subquery)", expression, negation(*is_negated)),
+            Expression::InList {
+                expression,
+                list,
+                is_negated,
+            } => {
+                write!(f, "{} {}in (", expression, negation(*is_negated))?;
+                write_list(f, list)?;
+                f.write_str(")")
+            }
+            Expression::Between {
+                expression,
+                low,
+                high,
+                is_negated,
+            } => write!(f, "{} {}between {} and {}", expression, negation(*is_negated), low, high),
+            Expression::IsNull {
+                expression,
+                is_negated,
+            } => write!(f, "{} is {}null", expression, negation(*is_negated)),
+            Expression::IsDistinctFrom {
+                left,
+                right,
+                is_negated,
+            } => write!(f, "{} is {}distinct from {}", left, negation(*is_negated), right),
+            Expression::Case {
+                operand,
+                branches,
+                else_result,
+            } => {
+                f.write_str("case")?;
+                if let Some(operand) = operand {
+                    write!(f, " {}", operand)?;
+                }
+                for branch in branches {
+                    write!(f, " when {} then {}", branch.condition, branch.result)?;
+                }
+                if let Some(else_result) = else_result {
+                    write!(f, " else {}", else_result)?;
+                }
+                f.write_str(" end")
+            }
+            Expression::Like {
+                expression,
+                operator,
+                pattern,
+                escape,
+                is_negated,
+            } => {
+                write!(f, "{} {}{} {}", expression, negation(*is_negated), operator.name(), pattern)?;
+                match escape {
+                    Some(escape) => write!(f, " escape {}", escape),
+                    None => Ok(()),
+                }
+            }
+            Expression::Cast { expression, data_type } => write!(f, "cast({} as {})", expression, data_type.value),
+        }
+    }
+}
+
+impl std::fmt::Display for FunctionCall {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}(", self.name.value)?;
+        if self.is_star {
+            f.write_str("*")?;
+        }
+        if self.is_distinct {
+            f.write_str("distinct ")?;
+        }
+        write_list(f, &self.args)?;
+        f.write_str(")")
+    }
+}
+
+impl std::fmt::Display for WindowSpec {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut clauses = vec![];
+        if !self.partition_by.is_empty() {
+            let expressions: Vec<String> = self.partition_by.iter().map(Expression::to_string).collect();
+            clauses.push(format!("partition by {}", expressions.join(", ")));
+        }
+        if !self.order_by.is_empty() {
+            let items: Vec<String> = self.order_by.iter().map(OrderByItem::to_string).collect();
+            clauses.push(format!("order by {}", items.join(", ")));
+        }
+        if let Some(frame) = &self.frame {
+            clauses.push(format!("rows between {} and {}", frame.start, frame.end));
+        }
+        f.write_str(&clauses.join(" "))
+    }
+}
+
+impl std::fmt::Display for FrameBound {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FrameBound::UnboundedPreceding => f.write_str("unbounded preceding"),
+            FrameBound::Preceding(rows) => write!(f, "{} preceding", rows),
+            FrameBound::CurrentRow => f.write_str("current row"),
+            FrameBound::Following(rows) => write!(f, "{} following", rows),
+            FrameBound::UnboundedFollowing => f.write_str("unbounded following"),
+        }
+    }
+}
+
+impl std::fmt::Display for OrderByItem {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.expression)?;
+        if self.is_descending {
+            f.write_str(" desc")?;
+        }
+        match self.nulls_first {
+            Some(true) => f.write_str(" nulls first"),
+            Some(false) => f.write_str(" nulls last"),
+            None => Ok(()),
+        }
+    }
 }
 
 pub trait Parsable: Sized {
@@ -120,237 +855,1309 @@ fn expect_token(
     }
 }
 
-impl Parsable for InsertStatement {
-    /// insert
-    /// into
-    /// $table_name
-    /// values
-    /// (
-    /// [...$value]
-    /// )
-    fn from_tokens(tokens: &[Token]) -> Result<Option<Self>, ParseError> {
-        let mut tokens = tokens.iter();
+struct TokenStream<'a> {
+    tokens: &'a [Token],
+    position: usize,
+}
 
-        if tokens.next().unwrap().kind != TokenKind::Keyword(KeywordType::Insert) {
-            return Ok(None);
+impl<'a> TokenStream<'a> {
+    fn new(tokens: &'a [Token]) -> Self {
+        TokenStream {
+            tokens,
+            position: 0,
         }
+    }
 
-        expect_token(
-            tokens.next(),
-            TokenKind::Keyword(KeywordType::Into),
-            ErrorKind::MissingIntoKeyword,
-        )?;
+    fn peek(&self) -> Option<&'a Token> {
+        self.tokens.get(self.position)
+    }
 
-        let table_name_token = tokens.next();
-        if table_name_token.is_none() {
-            return Err(ParseError {
-                token: Token::empty_token(),
-                error_kind: ErrorKind::MissingTableName,
-            });
+    fn peek_nth(&self, n: usize) -> Option<&'a Token> {
+        self.tokens.get(self.position + n)
+    }
+
+    fn next_token(&mut self) -> Option<&'a Token> {
+        let token = self.tokens.get(self.position);
+        if token.is_some() {
+            self.position += 1;
         }
+        token
+    }
 
-        let table_name_token = table_name_token.unwrap();
+    fn is_next(&self, kind: &TokenKind) -> bool {
+        self.peek().is_some_and(|token| &token.kind == kind)
+    }
 
-        expect_token(
-            tokens.next(),
-            TokenKind::Keyword(KeywordType::Values),
-            ErrorKind::MissingValuesKeyword,
-        )?;
+    // consumes the next token only if it is of the given kind.
+    fn consume(&mut self, kind: TokenKind) -> bool {
+        if self.is_next(&kind) {
+            self.position += 1;
+            true
+        } else {
+            false
+        }
+    }
 
-        let mut values = Vec::<Token>::new();
+    fn expect(&mut self, kind: TokenKind, error_kind: ErrorKind) -> Result<&'a Token, ParseError> {
+        let token = self.next_token();
+        expect_token(token, kind, error_kind)?;
+        Ok(token.unwrap())
+    }
 
-        let mut found_left_paren = false;
+    fn expect_identifier(&mut self, error_kind: ErrorKind) -> Result<Token, ParseError> {
+        Ok(self.expect(TokenKind::Identifier, error_kind)?.clone())
+    }
 
-        loop {
-            let token = tokens.next();
-            if token.is_none() {
-                return Err(ParseError {
-                    token: Token::empty_token(),
-                    error_kind: ErrorKind::MissingLeftParen,
-                });
-            }
+    fn error(&self, error_kind: ErrorKind) -> ParseError {
+        ParseError {
+            token: self.peek().cloned().unwrap_or_else(Token::empty_token),
+            error_kind,
+        }
+    }
 
-            let token = token.unwrap();
+    // a statement can only be followed by a semicolon.
+    fn expect_end(&mut self) -> Result<(), ParseError> {
+        self.consume(TokenKind::Symbol(SymbolType::Semicolon));
+        match self.peek() {
+            Some(_) => Err(self.error(ErrorKind::UnexpectedToken)),
+            None => Ok(()),
+        }
+    }
 
-            if !found_left_paren {
-                expect_token(
-                    Some(token),
-                    TokenKind::Symbol(SymbolType::LeftParen),
-                    ErrorKind::MissingLeftParen,
-                )?;
-                found_left_paren = true;
-                continue;
-            }
+    // the tokens that are left, they count as consumed.
+    fn take_rest(&mut self) -> &'a [Token] {
+        let rest = &self.tokens[self.position..];
+        self.position = self.tokens.len();
+        rest
+    }
 
-            match token.kind {
-                TokenKind::Identifier | TokenKind::Numeric => values.push(token.clone()),
-                TokenKind::Symbol(SymbolType::Comma) => continue,
-                _ => {
-                    expect_token(
-                        Some(token),
-                        TokenKind::Symbol(SymbolType::RightParen),
-                        ErrorKind::MissingRightParens,
-                    )?;
-                    break;
-                }
-            }
+    // parses a comma separated list of names inside parens, e.g. `(a, b)`.
+    fn parse_name_list(&mut self) -> Result<Vec<Token>, ParseError> {
+        self.expect(
+            TokenKind::Symbol(SymbolType::LeftParen),
+            ErrorKind::MissingLeftParen,
+        )?;
+        let mut names = vec![self.expect_identifier(ErrorKind::ExpectedColumnName)?];
+        while self.consume(TokenKind::Symbol(SymbolType::Comma)) {
+            names.push(self.expect_identifier(ErrorKind::ExpectedColumnName)?);
         }
+        self.expect(
+            TokenKind::Symbol(SymbolType::RightParen),
+            ErrorKind::MissingRightParens,
+        )?;
+        Ok(names)
+    }
+}
 
-        Ok(Some(InsertStatement {
-            table: table_name_token.clone(),
-            values,
-        }))
+// runs `parse` if the tokens start with `keyword` and makes sure nothing is left after the statement.
+fn parse_statement<T>(
+    tokens: &[Token],
+    keyword: KeywordType,
+    parse: fn(&mut TokenStream) -> Result<T, ParseError>,
+) -> Result<Option<T>, ParseError> {
+    let mut tokens = TokenStream::new(tokens);
+    if !tokens.is_next(&TokenKind::Keyword(keyword)) {
+        return Ok(None);
     }
+    let statement = parse(&mut tokens)?;
+    tokens.expect_end()?;
+    Ok(Some(statement))
 }
 
-impl Parsable for SelectStatement {
-    // select
-    // [...$value [ as $name ] ]
-    // from
-    // $table_name
-    //
+impl Parsable for Statement {
     fn from_tokens(tokens: &[Token]) -> Result<Option<Self>, ParseError> {
-        let mut tokens = tokens.iter();
-
-        if tokens.next().unwrap().kind != TokenKind::Keyword(KeywordType::Select) {
-            return Ok(None);
+        if let Some(stmt) = SelectStatement::from_tokens(tokens)? {
+            return Ok(Some(Statement::Select(Box::new(stmt))));
+        }
+        if let Some(stmt) = InsertStatement::from_tokens(tokens)? {
+            return Ok(Some(Statement::Insert(stmt)));
+        }
+        if let Some(stmt) = CreateViewStatement::from_tokens(tokens)? {
+            return Ok(Some(Statement::CreateView(stmt)));
+        }
+        if let Some(stmt) = CreateStatement::from_tokens(tokens)? {
+            return Ok(Some(Statement::Create(stmt)));
+        }
+        if let Some(stmt) = DropStatement::from_tokens(tokens)? {
+            return Ok(Some(Statement::Drop(stmt)));
+        }
+        if let Some(stmt) = UpdateStatement::from_tokens(tokens)? {
+            return Ok(Some(Statement::Update(stmt)));
+        }
+        if let Some(stmt) = DeleteStatement::from_tokens(tokens)? {
+            return Ok(Some(Statement::Delete(stmt)));
+        }
+        if let Some(stmt) = ExplainStatement::from_tokens(tokens)? {
+            return Ok(Some(Statement::Explain(Box::new(stmt))));
         }
+        if let Some(stmt) = PrepareStatement::from_tokens(tokens)? {
+            return Ok(Some(Statement::Prepare(stmt)));
+        }
+        if let Some(stmt) = ExecuteStatement::from_tokens(tokens)? {
+            return Ok(Some(Statement::Execute(stmt)));
+        }
+        if let Some(stmt) = DeallocateStatement::from_tokens(tokens)? {
+            return Ok(Some(Statement::Deallocate(stmt)));
+        }
+        if let Some(stmt) = SetStatement::from_tokens(tokens)? {
+            return Ok(Some(Statement::Set(stmt)));
+        }
+        Ok(None)
+    }
+}
+
+impl Expression {
+    fn parse(tokens: &mut TokenStream) -> Result<Self, ParseError> {
+        Self::parse_binary(tokens, 0)
+    }
 
-        let mut select_items: Vec<SelectItem> = vec![];
-        let mut is_comma = false;
+    // precedence climbing, only operators binding tighter than `min_precedence` are consumed.
+    fn parse_binary(tokens: &mut TokenStream, min_precedence: u8) -> Result<Self, ParseError> {
+        let mut left = Self::parse_prefix(tokens)?;
 
         loop {
-            if let Some(token) = tokens.next() {
-                match token.kind {
-                    TokenKind::Identifier | TokenKind::Numeric => {
-                        if !select_items.is_empty() && !is_comma {
-                            return Err(ParseError {
-                                token: token.clone(),
-                                error_kind: ErrorKind::ExpectedComma,
-                            });
-                        }
-                        select_items.push(SelectItem {
-                            name: token.clone(),
-                            as_name: None,
-                        });
-                        is_comma = false;
+            // the predicates that can be negated with a `not` before their keyword.
+            let negation = usize::from(tokens.is_next(&TokenKind::Keyword(KeywordType::Not)));
+            if IN_PRECEDENCE > min_precedence {
+                match tokens.peek_nth(negation).map(|token| &token.kind) {
+                    Some(TokenKind::Keyword(KeywordType::In)) => {
+                        left = Self::parse_in(tokens, left)?;
+                        continue;
                     }
-                    TokenKind::Keyword(KeywordType::As) => {
-                        if let Some(last_item) = select_items.last_mut() {
-                            if let Some(as_name_token) = tokens.next() {
-                                last_item.as_name = Some(as_name_token.clone());
-                            } else {
-                                return Err(ParseError {
-                                    token: Token::empty_token(),
-                                    error_kind: ErrorKind::ExpectedNameAfterAs,
-                                });
-                            }
-                        } else {
-                            return Err(ParseError {
-                                token: token.clone(),
-                                error_kind: ErrorKind::UnexpectedAsKeyword,
-                            });
-                        }
+                    Some(TokenKind::Keyword(KeywordType::Like | KeywordType::Ilike | KeywordType::Similar)) => {
+                        left = Self::parse_like(tokens, left)?;
+                        continue;
                     }
-                    TokenKind::Symbol(SymbolType::Comma) => is_comma = true,
-                    _ => {
-                        expect_token(
-                            Some(token),
-                            TokenKind::Keyword(KeywordType::From),
-                            ErrorKind::MissingTableName,
-                        )?;
-                        break;
+                    Some(TokenKind::Keyword(KeywordType::Between)) => {
+                        left = Self::parse_between(tokens, left)?;
+                        continue;
+                    }
+                    _ => {}
+                }
+                if tokens.is_next(&TokenKind::Keyword(KeywordType::Is)) {
+                    left = Self::parse_is(tokens, left)?;
+                    continue;
+                }
+            }
+
+            let operator = match tokens.peek().and_then(BinaryOperator::from_token) {
+                Some(operator) if operator.precedence() > min_precedence => operator,
+                _ => break,
+            };
+            tokens.next_token();
+
+            let right = Self::parse_binary(tokens, operator.precedence())?;
+            left = Expression::Binary {
+                left: Box::new(left),
+                operator,
+                right: Box::new(right),
+            };
+        }
+
+        Ok(left)
+    }
+
+    fn parse_prefix(tokens: &mut TokenStream) -> Result<Self, ParseError> {
+        if tokens.consume(TokenKind::Keyword(KeywordType::Not)) {
+            let operand = Self::parse_binary(tokens, NOT_PRECEDENCE)?;
+            return Ok(Expression::Unary {
+                operator: UnaryOperator::Not,
+                operand: Box::new(operand),
+            });
+        }
+        if tokens.consume(TokenKind::Symbol(SymbolType::Minus)) {
+            let operand = Self::parse_binary(tokens, UNARY_MINUS_PRECEDENCE)?;
+            return Ok(Expression::Unary {
+                operator: UnaryOperator::Minus,
+                operand: Box::new(operand),
+            });
+        }
+        Self::parse_postfix(tokens)
+    }
+
+    // $primary [...:: $type]
+    fn parse_postfix(tokens: &mut TokenStream) -> Result<Self, ParseError> {
+        let mut expression = Self::parse_primary(tokens)?;
+        while tokens.consume(TokenKind::Symbol(SymbolType::DoubleColon)) {
+            expression = Expression::Cast {
+                expression: Box::new(expression),
+                data_type: parse_data_type(tokens)?,
+            };
+        }
+        Ok(expression)
+    }
+
+    fn parse_primary(tokens: &mut TokenStream) -> Result<Self, ParseError> {
+        let token = match tokens.peek() {
+            Some(token) => token,
+            None => return Err(tokens.error(ErrorKind::ExpectedExpression)),
+        };
+
+        match token.kind {
+            TokenKind::Numeric | TokenKind::String | TokenKind::Null => {
+                tokens.next_token();
+                Ok(Expression::Literal(token.clone()))
+            }
+            TokenKind::Parameter => match token.value[1..].parse::<usize>() {
+                Ok(index) if index > 0 => {
+                    tokens.next_token();
+                    Ok(Expression::Parameter(index))
+                }
+                _ => Err(tokens.error(ErrorKind::InvalidParameter)),
+            },
+            TokenKind::Identifier => {
+                let is_function = tokens
+                    .peek_nth(1)
+                    .is_some_and(|token| token.kind == TokenKind::Symbol(SymbolType::LeftParen));
+                if is_function {
+                    let function = FunctionCall::parse(tokens)?;
+                    if tokens.consume(TokenKind::Keyword(KeywordType::Over)) {
+                        return Ok(Expression::Window {
+                            function,
+                            window: WindowSpec::parse(tokens)?,
+                        });
                     }
+                    return Ok(Expression::Function(function));
+                }
+                tokens.next_token();
+                if tokens.consume(TokenKind::Symbol(SymbolType::Dot)) {
+                    let name = tokens.expect_identifier(ErrorKind::ExpectedColumnName)?;
+                    return Ok(Expression::Column {
+                        table: Some(token.clone()),
+                        name,
+                    });
+                }
+                Ok(Expression::Column {
+                    table: None,
+                    name: token.clone(),
+                })
+            }
+            TokenKind::Symbol(SymbolType::LeftParen) => {
+                if tokens.peek_nth(1).is_some_and(is_query_start) {
+                    return Ok(Expression::Subquery(Box::new(parse_subquery(tokens)?)));
+                }
+                tokens.next_token();
+                let expression = Self::parse(tokens)?;
+                tokens.expect(
+                    TokenKind::Symbol(SymbolType::RightParen),
+                    ErrorKind::MissingRightParens,
+                )?;
+                Ok(expression)
+            }
+            TokenKind::Keyword(KeywordType::Exists) => {
+                tokens.next_token();
+                Ok(Expression::Exists(Box::new(parse_subquery(tokens)?)))
+            }
+            TokenKind::Keyword(KeywordType::Case) => Self::parse_case(tokens),
+            TokenKind::Keyword(KeywordType::Cast) => Self::parse_cast(tokens),
+            _ => Err(tokens.error(ErrorKind::ExpectedExpression)),
+        }
+    }
+
+    // case [ $expression ] [...when $expression then $expression] [ else $expression ] end
+    fn parse_case(tokens: &mut TokenStream) -> Result<Self, ParseError> {
+        tokens.next_token();
+
+        let operand = if tokens.is_next(&TokenKind::Keyword(KeywordType::When)) {
+            None
+        } else {
+            Some(Box::new(Self::parse(tokens)?))
+        };
+
+        let mut branches = vec![];
+        while tokens.consume(TokenKind::Keyword(KeywordType::When)) {
+            let condition = Self::parse(tokens)?;
+            tokens.expect(
+                TokenKind::Keyword(KeywordType::Then),
+                ErrorKind::ExpectedThenKeyword,
+            )?;
+            branches.push(WhenClause {
+                condition,
+                result: Self::parse(tokens)?,
+            });
+        }
+        if branches.is_empty() {
+            return Err(tokens.error(ErrorKind::ExpectedWhenKeyword));
+        }
+
+        let else_result = if tokens.consume(TokenKind::Keyword(KeywordType::Else)) {
+            Some(Box::new(Self::parse(tokens)?))
+        } else {
+            None
+        };
+        tokens.expect(
+            TokenKind::Keyword(KeywordType::End),
+            ErrorKind::ExpectedEndKeyword,
+        )?;
+
+        Ok(Expression::Case {
+            operand,
+            branches,
+            else_result,
+        })
+    }
+
+    // cast ( $expression as $type )
+    fn parse_cast(tokens: &mut TokenStream) -> Result<Self, ParseError> {
+        tokens.next_token();
+        tokens.expect(
+            TokenKind::Symbol(SymbolType::LeftParen),
+            ErrorKind::MissingLeftParen,
+        )?;
+        let expression = Self::parse(tokens)?;
+        tokens.expect(TokenKind::Keyword(KeywordType::As), ErrorKind::ExpectedAsKeyword)?;
+        let data_type = parse_data_type(tokens)?;
+        tokens.expect(
+            TokenKind::Symbol(SymbolType::RightParen),
+            ErrorKind::MissingRightParens,
+        )?;
+        Ok(Expression::Cast {
+            expression: Box::new(expression),
+            data_type,
+        })
+    }
+
+    // $expression [ not ] like | ilike | similar to $expression [ escape $expression ]
+    fn parse_like(tokens: &mut TokenStream, expression: Expression) -> Result<Self, ParseError> {
+        let is_negated = tokens.consume(TokenKind::Keyword(KeywordType::Not));
+        let operator = match tokens.next_token().map(|token| &token.kind) {
+            Some(TokenKind::Keyword(KeywordType::Like)) => LikeOperator::Like,
+            Some(TokenKind::Keyword(KeywordType::Ilike)) => LikeOperator::ILike,
+            _ => {
+                tokens.expect(TokenKind::Keyword(KeywordType::To), ErrorKind::ExpectedToKeyword)?;
+                LikeOperator::SimilarTo
+            }
+        };
+        let pattern = Self::parse_binary(tokens, IN_PRECEDENCE)?;
+        let escape = if tokens.consume(TokenKind::Keyword(KeywordType::Escape)) {
+            Some(Box::new(Self::parse_binary(tokens, IN_PRECEDENCE)?))
+        } else {
+            None
+        };
+        Ok(Expression::Like {
+            expression: Box::new(expression),
+            operator,
+            pattern: Box::new(pattern),
+            escape,
+            is_negated,
+        })
+    }
+
+    // $expression [ not ] in ( $select | [...$expression] )
+    fn parse_in(tokens: &mut TokenStream, expression: Expression) -> Result<Self, ParseError> {
+        let is_negated = tokens.consume(TokenKind::Keyword(KeywordType::Not));
+        tokens.next_token();
+        if tokens.peek_nth(1).is_some_and(is_query_start) {
+            return Ok(Expression::InSubquery {
+                expression: Box::new(expression),
+                subquery: Box::new(parse_subquery(tokens)?),
+                is_negated,
+            });
+        }
+
+        tokens.expect(
+            TokenKind::Symbol(SymbolType::LeftParen),
+            ErrorKind::MissingLeftParen,
+        )?;
+        let mut list = vec![Self::parse(tokens)?];
+        while tokens.consume(TokenKind::Symbol(SymbolType::Comma)) {
+            list.push(Self::parse(tokens)?);
+        }
+        tokens.expect(
+            TokenKind::Symbol(SymbolType::RightParen),
+            ErrorKind::MissingRightParens,
+        )?;
+        Ok(Expression::InList {
+            expression: Box::new(expression),
+            list,
+            is_negated,
+        })
+    }
+
+    // $expression [ not ] between $expression and $expression
+    fn parse_between(tokens: &mut TokenStream, expression: Expression) -> Result<Self, ParseError> {
+        let is_negated = tokens.consume(TokenKind::Keyword(KeywordType::Not));
+        tokens.next_token();
+        // the bounds bind tighter than `and`, which separates them.
+        let low = Self::parse_binary(tokens, IN_PRECEDENCE)?;
+        tokens.expect(TokenKind::Keyword(KeywordType::And), ErrorKind::ExpectedAndKeyword)?;
+        let high = Self::parse_binary(tokens, IN_PRECEDENCE)?;
+        Ok(Expression::Between {
+            expression: Box::new(expression),
+            low: Box::new(low),
+            high: Box::new(high),
+            is_negated,
+        })
+    }
+
+    // $expression is [ not ] null | $expression is [ not ] distinct from $expression
+    fn parse_is(tokens: &mut TokenStream, expression: Expression) -> Result<Self, ParseError> {
+        tokens.next_token();
+        let is_negated = tokens.consume(TokenKind::Keyword(KeywordType::Not));
+        if tokens.consume(TokenKind::Null) {
+            return Ok(Expression::IsNull {
+                expression: Box::new(expression),
+                is_negated,
+            });
+        }
+        tokens.expect(
+            TokenKind::Keyword(KeywordType::Distinct),
+            ErrorKind::ExpectedNullOrDistinct,
+        )?;
+        tokens.expect(TokenKind::Keyword(KeywordType::From), ErrorKind::ExpectedFromKeyword)?;
+        Ok(Expression::IsDistinctFrom {
+            left: Box::new(expression),
+            right: Box::new(Self::parse_binary(tokens, IN_PRECEDENCE)?),
+            is_negated,
+        })
+    }
+}
+
+fn is_data_type(kind: &TokenKind) -> bool {
+    matches!(
+        kind,
+        TokenKind::Keyword(KeywordType::Int)
+            | TokenKind::Keyword(KeywordType::Float)
+            | TokenKind::Keyword(KeywordType::Text)
+            | TokenKind::Keyword(KeywordType::Bool)
+    )
+}
+
+// int | float | text | bool
+fn parse_data_type(tokens: &mut TokenStream) -> Result<Token, ParseError> {
+    match tokens.peek() {
+        Some(token) if is_data_type(&token.kind) => {
+            tokens.next_token();
+            Ok(token.clone())
+        }
+        _ => Err(tokens.error(ErrorKind::ExpectedType)),
+    }
+}
+
+// ( $select )
+fn parse_subquery(tokens: &mut TokenStream) -> Result<SelectStatement, ParseError> {
+    tokens.expect(
+        TokenKind::Symbol(SymbolType::LeftParen),
+        ErrorKind::MissingLeftParen,
+    )?;
+    if !tokens.peek().is_some_and(is_query_start) {
+        return Err(tokens.error(ErrorKind::ExpectedSelect));
+    }
+    let query = SelectStatement::parse(tokens)?;
+    tokens.expect(
+        TokenKind::Symbol(SymbolType::RightParen),
+        ErrorKind::MissingRightParens,
+    )?;
+    Ok(query)
+}
+
+impl FunctionCall {
+    // $name ( [ * | [ distinct ] [...$expression] ] )
+    fn parse(tokens: &mut TokenStream) -> Result<Self, ParseError> {
+        let name = tokens.expect_identifier(ErrorKind::ExpectedExpression)?;
+        tokens.expect(
+            TokenKind::Symbol(SymbolType::LeftParen),
+            ErrorKind::MissingLeftParen,
+        )?;
+
+        let mut call = FunctionCall {
+            name,
+            args: vec![],
+            is_distinct: false,
+            is_star: false,
+        };
+        if tokens.consume(TokenKind::Symbol(SymbolType::Star)) {
+            call.is_star = true;
+        } else if !tokens.is_next(&TokenKind::Symbol(SymbolType::RightParen)) {
+            call.is_distinct = tokens.consume(TokenKind::Keyword(KeywordType::Distinct));
+            call.args.push(Expression::parse(tokens)?);
+            while tokens.consume(TokenKind::Symbol(SymbolType::Comma)) {
+                call.args.push(Expression::parse(tokens)?);
+            }
+        }
+
+        tokens.expect(
+            TokenKind::Symbol(SymbolType::RightParen),
+            ErrorKind::MissingRightParens,
+        )?;
+        Ok(call)
+    }
+}
+
+impl WindowSpec {
+    // ( [ partition by [...$expression] ] [ $order_by ] [ $frame ] )
+    fn parse(tokens: &mut TokenStream) -> Result<Self, ParseError> {
+        tokens.expect(
+            TokenKind::Symbol(SymbolType::LeftParen),
+            ErrorKind::MissingLeftParen,
+        )?;
+
+        let mut partition_by = vec![];
+        if tokens.consume(TokenKind::Keyword(KeywordType::Partition)) {
+            tokens.expect(
+                TokenKind::Keyword(KeywordType::By),
+                ErrorKind::ExpectedByKeyword,
+            )?;
+            partition_by.push(Expression::parse(tokens)?);
+            while tokens.consume(TokenKind::Symbol(SymbolType::Comma)) {
+                partition_by.push(Expression::parse(tokens)?);
+            }
+        }
+        let order_by = parse_order_by(tokens)?;
+        let frame = if tokens.consume(TokenKind::Keyword(KeywordType::Rows)) {
+            Some(WindowFrame::parse(tokens)?)
+        } else {
+            None
+        };
+
+        tokens.expect(
+            TokenKind::Symbol(SymbolType::RightParen),
+            ErrorKind::MissingRightParens,
+        )?;
+        Ok(WindowSpec {
+            partition_by,
+            order_by,
+            frame,
+        })
+    }
+}
+
+impl WindowFrame {
+    // rows { $start | between $start and $end }, a frame without an end ends at the current row.
+    fn parse(tokens: &mut TokenStream) -> Result<Self, ParseError> {
+        let (start, end) = if tokens.consume(TokenKind::Keyword(KeywordType::Between)) {
+            let start = FrameBound::parse(tokens)?;
+            tokens.expect(TokenKind::Keyword(KeywordType::And), ErrorKind::ExpectedAndKeyword)?;
+            (start, FrameBound::parse(tokens)?)
+        } else {
+            (FrameBound::parse(tokens)?, FrameBound::CurrentRow)
+        };
+        if start == FrameBound::UnboundedFollowing || end == FrameBound::UnboundedPreceding {
+            return Err(tokens.error(ErrorKind::InvalidFrameBound));
+        }
+        Ok(WindowFrame { start, end })
+    }
+}
+
+impl FrameBound {
+    // unbounded preceding | unbounded following | current row | $expression preceding | $expression following
+    fn parse(tokens: &mut TokenStream) -> Result<Self, ParseError> {
+        if tokens.consume(TokenKind::Keyword(KeywordType::Unbounded)) {
+            if tokens.consume(TokenKind::Keyword(KeywordType::Preceding)) {
+                return Ok(FrameBound::UnboundedPreceding);
+            }
+            tokens.expect(
+                TokenKind::Keyword(KeywordType::Following),
+                ErrorKind::ExpectedFrameBound,
+            )?;
+            return Ok(FrameBound::UnboundedFollowing);
+        }
+        if tokens.consume(TokenKind::Keyword(KeywordType::Current)) {
+            tokens.expect(TokenKind::Keyword(KeywordType::Row), ErrorKind::ExpectedFrameBound)?;
+            return Ok(FrameBound::CurrentRow);
+        }
+
+        let rows = Box::new(Expression::parse_binary(tokens, IN_PRECEDENCE)?);
+        if tokens.consume(TokenKind::Keyword(KeywordType::Preceding)) {
+            return Ok(FrameBound::Preceding(rows));
+        }
+        tokens.expect(
+            TokenKind::Keyword(KeywordType::Following),
+            ErrorKind::ExpectedFrameBound,
+        )?;
+        Ok(FrameBound::Following(rows))
+    }
+}
+
+// where $expression
+fn parse_where_clause(tokens: &mut TokenStream) -> Result<Option<Expression>, ParseError> {
+    if tokens.consume(TokenKind::Keyword(KeywordType::Where)) {
+        Ok(Some(Expression::parse(tokens)?))
+    } else {
+        Ok(None)
+    }
+}
+
+impl Parsable for InsertStatement {
+    fn from_tokens(tokens: &[Token]) -> Result<Option<Self>, ParseError> {
+        parse_statement(tokens, KeywordType::Insert, Self::parse)
+    }
+}
+
+impl InsertStatement {
+    /// insert
+    /// into
+    /// $table_name
+    /// [ ( [...$name] ) ]
+    /// values ( [...$expression] ) | $select
+    /// [ $on_conflict ]
+    /// [ $returning ]
+    fn parse(tokens: &mut TokenStream) -> Result<Self, ParseError> {
+        tokens.next_token();
+
+        tokens.expect(
+            TokenKind::Keyword(KeywordType::Into),
+            ErrorKind::MissingIntoKeyword,
+        )?;
+
+        let table = tokens.expect_identifier(ErrorKind::MissingTableName)?;
+
+        let columns = if tokens.is_next(&TokenKind::Symbol(SymbolType::LeftParen)) {
+            tokens.parse_name_list()?
+        } else {
+            vec![]
+        };
+
+        let source = InsertSource::parse(tokens)?;
+        let on_conflict = if tokens.consume(TokenKind::Keyword(KeywordType::On)) {
+            Some(OnConflict::parse(tokens)?)
+        } else {
+            None
+        };
+        let returning = Returning::parse(tokens)?;
+
+        Ok(InsertStatement {
+            table,
+            columns,
+            source,
+            on_conflict,
+            returning,
+        })
+    }
+}
+
+impl InsertSource {
+    fn parse(tokens: &mut TokenStream) -> Result<Self, ParseError> {
+        // a values query could start the same way, the single row of `values (...)` is read first.
+        if !tokens.consume(TokenKind::Keyword(KeywordType::Values)) {
+            if tokens.peek().is_some_and(is_query_start) {
+                return Ok(InsertSource::Query(Box::new(SelectStatement::parse(tokens)?)));
+            }
+            return Err(tokens.error(ErrorKind::MissingValuesKeyword));
+        }
+
+        tokens.expect(
+            TokenKind::Symbol(SymbolType::LeftParen),
+            ErrorKind::MissingLeftParen,
+        )?;
+
+        let mut values = vec![Expression::parse(tokens)?];
+        while tokens.consume(TokenKind::Symbol(SymbolType::Comma)) {
+            values.push(Expression::parse(tokens)?);
+        }
+
+        tokens.expect(
+            TokenKind::Symbol(SymbolType::RightParen),
+            ErrorKind::MissingRightParens,
+        )?;
+
+        Ok(InsertSource::Values(values))
+    }
+}
+
+impl OnConflict {
+    // conflict [ ( [...$name] ) ] do { nothing | update set [...$name = $expression] [ where $expression ] }
+    fn parse(tokens: &mut TokenStream) -> Result<Self, ParseError> {
+        tokens.expect(
+            TokenKind::Keyword(KeywordType::Conflict),
+            ErrorKind::ExpectedConflictKeyword,
+        )?;
+        let columns = if tokens.is_next(&TokenKind::Symbol(SymbolType::LeftParen)) {
+            tokens.parse_name_list()?
+        } else {
+            vec![]
+        };
+        tokens.expect(TokenKind::Keyword(KeywordType::Do), ErrorKind::ExpectedDoKeyword)?;
+
+        if tokens.consume(TokenKind::Keyword(KeywordType::Nothing)) {
+            return Ok(OnConflict {
+                columns,
+                action: ConflictAction::Nothing,
+            });
+        }
+        tokens.expect(
+            TokenKind::Keyword(KeywordType::Update),
+            ErrorKind::ExpectedNothingOrUpdate,
+        )?;
+        // an update needs to know which key's row it updates.
+        if columns.is_empty() {
+            return Err(tokens.error(ErrorKind::MissingConflictTarget));
+        }
+        tokens.expect(
+            TokenKind::Keyword(KeywordType::Set),
+            ErrorKind::MissingSetKeyword,
+        )?;
+        let mut assignments = vec![Assignment::parse(tokens)?];
+        while tokens.consume(TokenKind::Symbol(SymbolType::Comma)) {
+            assignments.push(Assignment::parse(tokens)?);
+        }
+        let where_clause = parse_where_clause(tokens)?;
+
+        Ok(OnConflict {
+            columns,
+            action: ConflictAction::Update {
+                assignments,
+                where_clause,
+            },
+        })
+    }
+}
+
+impl Parsable for SelectStatement {
+    fn from_tokens(tokens: &[Token]) -> Result<Option<Self>, ParseError> {
+        if let Some(statement) = parse_statement(tokens, KeywordType::With, Self::parse)? {
+            return Ok(Some(statement));
+        }
+        if let Some(statement) = parse_statement(tokens, KeywordType::Values, Self::parse)? {
+            return Ok(Some(statement));
+        }
+        parse_statement(tokens, KeywordType::Select, Self::parse)
+    }
+}
+
+// whether a query starts at `token`, e.g. after a left paren.
+fn is_query_start(token: &Token) -> bool {
+    token.kind == TokenKind::Keyword(KeywordType::Select)
+        || token.kind == TokenKind::Keyword(KeywordType::With)
+        || token.kind == TokenKind::Keyword(KeywordType::Values)
+}
+
+impl SelectStatement {
+    // [ $with_clause ]
+    // $intersection [...{ union | except } [ all ] $intersection ]
+    // [ order by [...$expression [ asc | desc ] [ nulls first | nulls last ] ] ]
+    // [ limit { $expression | all } ] [ offset $expression [ row | rows ] ]
+    // [ fetch { first | next } [ $expression ] { row | rows } only ]
+    fn parse(tokens: &mut TokenStream) -> Result<Self, ParseError> {
+        let with = CommonTableExpression::parse_with_clause(tokens)?;
+
+        let mut statement = Self::parse_intersection(tokens)?;
+        loop {
+            let operator = match tokens.peek().map(|token| &token.kind) {
+                Some(TokenKind::Keyword(KeywordType::Union)) => SetOperator::Union,
+                Some(TokenKind::Keyword(KeywordType::Except)) => SetOperator::Except,
+                _ => break,
+            };
+            tokens.next_token();
+            let is_all = tokens.consume(TokenKind::Keyword(KeywordType::All));
+            statement.set_operations.push(SetOperation {
+                operator,
+                is_all,
+                query: Self::parse_intersection(tokens)?,
+            });
+        }
+
+        statement.with = with;
+        statement.order_by = parse_order_by(tokens)?;
+        (statement.limit, statement.offset) = parse_limit_and_offset(tokens)?;
+        Ok(statement)
+    }
+
+    // intersect binds tighter than union and except, so its operands are grouped first.
+    //
+    // $select_core [...intersect [ all ] $select_core ]
+    fn parse_intersection(tokens: &mut TokenStream) -> Result<Self, ParseError> {
+        let mut statement = Self::parse_core(tokens)?;
+        while tokens.consume(TokenKind::Keyword(KeywordType::Intersect)) {
+            let is_all = tokens.consume(TokenKind::Keyword(KeywordType::All));
+            statement.set_operations.push(SetOperation {
+                operator: SetOperator::Intersect,
+                is_all,
+                query: Self::parse_core(tokens)?,
+            });
+        }
+        Ok(statement)
+    }
+
+    // $values |
+    // select
+    // [ all | distinct [ on ( [...$expression] ) ] ]
+    // [...$expression [ as $name ] ]
+    // [ from [...$table_reference] ]
+    // [ where $expression ]
+    // [ group by [...$expression] ]
+    // [ having $expression ]
+    fn parse_core(tokens: &mut TokenStream) -> Result<Self, ParseError> {
+        if tokens.is_next(&TokenKind::Keyword(KeywordType::Values)) {
+            return Self::parse_values(tokens);
+        }
+        tokens.expect(
+            TokenKind::Keyword(KeywordType::Select),
+            ErrorKind::ExpectedSelect,
+        )?;
+
+        let mut distinct = None;
+        if tokens.consume(TokenKind::Keyword(KeywordType::Distinct)) {
+            distinct = Some(Distinct::Row);
+            if tokens.consume(TokenKind::Keyword(KeywordType::On)) {
+                tokens.expect(
+                    TokenKind::Symbol(SymbolType::LeftParen),
+                    ErrorKind::MissingLeftParen,
+                )?;
+                let mut expressions = vec![Expression::parse(tokens)?];
+                while tokens.consume(TokenKind::Symbol(SymbolType::Comma)) {
+                    expressions.push(Expression::parse(tokens)?);
                 }
+                tokens.expect(
+                    TokenKind::Symbol(SymbolType::RightParen),
+                    ErrorKind::MissingRightParens,
+                )?;
+                distinct = Some(Distinct::On(expressions));
+            }
+        } else {
+            tokens.consume(TokenKind::Keyword(KeywordType::All));
+        }
+
+        let mut select_items = vec![SelectItem::parse(tokens)?];
+        loop {
+            match tokens.peek().map(|token| &token.kind) {
+                Some(TokenKind::Symbol(SymbolType::Comma)) => {
+                    tokens.next_token();
+                    select_items.push(SelectItem::parse(tokens)?);
+                }
+                Some(TokenKind::Identifier) | Some(TokenKind::Numeric) => {
+                    return Err(tokens.error(ErrorKind::ExpectedComma));
+                }
+                _ => break,
+            }
+        }
+
+        let from = if tokens.consume(TokenKind::Keyword(KeywordType::From)) {
+            // a comma between tables is a cross join.
+            let mut from = TableReference::parse(tokens)?;
+            while tokens.consume(TokenKind::Symbol(SymbolType::Comma)) {
+                from = TableReference::Join {
+                    left: Box::new(from),
+                    right: Box::new(TableReference::parse(tokens)?),
+                    kind: JoinKind::Cross,
+                    constraint: JoinConstraint::None,
+                };
+            }
+            Some(from)
+        } else {
+            None
+        };
+
+        let where_clause = parse_where_clause(tokens)?;
+
+        let mut group_by = vec![];
+        if tokens.consume(TokenKind::Keyword(KeywordType::Group)) {
+            tokens.expect(
+                TokenKind::Keyword(KeywordType::By),
+                ErrorKind::ExpectedByKeyword,
+            )?;
+            group_by.push(Expression::parse(tokens)?);
+            while tokens.consume(TokenKind::Symbol(SymbolType::Comma)) {
+                group_by.push(Expression::parse(tokens)?);
+            }
+        }
+        let having = if tokens.consume(TokenKind::Keyword(KeywordType::Having)) {
+            Some(Expression::parse(tokens)?)
+        } else {
+            None
+        };
+
+        Ok(SelectStatement {
+            id: QueryId::next(),
+            with: vec![],
+            distinct,
+            items: select_items,
+            from,
+            where_clause,
+            group_by,
+            having,
+            set_operations: vec![],
+            order_by: vec![],
+            limit: None,
+            offset: None,
+        })
+    }
+
+    // a standalone `values` selects the columns column1, column2... of its rows.
+    fn parse_values(tokens: &mut TokenStream) -> Result<Self, ParseError> {
+        let rows = parse_values_rows(tokens)?;
+        let items = (1..=rows[0].len())
+            .map(|number| SelectItem {
+                expression: Expression::Column {
+                    table: None,
+                    name: Token {
+                        value: format!("column{}", number),
+                        kind: TokenKind::Identifier,
+                    },
+                },
+                as_name: None,
+            })
+            .collect();
+
+        Ok(SelectStatement {
+            id: QueryId::next(),
+            with: vec![],
+            distinct: None,
+            items,
+            from: Some(TableReference::Values {
+                rows,
+                alias: None,
+                columns: vec![],
+            }),
+            where_clause: None,
+            group_by: vec![],
+            having: None,
+            set_operations: vec![],
+            order_by: vec![],
+            limit: None,
+            offset: None,
+        })
+    }
+}
+
+// values [...( [...$expression] )], all the rows need the same number of values.
+fn parse_values_rows(tokens: &mut TokenStream) -> Result<Vec<Vec<Expression>>, ParseError> {
+    tokens.expect(
+        TokenKind::Keyword(KeywordType::Values),
+        ErrorKind::MissingValuesKeyword,
+    )?;
+    let mut rows = vec![];
+    loop {
+        tokens.expect(
+            TokenKind::Symbol(SymbolType::LeftParen),
+            ErrorKind::MissingLeftParen,
+        )?;
+        let mut row = vec![Expression::parse(tokens)?];
+        while tokens.consume(TokenKind::Symbol(SymbolType::Comma)) {
+            row.push(Expression::parse(tokens)?);
+        }
+        if rows.first().is_some_and(|first: &Vec<Expression>| first.len() != row.len()) {
+            return Err(tokens.error(ErrorKind::ValuesLengthMismatch));
+        }
+        tokens.expect(
+            TokenKind::Symbol(SymbolType::RightParen),
+            ErrorKind::MissingRightParens,
+        )?;
+        rows.push(row);
+        if !tokens.consume(TokenKind::Symbol(SymbolType::Comma)) {
+            return Ok(rows);
+        }
+    }
+}
+
+impl CommonTableExpression {
+    // with [ recursive ] [...$name [ ( [...$column] ) ] as ( $select )]
+    fn parse_with_clause(tokens: &mut TokenStream) -> Result<Vec<Self>, ParseError> {
+        if !tokens.consume(TokenKind::Keyword(KeywordType::With)) {
+            return Ok(vec![]);
+        }
+        let is_recursive = tokens.consume(TokenKind::Keyword(KeywordType::Recursive));
+
+        let mut common_tables = vec![Self::parse(tokens, is_recursive)?];
+        while tokens.consume(TokenKind::Symbol(SymbolType::Comma)) {
+            common_tables.push(Self::parse(tokens, is_recursive)?);
+        }
+        Ok(common_tables)
+    }
+
+    fn parse(tokens: &mut TokenStream, is_recursive: bool) -> Result<Self, ParseError> {
+        let name = tokens.expect_identifier(ErrorKind::MissingTableName)?;
+        let columns = if tokens.is_next(&TokenKind::Symbol(SymbolType::LeftParen)) {
+            tokens.parse_name_list()?
+        } else {
+            vec![]
+        };
+        tokens.expect(
+            TokenKind::Keyword(KeywordType::As),
+            ErrorKind::ExpectedAsKeyword,
+        )?;
+
+        tokens.expect(
+            TokenKind::Symbol(SymbolType::LeftParen),
+            ErrorKind::MissingLeftParen,
+        )?;
+        let query = SelectStatement::parse(tokens)?;
+        tokens.expect(
+            TokenKind::Symbol(SymbolType::RightParen),
+            ErrorKind::MissingRightParens,
+        )?;
+
+        Ok(CommonTableExpression {
+            name,
+            columns,
+            query,
+            is_recursive,
+        })
+    }
+}
+
+impl TableReference {
+    // $table [...$join_kind join $table [ on $expression | using ( [...$name] ) ]]
+    fn parse(tokens: &mut TokenStream) -> Result<Self, ParseError> {
+        let mut reference = Self::parse_table(tokens)?;
+
+        while let Some(kind) = JoinKind::parse(tokens)? {
+            let right = Self::parse_table(tokens)?;
+            let constraint = if kind == JoinKind::Cross {
+                JoinConstraint::None
+            } else if tokens.consume(TokenKind::Keyword(KeywordType::On)) {
+                JoinConstraint::On(Expression::parse(tokens)?)
+            } else if tokens.consume(TokenKind::Keyword(KeywordType::Using)) {
+                JoinConstraint::Using(tokens.parse_name_list()?)
+            } else {
+                return Err(tokens.error(ErrorKind::ExpectedJoinConstraint));
+            };
+            reference = TableReference::Join {
+                left: Box::new(reference),
+                right: Box::new(right),
+                kind,
+                constraint,
+            };
+        }
+
+        Ok(reference)
+    }
+
+    // $table_name [ [ as ] $alias ] | ( $select ) [ as ] $alias | ( $values ) [ as ] $alias [ ( [...$column] ) ]
+    fn parse_table(tokens: &mut TokenStream) -> Result<Self, ParseError> {
+        let is_values = tokens.peek_nth(1).is_some_and(|token| token.kind == TokenKind::Keyword(KeywordType::Values));
+        if is_values && tokens.is_next(&TokenKind::Symbol(SymbolType::LeftParen)) {
+            tokens.next_token();
+            let rows = parse_values_rows(tokens)?;
+            tokens.expect(
+                TokenKind::Symbol(SymbolType::RightParen),
+                ErrorKind::MissingRightParens,
+            )?;
+            let alias = parse_alias(tokens)?.ok_or_else(|| tokens.error(ErrorKind::ExpectedAlias))?;
+            let columns = if tokens.is_next(&TokenKind::Symbol(SymbolType::LeftParen)) {
+                tokens.parse_name_list()?
+            } else {
+                vec![]
+            };
+            return Ok(TableReference::Values {
+                rows,
+                alias: Some(alias),
+                columns,
+            });
+        }
+        if tokens.is_next(&TokenKind::Symbol(SymbolType::LeftParen)) {
+            let query = Box::new(parse_subquery(tokens)?);
+            return match parse_alias(tokens)? {
+                Some(alias) => Ok(TableReference::Subquery { query, alias }),
+                None => Err(tokens.error(ErrorKind::ExpectedAlias)),
+            };
+        }
+
+        let name = tokens.expect_identifier(ErrorKind::MissingTableName)?;
+        let alias = parse_alias(tokens)?;
+        Ok(TableReference::Table { name, alias })
+    }
+}
+
+// [ [ as ] $alias ]
+fn parse_alias(tokens: &mut TokenStream) -> Result<Option<Token>, ParseError> {
+    if tokens.consume(TokenKind::Keyword(KeywordType::As)) {
+        Ok(Some(tokens.expect_identifier(ErrorKind::ExpectedNameAfterAs)?))
+    } else if tokens.is_next(&TokenKind::Identifier) {
+        Ok(tokens.next_token().cloned())
+    } else {
+        Ok(None)
+    }
+}
+
+impl JoinKind {
+    // [ inner ] join | left [ outer ] join | right [ outer ] join | full [ outer ] join | cross join
+    fn parse(tokens: &mut TokenStream) -> Result<Option<Self>, ParseError> {
+        let kind = match tokens.peek().map(|token| &token.kind) {
+            Some(TokenKind::Keyword(KeywordType::Join)) => {
+                tokens.next_token();
+                return Ok(Some(JoinKind::Inner));
+            }
+            Some(TokenKind::Keyword(KeywordType::Inner)) => JoinKind::Inner,
+            Some(TokenKind::Keyword(KeywordType::Left)) => JoinKind::Left,
+            Some(TokenKind::Keyword(KeywordType::Right)) => JoinKind::Right,
+            Some(TokenKind::Keyword(KeywordType::Full)) => JoinKind::Full,
+            Some(TokenKind::Keyword(KeywordType::Cross)) => JoinKind::Cross,
+            _ => return Ok(None),
+        };
+        tokens.next_token();
+
+        if kind == JoinKind::Left || kind == JoinKind::Right || kind == JoinKind::Full {
+            tokens.consume(TokenKind::Keyword(KeywordType::Outer));
+        }
+        tokens.expect(
+            TokenKind::Keyword(KeywordType::Join),
+            ErrorKind::ExpectedJoinKeyword,
+        )?;
+        Ok(Some(kind))
+    }
+}
+
+impl SelectItem {
+    // $expression [ as $name ]
+    fn parse(tokens: &mut TokenStream) -> Result<Self, ParseError> {
+        if tokens.is_next(&TokenKind::Keyword(KeywordType::As)) {
+            return Err(tokens.error(ErrorKind::UnexpectedAsKeyword));
+        }
+        let expression = Expression::parse(tokens)?;
+        let as_name = if tokens.consume(TokenKind::Keyword(KeywordType::As)) {
+            Some(tokens.expect_identifier(ErrorKind::ExpectedNameAfterAs)?)
+        } else {
+            None
+        };
+        Ok(SelectItem {
+            expression,
+            as_name,
+        })
+    }
+}
+
+// limit and offset can come in either order, fetch first is the standard spelling of limit.
+fn parse_limit_and_offset(
+    tokens: &mut TokenStream,
+) -> Result<(Option<Expression>, Option<Expression>), ParseError> {
+    let mut limit = None;
+    let mut offset = None;
+    let mut found_limit = false;
+    loop {
+        if !found_limit && tokens.consume(TokenKind::Keyword(KeywordType::Limit)) {
+            found_limit = true;
+            if !tokens.consume(TokenKind::Keyword(KeywordType::All)) {
+                limit = Some(Expression::parse(tokens)?);
+            }
+        } else if !found_limit && tokens.consume(TokenKind::Keyword(KeywordType::Fetch)) {
+            found_limit = true;
+            if !tokens.consume(TokenKind::Keyword(KeywordType::First)) {
+                tokens.expect(
+                    TokenKind::Keyword(KeywordType::Next),
+                    ErrorKind::ExpectedFirstOrNext,
+                )?;
+            }
+            // the count defaults to one row.
+            if !tokens.is_next(&TokenKind::Keyword(KeywordType::Row))
+                && !tokens.is_next(&TokenKind::Keyword(KeywordType::Rows))
+            {
+                limit = Some(Expression::parse(tokens)?);
             } else {
-                return Err(ParseError {
-                    token: Token::empty_token(),
-                    error_kind: ErrorKind::MissingTableName,
-                });
+                limit = Some(Expression::Literal(Token {
+                    value: "1".to_string(),
+                    kind: TokenKind::Numeric,
+                }));
+            }
+            if !tokens.consume(TokenKind::Keyword(KeywordType::Row)) {
+                tokens.expect(
+                    TokenKind::Keyword(KeywordType::Rows),
+                    ErrorKind::ExpectedRowsKeyword,
+                )?;
+            }
+            tokens.expect(
+                TokenKind::Keyword(KeywordType::Only),
+                ErrorKind::ExpectedOnlyKeyword,
+            )?;
+        } else if offset.is_none() && tokens.consume(TokenKind::Keyword(KeywordType::Offset)) {
+            offset = Some(Expression::parse(tokens)?);
+            if !tokens.consume(TokenKind::Keyword(KeywordType::Row)) {
+                tokens.consume(TokenKind::Keyword(KeywordType::Rows));
             }
+        } else {
+            break;
         }
+    }
+    Ok((limit, offset))
+}
+
+// order by [...$expression [ asc | desc ] [ nulls first | nulls last ] ]
+fn parse_order_by(tokens: &mut TokenStream) -> Result<Vec<OrderByItem>, ParseError> {
+    if !tokens.consume(TokenKind::Keyword(KeywordType::Order)) {
+        return Ok(vec![]);
+    }
+    tokens.expect(
+        TokenKind::Keyword(KeywordType::By),
+        ErrorKind::ExpectedByKeyword,
+    )?;
 
-        if let Some(table_name_token) = tokens.next() {
-            Ok(Some(SelectStatement {
-                items: select_items,
-                table_name: table_name_token.clone(),
-            }))
+    let mut items = vec![];
+    loop {
+        let expression = Expression::parse(tokens)?;
+        let is_descending = if tokens.consume(TokenKind::Keyword(KeywordType::Desc)) {
+            true
         } else {
-            Err(ParseError {
-                token: Token::empty_token(),
-                error_kind: ErrorKind::MissingTableName,
-            })
+            tokens.consume(TokenKind::Keyword(KeywordType::Asc));
+            false
+        };
+        let nulls_first = if tokens.consume(TokenKind::Keyword(KeywordType::Nulls)) {
+            if tokens.consume(TokenKind::Keyword(KeywordType::First)) {
+                Some(true)
+            } else {
+                tokens.expect(
+                    TokenKind::Keyword(KeywordType::Last),
+                    ErrorKind::ExpectedFirstOrLast,
+                )?;
+                Some(false)
+            }
+        } else {
+            None
+        };
+        items.push(OrderByItem {
+            expression,
+            is_descending,
+            nulls_first,
+        });
+
+        if !tokens.consume(TokenKind::Symbol(SymbolType::Comma)) {
+            break;
         }
     }
+    Ok(items)
 }
 
 impl Parsable for CreateStatement {
-    // create
-    // table $table_name
-    // (
-    //  [$name $type]
-    // )
     fn from_tokens(tokens: &[Token]) -> Result<Option<Self>, ParseError> {
-        let mut tokens = tokens.iter();
+        parse_statement(tokens, KeywordType::Create, Self::parse)
+    }
+}
 
-        if tokens.next().unwrap().kind != TokenKind::Keyword(KeywordType::Create) {
-            return Ok(None);
-        }
+impl CreateStatement {
+    // create
+    // table [ if not exists ] $table_name
+    // (
+    //  [$name $type [...$column_constraint] | $table_constraint]
+    // ) | as $select
+    fn parse(tokens: &mut TokenStream) -> Result<Self, ParseError> {
+        tokens.next_token();
 
-        expect_token(
-            tokens.next(),
+        tokens.expect(
             TokenKind::Keyword(KeywordType::Table),
             ErrorKind::ExpectedTableNameAfterCreate,
         )?;
 
-        let table_name_token = tokens.next();
-        if table_name_token.is_none() {
-            return Err(ParseError {
-                error_kind: ErrorKind::MissingTableName,
-                token: Token::empty_token(),
+        let is_if_not_exists = parse_if_not_exists(tokens)?;
+        let table_name_token = tokens.expect_identifier(ErrorKind::MissingTableName)?;
+
+        if tokens.consume(TokenKind::Keyword(KeywordType::As)) {
+            if !tokens.peek().is_some_and(is_query_start) {
+                return Err(tokens.error(ErrorKind::ExpectedSelect));
+            }
+            return Ok(CreateStatement {
+                name: table_name_token,
+                cols: vec![],
+                constraints: vec![],
+                query: Some(Box::new(SelectStatement::parse(tokens)?)),
+                is_if_not_exists,
             });
         }
-        let table_name_token = table_name_token.unwrap();
 
-        expect_token(
-            tokens.next(),
+        tokens.expect(
             TokenKind::Symbol(SymbolType::LeftParen),
             ErrorKind::MissingLeftParen,
         )?;
 
         let mut cols = Vec::<Column>::new();
+        let mut constraints = Vec::<TableConstraint>::new();
 
         loop {
-            let col_name = tokens.next();
-            if col_name.is_none() {
-                return Err(ParseError {
-                    token: Token::empty_token(),
-                    error_kind: ErrorKind::MissingRightParens,
-                });
-            }
-            let col_name = col_name.unwrap();
-            let col_type = tokens.next();
-            if col_type.is_none() {
-                return Err(ParseError {
-                    token: Token::empty_token(),
-                    error_kind: ErrorKind::ExpectedColumnType,
-                });
-            }
-            let col_type = col_type.unwrap();
-            if col_type.kind != TokenKind::Keyword(KeywordType::Int)
-                && col_type.kind != TokenKind::Keyword(KeywordType::Text)
-            {
-                return Err(ParseError {
-                    token: col_type.clone(),
-                    error_kind: ErrorKind::InvalidType,
-                });
+            if let Some(constraint) = TableConstraint::parse(tokens)? {
+                constraints.push(constraint);
+            } else {
+                cols.push(Column::parse(tokens)?);
             }
-            cols.push(Column {
-                name: col_name.clone(),
-                data_type: col_type.clone(),
-                is_primary_key: false,
-            });
-            let comma_or_right_paren = tokens.next();
+
+            let comma_or_right_paren = tokens.next_token();
             if comma_or_right_paren.is_none() {
                 return Err(ParseError {
                     error_kind: ErrorKind::MissingRightParens,
@@ -370,17 +2177,613 @@ impl Parsable for CreateStatement {
             }
         }
 
-        Ok(Some(CreateStatement {
+        Ok(CreateStatement {
             cols,
-            name: table_name_token.clone(),
-        }))
+            constraints,
+            name: table_name_token,
+            query: None,
+            is_if_not_exists,
+        })
     }
 }
 
-#[derive(Debug, Clone)]
+impl Parsable for CreateViewStatement {
+    fn from_tokens(tokens: &[Token]) -> Result<Option<Self>, ParseError> {
+        // `create table` is a `CreateStatement`.
+        if !tokens.get(1).is_some_and(|token| token.kind == TokenKind::Keyword(KeywordType::View)) {
+            return Ok(None);
+        }
+        parse_statement(tokens, KeywordType::Create, Self::parse)
+    }
+}
+
+impl CreateViewStatement {
+    // create view [ if not exists ] $view_name [ ( [...$name] ) ] as $select
+    fn parse(tokens: &mut TokenStream) -> Result<Self, ParseError> {
+        tokens.next_token();
+        tokens.next_token();
+
+        let is_if_not_exists = parse_if_not_exists(tokens)?;
+        let name = tokens.expect_identifier(ErrorKind::MissingTableName)?;
+        let columns = if tokens.is_next(&TokenKind::Symbol(SymbolType::LeftParen)) {
+            tokens.parse_name_list()?
+        } else {
+            vec![]
+        };
+
+        tokens.expect(TokenKind::Keyword(KeywordType::As), ErrorKind::ExpectedAsKeyword)?;
+        if !tokens.peek().is_some_and(is_query_start) {
+            return Err(tokens.error(ErrorKind::ExpectedSelect));
+        }
+        Ok(CreateViewStatement {
+            name,
+            columns,
+            query: Box::new(SelectStatement::parse(tokens)?),
+            is_if_not_exists,
+        })
+    }
+}
+
+impl Parsable for DropStatement {
+    fn from_tokens(tokens: &[Token]) -> Result<Option<Self>, ParseError> {
+        parse_statement(tokens, KeywordType::Drop, Self::parse)
+    }
+}
+
+impl DropStatement {
+    // drop table | view [ if exists ] $name [ cascade | restrict ]
+    fn parse(tokens: &mut TokenStream) -> Result<Self, ParseError> {
+        tokens.next_token();
+
+        let kind = if tokens.consume(TokenKind::Keyword(KeywordType::Table)) {
+            DropKind::Table
+        } else if tokens.consume(TokenKind::Keyword(KeywordType::View)) {
+            DropKind::View
+        } else {
+            return Err(tokens.error(ErrorKind::ExpectedTableOrView));
+        };
+        let is_if_exists = tokens.consume(TokenKind::Keyword(KeywordType::If));
+        if is_if_exists {
+            tokens.expect(TokenKind::Keyword(KeywordType::Exists), ErrorKind::ExpectedExistsKeyword)?;
+        }
+        let name = tokens.expect_identifier(ErrorKind::MissingTableName)?;
+
+        let is_cascade = tokens.consume(TokenKind::Keyword(KeywordType::Cascade));
+        if !is_cascade {
+            tokens.consume(TokenKind::Keyword(KeywordType::Restrict));
+        }
+        Ok(DropStatement {
+            kind,
+            name,
+            is_cascade,
+            is_if_exists,
+        })
+    }
+}
+
+// [ if not exists ]
+fn parse_if_not_exists(tokens: &mut TokenStream) -> Result<bool, ParseError> {
+    if !tokens.consume(TokenKind::Keyword(KeywordType::If)) {
+        return Ok(false);
+    }
+    tokens.expect(TokenKind::Keyword(KeywordType::Not), ErrorKind::ExpectedNotKeyword)?;
+    tokens.expect(TokenKind::Keyword(KeywordType::Exists), ErrorKind::ExpectedExistsKeyword)?;
+    Ok(true)
+}
+
+// primary key
+fn parse_primary_key(tokens: &mut TokenStream) -> Result<(), ParseError> {
+    tokens.expect(
+        TokenKind::Keyword(KeywordType::Primary),
+        ErrorKind::ExpectedPrimaryKeyword,
+    )?;
+    tokens.expect(
+        TokenKind::Keyword(KeywordType::Key),
+        ErrorKind::ExpectedKeyKeyword,
+    )?;
+    Ok(())
+}
+
+impl Column {
+    // $name $type [ primary key ]
+    fn parse(tokens: &mut TokenStream) -> Result<Self, ParseError> {
+        let col_name = tokens.next_token();
+        if col_name.is_none() {
+            return Err(ParseError {
+                token: Token::empty_token(),
+                error_kind: ErrorKind::MissingRightParens,
+            });
+        }
+        let col_name = col_name.unwrap();
+        let col_type = tokens.next_token();
+        if col_type.is_none() {
+            return Err(ParseError {
+                token: Token::empty_token(),
+                error_kind: ErrorKind::ExpectedColumnType,
+            });
+        }
+        let col_type = col_type.unwrap();
+        let is_serial = col_type.kind == TokenKind::Keyword(KeywordType::Serial);
+        if !is_data_type(&col_type.kind) && !is_serial {
+            return Err(ParseError {
+                token: col_type.clone(),
+                error_kind: ErrorKind::InvalidType,
+            });
+        }
+
+        let mut column = Column {
+            name: col_name.clone(),
+            data_type: col_type.clone(),
+            is_primary_key: false,
+            is_not_null: false,
+            is_unique: false,
+            default: None,
+            checks: vec![],
+            references: None,
+            identity: if is_serial { Some(Identity::ByDefault) } else { None },
+        };
+
+        // primary key | not null | null | unique | default $expression | check ( $expression ) | $references
+        // | $identity
+        while let Some(token) = tokens.peek() {
+            match token.kind {
+                TokenKind::Keyword(KeywordType::Primary) => {
+                    parse_primary_key(tokens)?;
+                    column.is_primary_key = true;
+                }
+                TokenKind::Keyword(KeywordType::Not) => {
+                    tokens.next_token();
+                    tokens.expect(TokenKind::Null, ErrorKind::ExpectedNullAfterNot)?;
+                    column.is_not_null = true;
+                }
+                TokenKind::Null => {
+                    tokens.next_token();
+                }
+                TokenKind::Keyword(KeywordType::Unique) => {
+                    tokens.next_token();
+                    column.is_unique = true;
+                }
+                TokenKind::Keyword(KeywordType::Default) => {
+                    tokens.next_token();
+                    column.default = Some(Expression::parse(tokens)?);
+                }
+                TokenKind::Keyword(KeywordType::Check) => {
+                    tokens.next_token();
+                    column.checks.push(parse_check_expression(tokens)?);
+                }
+                TokenKind::Keyword(KeywordType::References) => {
+                    column.references = Some(References::parse(tokens)?);
+                }
+                TokenKind::Keyword(KeywordType::Generated) => {
+                    column.identity = Some(Identity::parse(tokens)?);
+                }
+                _ => break,
+            }
+        }
+
+        Ok(column)
+    }
+}
+
+impl Identity {
+    // generated { always | by default } as identity
+    fn parse(tokens: &mut TokenStream) -> Result<Self, ParseError> {
+        tokens.next_token();
+        let identity = if tokens.consume(TokenKind::Keyword(KeywordType::Always)) {
+            Identity::Always
+        } else {
+            tokens.expect(TokenKind::Keyword(KeywordType::By), ErrorKind::ExpectedAlwaysOrByDefault)?;
+            tokens.expect(TokenKind::Keyword(KeywordType::Default), ErrorKind::ExpectedAlwaysOrByDefault)?;
+            Identity::ByDefault
+        };
+        tokens.expect(TokenKind::Keyword(KeywordType::As), ErrorKind::ExpectedAsKeyword)?;
+        tokens.expect(
+            TokenKind::Keyword(KeywordType::Identity),
+            ErrorKind::ExpectedIdentityKeyword,
+        )?;
+        Ok(identity)
+    }
+}
+
+impl TableConstraint {
+    // primary key ( [...$name] ) | unique ( [...$name] ) | check ( $expression )
+    // | foreign key ( [...$name] ) $references
+    fn parse(tokens: &mut TokenStream) -> Result<Option<Self>, ParseError> {
+        let token = match tokens.peek() {
+            Some(token) => token,
+            None => return Ok(None),
+        };
+        match token.kind {
+            TokenKind::Keyword(KeywordType::Primary) => {
+                parse_primary_key(tokens)?;
+                Ok(Some(TableConstraint::PrimaryKey(tokens.parse_name_list()?)))
+            }
+            TokenKind::Keyword(KeywordType::Unique) => {
+                tokens.next_token();
+                Ok(Some(TableConstraint::Unique(tokens.parse_name_list()?)))
+            }
+            TokenKind::Keyword(KeywordType::Check) => {
+                tokens.next_token();
+                Ok(Some(TableConstraint::Check(parse_check_expression(tokens)?)))
+            }
+            TokenKind::Keyword(KeywordType::Foreign) => {
+                tokens.next_token();
+                tokens.expect(TokenKind::Keyword(KeywordType::Key), ErrorKind::ExpectedKeyKeyword)?;
+                let columns = tokens.parse_name_list()?;
+                Ok(Some(TableConstraint::ForeignKey(columns, References::parse(tokens)?)))
+            }
+            _ => Ok(None),
+        }
+    }
+}
+
+impl References {
+    // references $table [ ( [...$name] ) ] [ on delete $action ] [ on update $action ]
+    fn parse(tokens: &mut TokenStream) -> Result<Self, ParseError> {
+        tokens.expect(
+            TokenKind::Keyword(KeywordType::References),
+            ErrorKind::ExpectedReferencesKeyword,
+        )?;
+        let table = tokens.expect_identifier(ErrorKind::MissingTableName)?;
+        let columns = if tokens.is_next(&TokenKind::Symbol(SymbolType::LeftParen)) {
+            tokens.parse_name_list()?
+        } else {
+            vec![]
+        };
+
+        let mut references = References {
+            table,
+            columns,
+            on_delete: ReferentialAction::default(),
+            on_update: ReferentialAction::default(),
+        };
+        // the actions can be given in either order.
+        while tokens.consume(TokenKind::Keyword(KeywordType::On)) {
+            if tokens.consume(TokenKind::Keyword(KeywordType::Delete)) {
+                references.on_delete = ReferentialAction::parse(tokens)?;
+            } else if tokens.consume(TokenKind::Keyword(KeywordType::Update)) {
+                references.on_update = ReferentialAction::parse(tokens)?;
+            } else {
+                return Err(tokens.error(ErrorKind::ExpectedDeleteOrUpdate));
+            }
+        }
+        Ok(references)
+    }
+}
+
+impl ReferentialAction {
+    // no action | restrict | cascade | set null | set default
+    fn parse(tokens: &mut TokenStream) -> Result<Self, ParseError> {
+        let action = match tokens.peek().map(|token| &token.kind) {
+            Some(TokenKind::Keyword(KeywordType::No)) => {
+                tokens.next_token();
+                tokens.expect(
+                    TokenKind::Keyword(KeywordType::Action),
+                    ErrorKind::ExpectedReferentialAction,
+                )?;
+                return Ok(ReferentialAction::NoAction);
+            }
+            Some(TokenKind::Keyword(KeywordType::Restrict)) => ReferentialAction::Restrict,
+            Some(TokenKind::Keyword(KeywordType::Cascade)) => ReferentialAction::Cascade,
+            Some(TokenKind::Keyword(KeywordType::Set)) => {
+                tokens.next_token();
+                return match tokens.peek().map(|token| &token.kind) {
+                    Some(TokenKind::Null) => {
+                        tokens.next_token();
+                        Ok(ReferentialAction::SetNull)
+                    }
+                    Some(TokenKind::Keyword(KeywordType::Default)) => {
+                        tokens.next_token();
+                        Ok(ReferentialAction::SetDefault)
+                    }
+                    _ => Err(tokens.error(ErrorKind::ExpectedReferentialAction)),
+                };
+            }
+            _ => return Err(tokens.error(ErrorKind::ExpectedReferentialAction)),
+        };
+        tokens.next_token();
+        Ok(action)
+    }
+}
+
+// ( $expression )
+fn parse_check_expression(tokens: &mut TokenStream) -> Result<Expression, ParseError> {
+    tokens.expect(
+        TokenKind::Symbol(SymbolType::LeftParen),
+        ErrorKind::MissingLeftParen,
+    )?;
+    let expression = Expression::parse(tokens)?;
+    tokens.expect(
+        TokenKind::Symbol(SymbolType::RightParen),
+        ErrorKind::MissingRightParens,
+    )?;
+    Ok(expression)
+}
+
+impl Parsable for UpdateStatement {
+    fn from_tokens(tokens: &[Token]) -> Result<Option<Self>, ParseError> {
+        parse_statement(tokens, KeywordType::Update, Self::parse)
+    }
+}
+
+impl UpdateStatement {
+    // update
+    // $table_name
+    // set
+    // [...$name = $expression]
+    // [ where $expression ]
+    // [ $returning ]
+    fn parse(tokens: &mut TokenStream) -> Result<Self, ParseError> {
+        tokens.next_token();
+
+        let table = tokens.expect_identifier(ErrorKind::MissingTableName)?;
+
+        tokens.expect(
+            TokenKind::Keyword(KeywordType::Set),
+            ErrorKind::MissingSetKeyword,
+        )?;
+
+        let mut assignments = vec![Assignment::parse(tokens)?];
+        while tokens.consume(TokenKind::Symbol(SymbolType::Comma)) {
+            assignments.push(Assignment::parse(tokens)?);
+        }
+
+        let where_clause = parse_where_clause(tokens)?;
+        let returning = Returning::parse(tokens)?;
+
+        Ok(UpdateStatement {
+            table,
+            assignments,
+            where_clause,
+            returning,
+        })
+    }
+}
+
+impl Parsable for DeleteStatement {
+    fn from_tokens(tokens: &[Token]) -> Result<Option<Self>, ParseError> {
+        parse_statement(tokens, KeywordType::Delete, Self::parse)
+    }
+}
+
+impl DeleteStatement {
+    // delete
+    // from
+    // $table_name
+    // [ where $expression ]
+    // [ $returning ]
+    fn parse(tokens: &mut TokenStream) -> Result<Self, ParseError> {
+        tokens.next_token();
+
+        tokens.expect(
+            TokenKind::Keyword(KeywordType::From),
+            ErrorKind::ExpectedFromKeyword,
+        )?;
+        let table = tokens.expect_identifier(ErrorKind::MissingTableName)?;
+        let where_clause = parse_where_clause(tokens)?;
+        let returning = Returning::parse(tokens)?;
+
+        Ok(DeleteStatement {
+            table,
+            where_clause,
+            returning,
+        })
+    }
+}
+
+impl Parsable for ExplainStatement {
+    fn from_tokens(tokens: &[Token]) -> Result<Option<Self>, ParseError> {
+        parse_statement(tokens, KeywordType::Explain, Self::parse)
+    }
+}
+
+impl ExplainStatement {
+    // explain
+    // [ analyze ]
+    // [ ( [...analyze | format { text | json }] ) ]
+    // $select | $insert | $update | $delete
+    fn parse(tokens: &mut TokenStream) -> Result<Self, ParseError> {
+        tokens.next_token();
+
+        let mut is_analyze = tokens.consume(TokenKind::Keyword(KeywordType::Analyze));
+        let mut format = ExplainFormat::Text;
+        if tokens.consume(TokenKind::Symbol(SymbolType::LeftParen)) {
+            loop {
+                if tokens.consume(TokenKind::Keyword(KeywordType::Analyze)) {
+                    is_analyze = true;
+                } else {
+                    tokens.expect(
+                        TokenKind::Keyword(KeywordType::Format),
+                        ErrorKind::ExpectedExplainOption,
+                    )?;
+                    format = if tokens.consume(TokenKind::Keyword(KeywordType::Json)) {
+                        ExplainFormat::Json
+                    } else {
+                        tokens.expect(
+                            TokenKind::Keyword(KeywordType::Text),
+                            ErrorKind::ExpectedExplainFormat,
+                        )?;
+                        ExplainFormat::Text
+                    };
+                }
+                if !tokens.consume(TokenKind::Symbol(SymbolType::Comma)) {
+                    break;
+                }
+            }
+            tokens.expect(
+                TokenKind::Symbol(SymbolType::RightParen),
+                ErrorKind::MissingRightParens,
+            )?;
+        }
+
+        let error = tokens.error(ErrorKind::ExpectedExplainableStatement);
+        let statement = match Statement::from_tokens(tokens.take_rest())? {
+            Some(
+                statement @ (Statement::Select(_)
+                | Statement::Insert(_)
+                | Statement::Update(_)
+                | Statement::Delete(_)),
+            ) => statement,
+            _ => return Err(error),
+        };
+        Ok(ExplainStatement {
+            is_analyze,
+            format,
+            statement: Box::new(statement),
+        })
+    }
+}
+
+impl Parsable for PrepareStatement {
+    fn from_tokens(tokens: &[Token]) -> Result<Option<Self>, ParseError> {
+        parse_statement(tokens, KeywordType::Prepare, Self::parse)
+    }
+}
+
+impl PrepareStatement {
+    // prepare $name [ ( [...$type] ) ] as $select | $insert | $update | $delete
+    fn parse(tokens: &mut TokenStream) -> Result<Self, ParseError> {
+        tokens.next_token();
+
+        let name = tokens.expect_identifier(ErrorKind::ExpectedStatementName)?;
+        let mut parameter_types = vec![];
+        if tokens.consume(TokenKind::Symbol(SymbolType::LeftParen)) {
+            parameter_types.push(parse_data_type(tokens)?);
+            while tokens.consume(TokenKind::Symbol(SymbolType::Comma)) {
+                parameter_types.push(parse_data_type(tokens)?);
+            }
+            tokens.expect(
+                TokenKind::Symbol(SymbolType::RightParen),
+                ErrorKind::MissingRightParens,
+            )?;
+        }
+        tokens.expect(TokenKind::Keyword(KeywordType::As), ErrorKind::ExpectedAsKeyword)?;
+
+        let error = tokens.error(ErrorKind::ExpectedPreparableStatement);
+        let statement = match Statement::from_tokens(tokens.take_rest())? {
+            Some(
+                statement @ (Statement::Select(_)
+                | Statement::Insert(_)
+                | Statement::Update(_)
+                | Statement::Delete(_)),
+            ) => statement,
+            _ => return Err(error),
+        };
+        Ok(PrepareStatement {
+            name,
+            parameter_types,
+            statement: Box::new(statement),
+        })
+    }
+}
+
+impl Parsable for ExecuteStatement {
+    fn from_tokens(tokens: &[Token]) -> Result<Option<Self>, ParseError> {
+        parse_statement(tokens, KeywordType::Execute, Self::parse)
+    }
+}
+
+impl ExecuteStatement {
+    // execute $name [ ( [...$expression] ) ]
+    fn parse(tokens: &mut TokenStream) -> Result<Self, ParseError> {
+        tokens.next_token();
+
+        let name = tokens.expect_identifier(ErrorKind::ExpectedStatementName)?;
+        let mut parameters = vec![];
+        if tokens.consume(TokenKind::Symbol(SymbolType::LeftParen)) {
+            parameters.push(Expression::parse(tokens)?);
+            while tokens.consume(TokenKind::Symbol(SymbolType::Comma)) {
+                parameters.push(Expression::parse(tokens)?);
+            }
+            tokens.expect(
+                TokenKind::Symbol(SymbolType::RightParen),
+                ErrorKind::MissingRightParens,
+            )?;
+        }
+        Ok(ExecuteStatement { name, parameters })
+    }
+}
+
+impl Parsable for DeallocateStatement {
+    fn from_tokens(tokens: &[Token]) -> Result<Option<Self>, ParseError> {
+        parse_statement(tokens, KeywordType::Deallocate, Self::parse)
+    }
+}
+
+impl DeallocateStatement {
+    // deallocate [ prepare ] $name | all
+    fn parse(tokens: &mut TokenStream) -> Result<Self, ParseError> {
+        tokens.next_token();
+        tokens.consume(TokenKind::Keyword(KeywordType::Prepare));
+
+        if tokens.consume(TokenKind::Keyword(KeywordType::All)) {
+            return Ok(DeallocateStatement { name: None });
+        }
+        let name = tokens.expect_identifier(ErrorKind::ExpectedStatementName)?;
+        Ok(DeallocateStatement { name: Some(name) })
+    }
+}
+
+impl Parsable for SetStatement {
+    fn from_tokens(tokens: &[Token]) -> Result<Option<Self>, ParseError> {
+        parse_statement(tokens, KeywordType::Set, Self::parse)
+    }
+}
+
+impl SetStatement {
+    // set $name { = | to } $expression
+    fn parse(tokens: &mut TokenStream) -> Result<Self, ParseError> {
+        tokens.next_token();
+
+        let name = tokens.expect_identifier(ErrorKind::ExpectedSettingName)?;
+        if !tokens.consume(TokenKind::Keyword(KeywordType::To)) {
+            tokens.expect(TokenKind::Symbol(SymbolType::Eq), ErrorKind::ExpectedEq)?;
+        }
+        let value = Expression::parse(tokens)?;
+        Ok(SetStatement { name, value })
+    }
+}
+
+impl Returning {
+    // returning { * | [...$select_item] }
+    fn parse(tokens: &mut TokenStream) -> Result<Option<Self>, ParseError> {
+        if !tokens.consume(TokenKind::Keyword(KeywordType::Returning)) {
+            return Ok(None);
+        }
+        if tokens.consume(TokenKind::Symbol(SymbolType::Star)) {
+            return Ok(Some(Returning::AllColumns));
+        }
+
+        let mut items = vec![SelectItem::parse(tokens)?];
+        while tokens.consume(TokenKind::Symbol(SymbolType::Comma)) {
+            items.push(SelectItem::parse(tokens)?);
+        }
+        Ok(Some(Returning::Items(items)))
+    }
+}
+
+impl Assignment {
+    // $name = $expression
+    fn parse(tokens: &mut TokenStream) -> Result<Self, ParseError> {
+        let column = tokens.expect_identifier(ErrorKind::ExpectedColumnName)?;
+        tokens.expect(TokenKind::Symbol(SymbolType::Eq), ErrorKind::ExpectedEq)?;
+        let value = Expression::parse(tokens)?;
+        Ok(Assignment { column, value })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
 pub struct SelectItem {
-    pub name: Token,
+    pub expression: Expression,
     pub as_name: Option<Token>,
 }
 
+#[derive(Debug, Clone, PartialEq)]
+pub struct OrderByItem {
+    pub expression: Expression,
+    pub is_descending: bool,
+    // `None` when not given, nulls then sort as if they were larger than any value.
+    pub nulls_first: Option<bool>,
+}
+
 impl std::error::Error for ParseError {}
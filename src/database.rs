@@ -1,16 +1,94 @@
 use std::fmt::Display;
 
-use crate::ast::{CreateStatement, InsertStatement, SelectStatement};
+use crate::ast::{
+    CreateStatement, CreateViewStatement, DeleteStatement, DropStatement, ExplainStatement, Expression, Identity,
+    InsertStatement, SelectStatement, Statement, UpdateStatement,
+};
 use crate::lexer::{KeywordType, Token, TokenKind};
-use crate::table::{MemoryError, Result};
+use crate::table::{CellValue, MemoryError, Result};
 pub trait Database {
     fn run_query(&mut self, query: &str) -> std::result::Result<Option<QueryResult>, Box<dyn std::error::Error>>;
 
-    fn create_table(&mut self, create_statement: CreateStatement) -> Result<()>;
+    // the statements with `if [ not ] exists` return a notice when they are skipped.
+    fn create_table(&mut self, create_statement: CreateStatement) -> Result<Option<QueryResult>>;
 
-    fn insert(&mut self, insert_statement: InsertStatement) -> Result<()>;
+    // the write statements return the rows they changed when they have a returning clause.
+    fn insert(&mut self, insert_statement: InsertStatement) -> Result<Option<QueryResult>>;
 
     fn select(&self, select_statement: SelectStatement) -> Result<QueryResult>;
+
+    // the plan of the query as rows of text, or a single row of JSON.
+    fn explain(&mut self, explain_statement: ExplainStatement) -> Result<QueryResult>;
+
+    fn update(&mut self, update_statement: UpdateStatement) -> Result<Option<QueryResult>>;
+
+    fn delete(&mut self, delete_statement: DeleteStatement) -> Result<Option<QueryResult>>;
+
+    fn create_view(&mut self, create_view_statement: CreateViewStatement) -> Result<Option<QueryResult>>;
+
+    fn drop(&mut self, drop_statement: DropStatement) -> Result<Option<QueryResult>>;
+
+    // parses a select, insert, update or delete once, to run it with different values for its parameters.
+    // nothing in the repl calls it, only the tests do. the repl has `prepare name as ...` instead.
+    #[cfg_attr(not(test), allow(dead_code))]
+    fn prepare(&self, query: &str) -> std::result::Result<PreparedStatement, Box<dyn std::error::Error>>;
+
+    // runs the statement with the values of its parameters.
+    fn execute_prepared(&mut self, prepared: &Prepared, parameters: &[CellValue]) -> Result<Option<QueryResult>>;
+}
+
+// a statement with `$1`, `$2`... in place of some of its values.
+#[derive(Debug, Clone)]
+pub struct Prepared {
+    pub statement: Statement,
+    // the type of each parameter, `None` when it can have any type
+    pub parameter_types: Vec<Option<CellType>>,
+}
+
+impl Prepared {
+    // selects return rows, writes only with a returning clause.
+    pub fn returns_rows(&self) -> bool {
+        match &self.statement {
+            Statement::Insert(insert_statement) => insert_statement.returning.is_some(),
+            Statement::Update(update_statement) => update_statement.returning.is_some(),
+            Statement::Delete(delete_statement) => delete_statement.returning.is_some(),
+            _ => true,
+        }
+    }
+}
+
+/// A statement parsed once by `Database::prepare`, it can be run any number of times on the
+/// database it was prepared for.
+// made by `Database::prepare`, so only the tests use it.
+#[cfg_attr(not(test), allow(dead_code))]
+pub struct PreparedStatement {
+    prepared: Prepared,
+}
+
+#[cfg_attr(not(test), allow(dead_code))]
+impl PreparedStatement {
+    pub fn new(prepared: Prepared) -> Self {
+        PreparedStatement { prepared }
+    }
+
+    /// Runs the statement with `parameters` as the values of `$1`, `$2`... The count and types of
+    /// the values are checked before anything runs.
+    pub fn execute<D: Database + ?Sized>(
+        &self,
+        database: &mut D,
+        parameters: &[CellValue],
+    ) -> Result<Option<QueryResult>> {
+        database.execute_prepared(&self.prepared, parameters)
+    }
+
+    /// Like `execute`, for the statements that return rows: selects and the ones with a returning
+    /// clause. The others are refused without running.
+    pub fn query<D: Database + ?Sized>(&self, database: &mut D, parameters: &[CellValue]) -> Result<QueryResult> {
+        if !self.prepared.returns_rows() {
+            return Err(MemoryError::NoRowsReturned);
+        }
+        self.execute(database, parameters)?.ok_or(MemoryError::NoRowsReturned)
+    }
 }
 
 #[derive(Debug)]
@@ -35,39 +113,96 @@ impl Display for QueryResult {
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Default, PartialEq)]
 pub enum CellType {
     Int,
+    Float,
+    #[default]
     Text,
+    Bool,
 }
 
-impl Default for CellType {
-    fn default() -> Self {
-        CellType::Text
+impl Display for CellType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            CellType::Int => "int",
+            CellType::Float => "float",
+            CellType::Text => "text",
+            CellType::Bool => "bool",
+        })
     }
 }
 
 impl CellType {
-    fn parse_token(token: &Token) -> Result<Self> {
+    pub fn parse_token(token: &Token) -> Result<Self> {
         match token.kind {
             TokenKind::Keyword(KeywordType::Int) => Ok(CellType::Int),
+            TokenKind::Keyword(KeywordType::Float) => Ok(CellType::Float),
             TokenKind::Keyword(KeywordType::Text) => Ok(CellType::Text),
+            TokenKind::Keyword(KeywordType::Bool) => Ok(CellType::Bool),
             _ => Err(MemoryError::InvalidType(token.value.clone())),
         }
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Default, Clone)]
 pub struct Column {
     pub name: String,
     pub column_type: CellType,
+    pub is_not_null: bool,
+    pub default: Option<Expression>,
+    pub checks: Vec<Check>,
+    // only for int columns, the table keeps the last value it generated
+    pub identity: Option<Identity>,
+    // the table, or its alias, a column is read from in a query
+    pub table: Option<String>,
+    // hidden columns can only be referenced through their table, e.g. the columns of a join's `using`
+    pub is_hidden: bool,
+}
+
+#[derive(Debug, Clone)]
+pub struct Check {
+    pub name: String,
+    pub expression: Expression,
 }
 
 impl Column {
-    pub fn parse_token(ast_column: &crate::ast::Column) -> Result<Self> {
+    pub fn parse_token(ast_column: &crate::ast::Column, table_name: &str) -> Result<Self> {
+        let name = ast_column.name.value.clone();
+        let checks = ast_column
+            .checks
+            .iter()
+            .enumerate()
+            .map(|(index, expression)| Check {
+                name: numbered_name(format!("{}_{}_check", table_name, name), index),
+                expression: expression.clone(),
+            })
+            .collect();
+        let column_type = match ast_column.data_type.kind {
+            TokenKind::Keyword(KeywordType::Serial) => CellType::Int,
+            _ => CellType::parse_token(&ast_column.data_type)?,
+        };
+        if ast_column.identity.is_some() && (column_type != CellType::Int || ast_column.default.is_some()) {
+            return Err(MemoryError::InvalidIdentity(name));
+        }
         Ok(Column {
-            name: ast_column.name.value.clone(),
-            column_type: CellType::parse_token(&ast_column.data_type)?,
+            column_type,
+            // primary key and identity columns can't be null either.
+            is_not_null: ast_column.is_not_null || ast_column.is_primary_key || ast_column.identity.is_some(),
+            default: ast_column.default.clone(),
+            checks,
+            identity: ast_column.identity,
+            name,
+            ..Default::default()
         })
     }
 }
+
+// the first constraint gets the base name, the following ones are numbered.
+pub fn numbered_name(base: String, index: usize) -> String {
+    if index == 0 {
+        base
+    } else {
+        format!("{}{}", base, index)
+    }
+}
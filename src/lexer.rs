@@ -1,4 +1,4 @@
-use crate::cursor::Cursor;
+use crate::cursor::{Cursor, Location};
 use crate::lex_error::{ErrorKind, LexError};
 
 #[derive(PartialEq, Debug, Clone)]
@@ -25,10 +25,15 @@ pub enum SymbolType {
     Eq,
     Neq,
     Plus,
+    Minus,
+    Star,
+    Slash,
+    Dot,
     Lt,
     Lte,
     Gt,
     Gte,
+    DoubleColon,
     Endl,
     Tab,
 }
@@ -43,10 +48,15 @@ impl SymbolType {
             SymbolType::Eq => "=",
             SymbolType::Neq => "!=",
             SymbolType::Plus => "+",
+            SymbolType::Minus => "-",
+            SymbolType::Star => "*",
+            SymbolType::Slash => "/",
+            SymbolType::Dot => ".",
             SymbolType::Lt => "<",
             SymbolType::Lte => "<=",
             SymbolType::Gt => ">",
             SymbolType::Gte => ">=",
+            SymbolType::DoubleColon => "::",
             SymbolType::Endl => "\n",
             SymbolType::Tab => "\t",
         }
@@ -61,10 +71,16 @@ impl SymbolType {
             SymbolType::Eq,
             SymbolType::Neq,
             SymbolType::Plus,
-            SymbolType::Lt,
+            SymbolType::Minus,
+            SymbolType::Star,
+            SymbolType::Slash,
+            SymbolType::Dot,
+            // two-character symbols have to come before their one-character prefixes.
             SymbolType::Lte,
-            SymbolType::Gt,
             SymbolType::Gte,
+            SymbolType::Lt,
+            SymbolType::Gt,
+            SymbolType::DoubleColon,
             SymbolType::Endl,
             SymbolType::Tab,
         ];
@@ -87,6 +103,95 @@ pub enum KeywordType {
     Int,
     Text,
     Where,
+    Not,
+    Update,
+    Set,
+    Primary,
+    Key,
+    Null,
+    Unique,
+    Default,
+    Check,
+    Order,
+    By,
+    Asc,
+    Desc,
+    Nulls,
+    First,
+    Last,
+    Limit,
+    Offset,
+    Fetch,
+    Next,
+    Rows,
+    Row,
+    Only,
+    All,
+    Group,
+    Having,
+    Distinct,
+    Join,
+    Inner,
+    Left,
+    Right,
+    Full,
+    Outer,
+    Cross,
+    On,
+    Using,
+    In,
+    Exists,
+    With,
+    Recursive,
+    Union,
+    Intersect,
+    Except,
+    Case,
+    When,
+    Then,
+    Else,
+    End,
+    Float,
+    Bool,
+    Cast,
+    Like,
+    Ilike,
+    Similar,
+    To,
+    Escape,
+    Is,
+    Between,
+    View,
+    Drop,
+    Cascade,
+    Restrict,
+    Over,
+    Partition,
+    Preceding,
+    Following,
+    Unbounded,
+    Current,
+    Conflict,
+    Do,
+    Nothing,
+    Delete,
+    Returning,
+    Explain,
+    Analyze,
+    Format,
+    Json,
+    If,
+    Prepare,
+    Execute,
+    Deallocate,
+    References,
+    Foreign,
+    No,
+    Action,
+    Generated,
+    Always,
+    Identity,
+    Serial,
 }
 
 impl KeywordType {
@@ -105,6 +210,95 @@ impl KeywordType {
             KeywordType::Where => "where",
             KeywordType::And => "and",
             KeywordType::Or => "or",
+            KeywordType::Not => "not",
+            KeywordType::Update => "update",
+            KeywordType::Set => "set",
+            KeywordType::Primary => "primary",
+            KeywordType::Key => "key",
+            KeywordType::Null => "null",
+            KeywordType::Unique => "unique",
+            KeywordType::Default => "default",
+            KeywordType::Check => "check",
+            KeywordType::Order => "order",
+            KeywordType::By => "by",
+            KeywordType::Asc => "asc",
+            KeywordType::Desc => "desc",
+            KeywordType::Nulls => "nulls",
+            KeywordType::First => "first",
+            KeywordType::Last => "last",
+            KeywordType::Limit => "limit",
+            KeywordType::Offset => "offset",
+            KeywordType::Fetch => "fetch",
+            KeywordType::Next => "next",
+            KeywordType::Rows => "rows",
+            KeywordType::Row => "row",
+            KeywordType::Only => "only",
+            KeywordType::All => "all",
+            KeywordType::Group => "group",
+            KeywordType::Having => "having",
+            KeywordType::Distinct => "distinct",
+            KeywordType::Join => "join",
+            KeywordType::Inner => "inner",
+            KeywordType::Left => "left",
+            KeywordType::Right => "right",
+            KeywordType::Full => "full",
+            KeywordType::Outer => "outer",
+            KeywordType::Cross => "cross",
+            KeywordType::On => "on",
+            KeywordType::Using => "using",
+            KeywordType::In => "in",
+            KeywordType::Exists => "exists",
+            KeywordType::With => "with",
+            KeywordType::Recursive => "recursive",
+            KeywordType::Union => "union",
+            KeywordType::Intersect => "intersect",
+            KeywordType::Except => "except",
+            KeywordType::Case => "case",
+            KeywordType::When => "when",
+            KeywordType::Then => "then",
+            KeywordType::Else => "else",
+            KeywordType::End => "end",
+            KeywordType::Float => "float",
+            KeywordType::Bool => "bool",
+            KeywordType::Cast => "cast",
+            KeywordType::Like => "like",
+            KeywordType::Ilike => "ilike",
+            KeywordType::Similar => "similar",
+            KeywordType::To => "to",
+            KeywordType::Escape => "escape",
+            KeywordType::Is => "is",
+            KeywordType::Between => "between",
+            KeywordType::View => "view",
+            KeywordType::Drop => "drop",
+            KeywordType::Cascade => "cascade",
+            KeywordType::Restrict => "restrict",
+            KeywordType::Over => "over",
+            KeywordType::Partition => "partition",
+            KeywordType::Preceding => "preceding",
+            KeywordType::Following => "following",
+            KeywordType::Unbounded => "unbounded",
+            KeywordType::Current => "current",
+            KeywordType::Conflict => "conflict",
+            KeywordType::Do => "do",
+            KeywordType::Nothing => "nothing",
+            KeywordType::Delete => "delete",
+            KeywordType::Returning => "returning",
+            KeywordType::Explain => "explain",
+            KeywordType::Analyze => "analyze",
+            KeywordType::Format => "format",
+            KeywordType::Json => "json",
+            KeywordType::If => "if",
+            KeywordType::Prepare => "prepare",
+            KeywordType::Execute => "execute",
+            KeywordType::Deallocate => "deallocate",
+            KeywordType::References => "references",
+            KeywordType::Foreign => "foreign",
+            KeywordType::No => "no",
+            KeywordType::Action => "action",
+            KeywordType::Generated => "generated",
+            KeywordType::Always => "always",
+            KeywordType::Identity => "identity",
+            KeywordType::Serial => "serial",
         }
     }
 
@@ -123,6 +317,95 @@ impl KeywordType {
             KeywordType::Where,
             KeywordType::And,
             KeywordType::Or,
+            KeywordType::Not,
+            KeywordType::Update,
+            KeywordType::Set,
+            KeywordType::Primary,
+            KeywordType::Key,
+            KeywordType::Null,
+            KeywordType::Unique,
+            KeywordType::Default,
+            KeywordType::Check,
+            KeywordType::Order,
+            KeywordType::By,
+            KeywordType::Asc,
+            KeywordType::Desc,
+            KeywordType::Nulls,
+            KeywordType::First,
+            KeywordType::Last,
+            KeywordType::Limit,
+            KeywordType::Offset,
+            KeywordType::Fetch,
+            KeywordType::Next,
+            KeywordType::Rows,
+            KeywordType::Row,
+            KeywordType::Only,
+            KeywordType::All,
+            KeywordType::Group,
+            KeywordType::Having,
+            KeywordType::Distinct,
+            KeywordType::Join,
+            KeywordType::Inner,
+            KeywordType::Left,
+            KeywordType::Right,
+            KeywordType::Full,
+            KeywordType::Outer,
+            KeywordType::Cross,
+            KeywordType::On,
+            KeywordType::Using,
+            KeywordType::In,
+            KeywordType::Exists,
+            KeywordType::With,
+            KeywordType::Recursive,
+            KeywordType::Union,
+            KeywordType::Intersect,
+            KeywordType::Except,
+            KeywordType::Case,
+            KeywordType::When,
+            KeywordType::Then,
+            KeywordType::Else,
+            KeywordType::End,
+            KeywordType::Float,
+            KeywordType::Bool,
+            KeywordType::Cast,
+            KeywordType::Like,
+            KeywordType::Ilike,
+            KeywordType::Similar,
+            KeywordType::To,
+            KeywordType::Escape,
+            KeywordType::Is,
+            KeywordType::Between,
+            KeywordType::View,
+            KeywordType::Drop,
+            KeywordType::Cascade,
+            KeywordType::Restrict,
+            KeywordType::Over,
+            KeywordType::Partition,
+            KeywordType::Preceding,
+            KeywordType::Following,
+            KeywordType::Unbounded,
+            KeywordType::Current,
+            KeywordType::Conflict,
+            KeywordType::Do,
+            KeywordType::Nothing,
+            KeywordType::Delete,
+            KeywordType::Returning,
+            KeywordType::Explain,
+            KeywordType::Analyze,
+            KeywordType::Format,
+            KeywordType::Json,
+            KeywordType::If,
+            KeywordType::Prepare,
+            KeywordType::Execute,
+            KeywordType::Deallocate,
+            KeywordType::References,
+            KeywordType::Foreign,
+            KeywordType::No,
+            KeywordType::Action,
+            KeywordType::Generated,
+            KeywordType::Always,
+            KeywordType::Identity,
+            KeywordType::Serial,
         ]
         .to_vec();
         keyword_types
@@ -137,6 +420,8 @@ pub enum TokenKind {
     String,
     Numeric,
     Null,
+    // `$1`, `$2`... in a prepared statement
+    Parameter,
 }
 
 fn lex_symbol(source: &str) -> Option<(Token, Cursor)> {
@@ -167,23 +452,31 @@ fn lex_symbol(source: &str) -> Option<(Token, Cursor)> {
 
 // TODO: fix case sensitivity.
 fn lex_keyword(source: &str) -> Option<(Token, Cursor)> {
-    let keyword_match = String::default();
-    for keyword in KeywordType::values().iter() {
-        if source.starts_with(keyword.value()) {
-            let mut cursor = Cursor::default();
-            cursor.pointer = keyword.value().len();
-            cursor.loc.column = cursor.pointer;
-            return Some((
-                Token {
-                    value: keyword.value().to_string(),
-                    kind: TokenKind::Keyword(keyword.clone()),
-                },
-                cursor,
-            ));
+    // the longest keyword wins, e.g. "into" should not be lexed as "int".
+    let keyword = KeywordType::values()
+        .into_iter()
+        .filter(|keyword| source.starts_with(keyword.value()))
+        .max_by_key(|keyword| keyword.value().len())?;
+
+    let cursor = Cursor {
+        pointer: keyword.value().len(),
+        loc: Location {
+            line: 0,
+            column: keyword.value().len(),
+        },
+    };
+    let token = if keyword == KeywordType::Null {
+        Token {
+            value: keyword.value().to_string(),
+            kind: TokenKind::Null,
         }
-    }
-
-    None
+    } else {
+        Token {
+            value: keyword.value().to_string(),
+            kind: TokenKind::Keyword(keyword),
+        }
+    };
+    Some((token, cursor))
 }
 
 fn lex_string(source: &str) -> Option<(Token, Cursor)> {
@@ -203,22 +496,22 @@ fn lex_char_delimited(source: &str, delimiter: char) -> Option<(Token, Cursor)>
         return None;
     }
 
-    let does_sec_delimiter_exist = source_iterator.find(|&(_, c)| c == '\'');
-    does_sec_delimiter_exist?;
-
-    let second_delimiter = source_iterator.skip_while(|&(_, c)| c == '\'');
-
-    let mut last_index = source.len();
-    for (index, ch) in second_delimiter {
-        if ch != delimiter {
-            last_index = index;
+    // a doubled delimiter stands for the delimiter itself, a single one ends the token.
+    let mut last_index = None;
+    let mut source_iterator = source_iterator.peekable();
+    while let Some((index, c)) = source_iterator.next() {
+        if c != delimiter {
+            continue;
+        }
+        if source_iterator.next_if(|&(_, next)| next == delimiter).is_none() {
+            last_index = Some(index + c.len_utf8());
             break;
         }
     }
+    let last_index = last_index?;
 
-    // TODO: this might be wrong.
-    cursor.pointer = last_index + 1;
-    cursor.loc.column = last_index + 1;
+    cursor.pointer = last_index;
+    cursor.loc.column = last_index;
 
     Some((
         Token {
@@ -229,6 +522,30 @@ fn lex_char_delimited(source: &str, delimiter: char) -> Option<(Token, Cursor)>
     ))
 }
 
+// a `$` followed by the number of the parameter.
+fn lex_parameter(source: &str) -> Option<(Token, Cursor)> {
+    let digits = source.strip_prefix('$')?.chars().take_while(char::is_ascii_digit).count();
+    if digits == 0 {
+        return None;
+    }
+
+    let length = digits + 1;
+    let cursor = Cursor {
+        pointer: length,
+        loc: Location {
+            line: 0,
+            column: length,
+        },
+    };
+    Some((
+        Token {
+            value: source[..length].to_string(),
+            kind: TokenKind::Parameter,
+        },
+        cursor,
+    ))
+}
+
 fn lex_identifier(source: &str) -> Option<(Token, Cursor)> {
     // TODO: handle quoted identifiers;
     let quoted_identifier = lex_char_delimited(source, '\'');
@@ -281,7 +598,7 @@ fn lex_numeric(source: &str) -> Option<(Token, Cursor)> {
     for (i, c) in source.char_indices() {
         cursor.loc.column += 1;
 
-        let is_digit = c.is_digit(10);
+        let is_digit = c.is_ascii_digit();
         let is_period = c == '.';
         let is_exp_marker = c == 'e';
 
@@ -290,6 +607,11 @@ fn lex_numeric(source: &str) -> Option<(Token, Cursor)> {
             return None;
         }
 
+        // a period on its own is a symbol, e.g. in `table.column`.
+        if i == 0 && is_period && !source[1..].starts_with(|c: char| c.is_ascii_digit()) {
+            return None;
+        }
+
         if is_period {
             // there should not be two periods.
             if period_found {
@@ -310,7 +632,7 @@ fn lex_numeric(source: &str) -> Option<(Token, Cursor)> {
             if i == source.len() - 1 {
                 return None;
             }
-        } else if (c == '+' || c == '-') && exp_marker_index == i - 1 {
+        } else if (c == '+' || c == '-') && exp_marker_found && exp_marker_index == i - 1 {
             cursor.pointer += 1;
             continue;
         } else if !is_digit {
@@ -329,6 +651,23 @@ fn lex_numeric(source: &str) -> Option<(Token, Cursor)> {
     ))
 }
 
+// a keyword is only a keyword if it is not the start of a longer identifier,
+// e.g. "keys" is an identifier and not the keyword "key" followed by "s".
+fn lex_keyword_or_identifier(source: &str) -> Option<(Token, Cursor)> {
+    let keyword = lex_keyword(source);
+    let identifier = lex_identifier(source);
+    match (keyword, identifier) {
+        (Some(keyword), Some(identifier)) => {
+            if identifier.1.pointer > keyword.1.pointer {
+                Some(identifier)
+            } else {
+                Some(keyword)
+            }
+        }
+        (keyword, identifier) => keyword.or(identifier),
+    }
+}
+
 pub fn lex(source: &str) -> Result<Vec<Token>, LexError> {
     let mut cursor = Cursor::default();
     let mut tokens = Vec::<Token>::new();
@@ -339,28 +678,33 @@ pub fn lex(source: &str) -> Result<Vec<Token>, LexError> {
             cursor.merge(moved_cursor);
             continue;
         }
-        if let Some((next_token, moved_cursor)) = lex_keyword(&source[cursor.pointer..]) {
+        if let Some((next_token, moved_cursor)) = lex_string(&source[cursor.pointer..]) {
             tokens.push(next_token);
             cursor.merge(moved_cursor);
             continue;
         }
-        if let Some((next_token, moved_cursor)) = lex_identifier(&source[cursor.pointer..]) {
+        if let Some((next_token, moved_cursor)) = lex_parameter(&source[cursor.pointer..]) {
             tokens.push(next_token);
             cursor.merge(moved_cursor);
             continue;
         }
-        if let Some((next_token, moved_cursor)) = lex_string(&source[cursor.pointer..]) {
+        if let Some((next_token, moved_cursor)) = lex_keyword_or_identifier(&source[cursor.pointer..]) {
             tokens.push(next_token);
             cursor.merge(moved_cursor);
             continue;
         }
         if let Some((next_token, moved_cursor)) = lex_symbol(&source[cursor.pointer..]) {
-            tokens.push(next_token);
+            // line breaks and tabs only move the cursor, the parser never needs them.
+            if next_token.kind != TokenKind::Symbol(SymbolType::Endl)
+                && next_token.kind != TokenKind::Symbol(SymbolType::Tab)
+            {
+                tokens.push(next_token);
+            }
             cursor.merge(moved_cursor);
             continue;
         }
 
-        if source[cursor.pointer..].starts_with(' ') {
+        if source[cursor.pointer..].starts_with(' ') || source[cursor.pointer..].starts_with('\r') {
             cursor.pointer += 1;
             cursor.loc.column += 1;
             continue;
@@ -374,8 +718,8 @@ pub fn lex(source: &str) -> Result<Vec<Token>, LexError> {
 #[cfg(test)]
 mod tests {
     use crate::lexer::{
-        lex_char_delimited, lex_identifier, lex_keyword, lex_numeric, lex_symbol, KeywordType,
-        SymbolType, TokenKind,
+        lex, lex_char_delimited, lex_identifier, lex_keyword, lex_numeric, lex_parameter, lex_symbol,
+        KeywordType, SymbolType, TokenKind,
     };
 
     fn test_numeric_lexer(source: &str, should_be_none: bool, expected_result: &str) {
@@ -485,6 +829,16 @@ mod tests {
         test_numeric_lexer(source, true, source);
     }
 
+    #[test]
+    fn test_lex_parameter() {
+        let (token, cursor) = lex_parameter("$12 + 1").unwrap();
+        assert_eq!(token.value, "$12");
+        assert_eq!(token.kind, TokenKind::Parameter);
+        assert_eq!(cursor.pointer, 3);
+        assert!(lex_parameter("$a").is_none());
+        assert!(lex_parameter("1").is_none());
+    }
+
     #[test]
     fn test_lex_delimiter_basic() {
         let source = "'aabbcc'";
@@ -511,7 +865,7 @@ mod tests {
 
     #[test]
     fn test_lex_delimiter_escape() {
-        let source = "'asdf''";
+        let source = "'asdf'''";
         let result = lex_char_delimited(source, '\'');
         assert!(result.is_some());
         if let Some((token, _)) = result {
@@ -530,6 +884,18 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_lex_delimiter_doubled_delimiter() {
+        let source = "'it''s' from";
+        let result = lex_char_delimited(source, '\'');
+        let expected = "'it''s'";
+        assert!(result.is_some());
+        if let Some((token, _)) = result {
+            assert_eq!(token.value, expected);
+        }
+        assert!(lex_char_delimited("'it''s", '\'').is_none());
+    }
+
     #[test]
     fn test_lex_delimiter_with_space() {
         let source = "'name' from";
@@ -648,4 +1014,49 @@ mod tests {
         let (token, _) = lex_identifier(source).unwrap();
         assert_eq!(token.value, source);
     }
+
+    #[test]
+    fn test_lex_keyword_prefix_of_identifier() {
+        let tokens = lex("select keys from internal").unwrap();
+        assert_eq!(tokens[1].kind, TokenKind::Identifier);
+        assert_eq!(tokens[1].value, "keys");
+        assert_eq!(tokens[3].kind, TokenKind::Identifier);
+        assert_eq!(tokens[3].value, "internal");
+    }
+
+    #[test]
+    fn test_lex_string_followed_by_symbol() {
+        let tokens = lex("('a',1)").unwrap();
+        let kinds: Vec<TokenKind> = tokens.into_iter().map(|token| token.kind).collect();
+        assert_eq!(
+            kinds,
+            vec![
+                TokenKind::Symbol(SymbolType::LeftParen),
+                TokenKind::String,
+                TokenKind::Symbol(SymbolType::Comma),
+                TokenKind::Numeric,
+                TokenKind::Symbol(SymbolType::RightParen),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_lex_qualified_column() {
+        let tokens = lex("users.id").unwrap();
+        let kinds: Vec<TokenKind> = tokens.into_iter().map(|token| token.kind).collect();
+        assert_eq!(
+            kinds,
+            vec![
+                TokenKind::Identifier,
+                TokenKind::Symbol(SymbolType::Dot),
+                TokenKind::Identifier,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_lex_symbol_lte() {
+        let (token, _) = lex_symbol("<=").unwrap();
+        assert_eq!(token.kind, TokenKind::Symbol(SymbolType::Lte));
+    }
 }
@@ -5,6 +5,8 @@ mod cursor;
 mod database;
 mod lex_error;
 mod lexer;
+mod pattern;
+mod plan;
 mod table;
 
 use crate::ast::Parsable;
@@ -12,6 +14,7 @@ use crate::database::Database;
 use crate::lexer::lex;
 use crate::table::Memory;
 
+#[allow(dead_code)]
 fn lex_tokens_example() {
     let source = "select name from users where user_id = 1;
     and email = 'something@gmail.com'";
@@ -23,6 +26,7 @@ fn lex_tokens_example() {
     }
 }
 
+#[allow(dead_code)]
 fn insert_statement_example() {
     let source = "insert into hello values (\'one\', 2, three)";
 
@@ -34,6 +38,7 @@ fn insert_statement_example() {
     println!("{:#?}", stmt.unwrap());
 }
 
+#[allow(dead_code)]
 fn select_statement_example() {
     let source = "select something as somethingelse from sometable";
 
@@ -45,6 +50,7 @@ fn select_statement_example() {
     println!("{:#?}", stmt.unwrap());
 }
 
+#[allow(dead_code)]
 fn create_statement_example() {
     let source = "create table my_table (id text , name text)";
 
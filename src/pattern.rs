@@ -0,0 +1,352 @@
+// the patterns of `like`, `ilike` and `similar to`, compiled to an automaton that is matched
+// against the whole text without backtracking.
+
+// the largest count of a `{m,n}` repetition, every repetition is a copy of the repeated pattern.
+const MAX_REPETITION: usize = 1000;
+
+// the most states a pattern compiles to, nested repetitions multiply their counts.
+const MAX_STATES: usize = 100_000;
+
+const ENDS_WITH_ESCAPE: &str = "pattern ends with the escape character";
+
+pub struct Pattern {
+    states: Vec<State>,
+    start: usize,
+    is_case_insensitive: bool,
+}
+
+enum State {
+    Char(char, usize),
+    // any single character
+    Any(usize),
+    Class(CharClass, usize),
+    // continues with both states without consuming a character
+    Split(usize, usize),
+    Match,
+}
+
+#[derive(Clone)]
+struct CharClass {
+    is_negated: bool,
+    // inclusive ranges, a single character is a range of one
+    ranges: Vec<(char, char)>,
+}
+
+impl CharClass {
+    fn contains(&self, c: char) -> bool {
+        let is_in_range = self.ranges.iter().any(|(low, high)| (*low..=*high).contains(&c));
+        is_in_range != self.is_negated
+    }
+}
+
+enum Node {
+    Char(char),
+    Any,
+    Class(CharClass),
+    Concat(Vec<Node>),
+    Alternation(Vec<Node>),
+    Repeat {
+        node: Box<Node>,
+        min: usize,
+        max: Option<usize>,
+    },
+}
+
+impl Node {
+    fn any_string() -> Node {
+        Node::Repeat {
+            node: Box::new(Node::Any),
+            min: 0,
+            max: None,
+        }
+    }
+}
+
+impl Pattern {
+    /// `%` matches any string and `_` any character, `escape` makes the character after it literal.
+    pub fn like(pattern: &str, escape: Option<char>, is_case_insensitive: bool) -> Result<Self, &'static str> {
+        let (pattern, escape) = if is_case_insensitive {
+            (pattern.to_lowercase(), escape.map(lowercase))
+        } else {
+            (pattern.to_string(), escape)
+        };
+
+        let mut nodes = vec![];
+        let mut chars = pattern.chars();
+        while let Some(c) = chars.next() {
+            nodes.push(match c {
+                c if Some(c) == escape => Node::Char(chars.next().ok_or(ENDS_WITH_ESCAPE)?),
+                '%' => Node::any_string(),
+                '_' => Node::Any,
+                c => Node::Char(c),
+            });
+        }
+        Pattern::compile(&Node::Concat(nodes), is_case_insensitive)
+    }
+
+    /// Like `like`, and also `|` alternatives, `( )` groups, `[ ]` classes and the `*`, `+`, `?`
+    /// and `{m,n}` repetitions of regular expressions.
+    pub fn similar(pattern: &str, escape: Option<char>) -> Result<Self, &'static str> {
+        let mut parser = SimilarParser {
+            chars: pattern.chars().collect(),
+            position: 0,
+            escape,
+        };
+        let node = parser.parse_alternation()?;
+        if parser.position < parser.chars.len() {
+            return Err("unmatched right paren");
+        }
+        Pattern::compile(&node, false)
+    }
+
+    fn compile(node: &Node, is_case_insensitive: bool) -> Result<Self, &'static str> {
+        let mut pattern = Pattern {
+            states: vec![State::Match],
+            start: 0,
+            is_case_insensitive,
+        };
+        pattern.start = pattern.add_node(node, 0)?;
+        Ok(pattern)
+    }
+
+    // adds the states matching `node` and then continuing with `next`, returns the first one.
+    fn add_node(&mut self, node: &Node, next: usize) -> Result<usize, &'static str> {
+        Ok(match node {
+            Node::Char(c) => self.add_state(State::Char(*c, next))?,
+            Node::Any => self.add_state(State::Any(next))?,
+            Node::Class(class) => self.add_state(State::Class(class.clone(), next))?,
+            Node::Concat(nodes) => nodes.iter().rev().try_fold(next, |next, node| self.add_node(node, next))?,
+            Node::Alternation(nodes) => {
+                let (last, others) = nodes.split_last().expect("an alternation has alternatives");
+                let mut start = self.add_node(last, next)?;
+                for node in others.iter().rev() {
+                    let alternative = self.add_node(node, next)?;
+                    start = self.add_state(State::Split(alternative, start))?;
+                }
+                start
+            }
+            Node::Repeat { node, min, max } => {
+                let mut start = match max {
+                    // the loop is closed once its split exists.
+                    None => {
+                        let split = self.add_state(State::Split(next, next))?;
+                        let body = self.add_node(node, split)?;
+                        self.states[split] = State::Split(body, next);
+                        split
+                    }
+                    Some(max) => {
+                        let mut start = next;
+                        for _ in *min..*max {
+                            let body = self.add_node(node, start)?;
+                            start = self.add_state(State::Split(body, next))?;
+                        }
+                        start
+                    }
+                };
+                for _ in 0..*min {
+                    start = self.add_node(node, start)?;
+                }
+                start
+            }
+        })
+    }
+
+    fn add_state(&mut self, state: State) -> Result<usize, &'static str> {
+        if self.states.len() == MAX_STATES {
+            return Err("pattern is too large");
+        }
+        self.states.push(state);
+        Ok(self.states.len() - 1)
+    }
+
+    pub fn is_match(&self, text: &str) -> bool {
+        let text = if self.is_case_insensitive {
+            text.to_lowercase()
+        } else {
+            text.to_string()
+        };
+
+        let mut is_added = vec![false; self.states.len()];
+        let mut current = vec![];
+        self.follow(self.start, &mut current, &mut is_added);
+        for c in text.chars() {
+            is_added.iter_mut().for_each(|is_added| *is_added = false);
+            let mut next_states = vec![];
+            for state in &current {
+                let next = match &self.states[*state] {
+                    State::Char(expected, next) if *expected == c => *next,
+                    State::Any(next) => *next,
+                    State::Class(class, next) if class.contains(c) => *next,
+                    _ => continue,
+                };
+                self.follow(next, &mut next_states, &mut is_added);
+            }
+            if next_states.is_empty() {
+                return false;
+            }
+            current = next_states;
+        }
+        current.iter().any(|state| matches!(self.states[*state], State::Match))
+    }
+
+    // adds `state` to `states`, or the states its splits lead to. The splits are followed with a
+    // stack of their own, chains of them can be as long as the pattern.
+    fn follow(&self, state: usize, states: &mut Vec<usize>, is_added: &mut [bool]) {
+        let mut pending = vec![state];
+        while let Some(state) = pending.pop() {
+            if is_added[state] {
+                continue;
+            }
+            is_added[state] = true;
+            match self.states[state] {
+                State::Split(first, second) => pending.extend([second, first]),
+                _ => states.push(state),
+            }
+        }
+    }
+}
+
+fn lowercase(c: char) -> char {
+    c.to_lowercase().next().unwrap_or(c)
+}
+
+struct SimilarParser {
+    chars: Vec<char>,
+    position: usize,
+    escape: Option<char>,
+}
+
+impl SimilarParser {
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.position).copied()
+    }
+
+    fn next_char(&mut self) -> Option<char> {
+        let c = self.peek();
+        self.position += 1;
+        c
+    }
+
+    fn consume(&mut self, c: char) -> bool {
+        if self.peek() == Some(c) {
+            self.position += 1;
+            return true;
+        }
+        false
+    }
+
+    // $concat [...| $concat]
+    fn parse_alternation(&mut self) -> Result<Node, &'static str> {
+        let mut alternatives = vec![self.parse_concat()?];
+        while self.consume('|') {
+            alternatives.push(self.parse_concat()?);
+        }
+        if alternatives.len() == 1 {
+            return Ok(alternatives.remove(0));
+        }
+        Ok(Node::Alternation(alternatives))
+    }
+
+    // [...$atom [...* | + | ? | {m} | {m,} | {m,n}]]
+    fn parse_concat(&mut self) -> Result<Node, &'static str> {
+        let mut nodes = vec![];
+        while let Some(c) = self.peek() {
+            if c == '|' || c == ')' {
+                break;
+            }
+            let mut node = self.parse_atom()?;
+            while let Some((min, max)) = self.parse_repetition()? {
+                node = Node::Repeat {
+                    node: Box::new(node),
+                    min,
+                    max,
+                };
+            }
+            nodes.push(node);
+        }
+        Ok(Node::Concat(nodes))
+    }
+
+    fn parse_repetition(&mut self) -> Result<Option<(usize, Option<usize>)>, &'static str> {
+        let repetition = match self.peek() {
+            Some('*') => (0, None),
+            Some('+') => (1, None),
+            Some('?') => (0, Some(1)),
+            Some('{') => {
+                self.position += 1;
+                let min = self.parse_count()?.ok_or("expected a repetition count")?;
+                let max = if self.consume(',') { self.parse_count()? } else { Some(min) };
+                if self.peek() != Some('}') {
+                    return Err("unterminated repetition");
+                }
+                if max.is_some_and(|max| max < min) {
+                    return Err("invalid repetition count");
+                }
+                (min, max)
+            }
+            _ => return Ok(None),
+        };
+        self.position += 1;
+        Ok(Some(repetition))
+    }
+
+    fn parse_count(&mut self) -> Result<Option<usize>, &'static str> {
+        let start = self.position;
+        while self.peek().is_some_and(|c| c.is_ascii_digit()) {
+            self.position += 1;
+        }
+        if self.position == start {
+            return Ok(None);
+        }
+        let digits: String = self.chars[start..self.position].iter().collect();
+        match digits.parse::<usize>() {
+            Ok(count) if count <= MAX_REPETITION => Ok(Some(count)),
+            _ => Err("repetition count is too large"),
+        }
+    }
+
+    fn parse_atom(&mut self) -> Result<Node, &'static str> {
+        let c = self.next_char().ok_or("expected a pattern")?;
+        Ok(match c {
+            c if Some(c) == self.escape => Node::Char(self.next_char().ok_or(ENDS_WITH_ESCAPE)?),
+            '%' => Node::any_string(),
+            '_' => Node::Any,
+            '(' => {
+                let node = self.parse_alternation()?;
+                if !self.consume(')') {
+                    return Err("unmatched left paren");
+                }
+                node
+            }
+            '[' => Node::Class(self.parse_class()?),
+            '*' | '+' | '?' | '{' => return Err("repetition without a pattern to repeat"),
+            c => Node::Char(c),
+        })
+    }
+
+    // [ [^] [...$char [- $char]] ]
+    fn parse_class(&mut self) -> Result<CharClass, &'static str> {
+        let is_negated = self.consume('^');
+        let mut ranges = vec![];
+        loop {
+            let low = match self.next_char().ok_or("unterminated character class")? {
+                ']' => break,
+                c if Some(c) == self.escape => self.next_char().ok_or("unterminated character class")?,
+                c => c,
+            };
+            let is_range =
+                self.peek() == Some('-') && self.chars.get(self.position + 1).is_some_and(|c| *c != ']');
+            if is_range {
+                self.position += 1;
+                let high = self.next_char().ok_or("unterminated character class")?;
+                if high < low {
+                    return Err("invalid character range");
+                }
+                ranges.push((low, high));
+            } else {
+                ranges.push((low, low));
+            }
+        }
+        Ok(CharClass { is_negated, ranges })
+    }
+}
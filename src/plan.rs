@@ -0,0 +1,222 @@
+// the plans `explain` shows, a tree of the operators a query runs, and the row counts and
+// times `explain analyze` adds to them.
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::time::Duration;
+
+use crate::ast::QueryId;
+
+pub struct PlanNode {
+    pub operator: &'static str,
+    // what the operator reads, e.g. the table of a scan
+    pub relation: Option<String>,
+    // e.g. ("Filter", "(salary > 10)"), in the order they are shown
+    pub details: Vec<(&'static str, String)>,
+    pub children: Vec<PlanNode>,
+    // how the node is used by its parent when it isn't one of its inputs, e.g. "SubPlan"
+    pub relationship: Option<String>,
+    // only for `explain analyze`, `None` when the operator never ran
+    pub actual: Option<NodeStats>,
+}
+
+// what an operator did over all the times it ran.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct NodeStats {
+    pub rows: usize,
+    pub loops: usize,
+    pub time: Duration,
+}
+
+// the operators of a single select, a plan node is the stage of the select that runs it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Stage {
+    // the scan or join at this position of the from clause, counted in preorder
+    From(usize),
+    Aggregate,
+    Window,
+    Project,
+    // the order by, distinct and limit or offset applied to the projected rows, of the select or of
+    // its set operations
+    Sort,
+    Unique,
+    Limit,
+    // the common table at this position of the with clause, only recorded for recursive ones
+    CommonTable(usize),
+    // the set operation at this position
+    SetOperation(usize),
+    // the projection of the rows of the set operations
+    Combine,
+    // the insert, update or delete being explained, its scan is `From(0)`
+    Modify,
+}
+
+/// The row counts and times of the operators run by an `explain analyze`.
+///
+/// Selects are told apart by their ids, which the copies the executor works on keep, like the
+/// subqueries of a rewritten select list. The same select written twice has two ids and two
+/// entries. The operators of an explained insert, update or delete have no select.
+#[derive(Default)]
+pub struct Profile {
+    nodes: RefCell<HashMap<(Option<usize>, Stage), NodeStats>>,
+}
+
+impl Profile {
+    pub fn record(&self, query: QueryId, stage: Stage, rows: usize, time: Duration) {
+        self.add(Some(query.0), stage, rows, time);
+    }
+
+    pub fn stats(&self, query: QueryId, stage: Stage) -> Option<NodeStats> {
+        self.find(Some(query.0), stage)
+    }
+
+    pub fn record_write(&self, stage: Stage, rows: usize, time: Duration) {
+        self.add(None, stage, rows, time);
+    }
+
+    pub fn write_stats(&self, stage: Stage) -> Option<NodeStats> {
+        self.find(None, stage)
+    }
+
+    fn add(&self, query_id: Option<usize>, stage: Stage, rows: usize, time: Duration) {
+        let mut nodes = self.nodes.borrow_mut();
+        let stats = nodes.entry((query_id, stage)).or_default();
+        stats.rows += rows;
+        stats.loops += 1;
+        stats.time += time;
+    }
+
+    fn find(&self, query_id: Option<usize>, stage: Stage) -> Option<NodeStats> {
+        self.nodes.borrow().get(&(query_id, stage)).copied()
+    }
+}
+
+impl PlanNode {
+    pub fn new(operator: &'static str) -> Self {
+        PlanNode {
+            operator,
+            relation: None,
+            details: vec![],
+            children: vec![],
+            relationship: None,
+            actual: None,
+        }
+    }
+
+    /// The plan as lines of text, like postgres shows it. `execution_time` is only given by
+    /// `explain analyze`, the nodes without stats are then marked as never executed.
+    pub fn text_lines(&self, execution_time: Option<Duration>) -> Vec<String> {
+        let mut lines = vec![];
+        self.add_text_lines(&mut lines, 0, "", execution_time.is_some());
+        if let Some(execution_time) = execution_time {
+            lines.push(format!("Execution Time: {:.3} ms", milliseconds(execution_time)));
+        }
+        lines
+    }
+
+    fn add_text_lines(&self, lines: &mut Vec<String>, indent: usize, arrow: &str, is_analyze: bool) {
+        let mut line = format!("{:indent$}{}{}", "", arrow, self.operator, indent = indent);
+        if let Some(relation) = &self.relation {
+            line.push_str(" on ");
+            line.push_str(relation);
+        }
+        match self.actual {
+            Some(stats) => line.push_str(&format!(
+                "  (actual rows={} loops={} time={:.3} ms)",
+                stats.rows,
+                stats.loops,
+                milliseconds(stats.time)
+            )),
+            None if is_analyze => line.push_str("  (never executed)"),
+            None => {}
+        }
+        lines.push(line);
+
+        let indent = indent + arrow.len() + 2;
+        for (name, value) in &self.details {
+            lines.push(format!("{:indent$}{}: {}", "", name, value, indent = indent));
+        }
+        for child in &self.children {
+            match &child.relationship {
+                Some(relationship) => {
+                    lines.push(format!("{:indent$}{}", "", relationship, indent = indent));
+                    child.add_text_lines(lines, indent + 2, "->  ", is_analyze);
+                }
+                None => child.add_text_lines(lines, indent, "->  ", is_analyze),
+            }
+        }
+    }
+
+    /// The plan as JSON, with the keys postgres uses where there is one.
+    pub fn to_json(&self, execution_time: Option<Duration>) -> String {
+        let mut json = String::from("[\n  {\n    \"Plan\": ");
+        self.write_json(&mut json, 4);
+        if let Some(execution_time) = execution_time {
+            json.push_str(&format!(",\n    \"Execution Time\": {:.3}", milliseconds(execution_time)));
+        }
+        json.push_str("\n  }\n]");
+        json
+    }
+
+    fn write_json(&self, json: &mut String, indent: usize) {
+        let mut fields = vec![("Node Type", json_string(self.operator))];
+        if let Some(relationship) = &self.relationship {
+            fields.push(("Parent Relationship", json_string(relationship)));
+        }
+        if let Some(relation) = &self.relation {
+            fields.push(("Relation Name", json_string(relation)));
+        }
+        for (name, value) in &self.details {
+            fields.push((*name, json_string(value)));
+        }
+        if let Some(stats) = self.actual {
+            fields.push(("Actual Rows", stats.rows.to_string()));
+            fields.push(("Actual Loops", stats.loops.to_string()));
+            fields.push(("Actual Total Time", format!("{:.3}", milliseconds(stats.time))));
+        }
+
+        json.push_str("{\n");
+        let inner = indent + 2;
+        for (index, (name, value)) in fields.iter().enumerate() {
+            let separator = if index + 1 < fields.len() || !self.children.is_empty() { "," } else { "" };
+            json.push_str(&format!(
+                "{:inner$}{}: {}{}\n",
+                "",
+                json_string(name),
+                value,
+                separator,
+                inner = inner
+            ));
+        }
+        if !self.children.is_empty() {
+            json.push_str(&format!("{:inner$}\"Plans\": [\n", "", inner = inner));
+            for (index, child) in self.children.iter().enumerate() {
+                json.push_str(&format!("{:indent$}", "", indent = inner + 2));
+                child.write_json(json, inner + 2);
+                json.push_str(if index + 1 < self.children.len() { ",\n" } else { "\n" });
+            }
+            json.push_str(&format!("{:inner$}]\n", "", inner = inner));
+        }
+        json.push_str(&format!("{:indent$}}}", "", indent = indent));
+    }
+}
+
+fn milliseconds(time: Duration) -> f64 {
+    time.as_secs_f64() * 1000.0
+}
+
+fn json_string(value: &str) -> String {
+    let mut json = String::from("\"");
+    for c in value.chars() {
+        match c {
+            '"' => json.push_str("\\\""),
+            '\\' => json.push_str("\\\\"),
+            '\n' => json.push_str("\\n"),
+            '\t' => json.push_str("\\t"),
+            c if (c as u32) < 0x20 => json.push_str(&format!("\\u{:04x}", c as u32)),
+            c => json.push(c),
+        }
+    }
+    json.push('"');
+    json
+}
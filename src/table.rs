@@ -1,91 +1,895 @@
-use std::collections::HashMap;
+use std::borrow::Cow;
+use std::cell::RefCell;
+use std::cmp::Ordering;
+use std::collections::{HashMap, HashSet};
+use std::hash::{Hash, Hasher};
+use std::iter;
+use std::time::{Duration, Instant};
 
-use crate::ast::{CreateStatement, InsertStatement, SelectItem, SelectStatement, FromSource};
-use crate::database::{CellType, Column, Database, QueryResult};
-use crate::lexer::Token;
+use crate::ast::{
+    Assignment, BinaryOperator, CommonTableExpression, ConflictAction, CreateStatement, CreateViewStatement,
+    DeallocateStatement, DeleteStatement, Distinct, DropKind, DropStatement, ExecuteStatement, ExplainFormat,
+    ExplainStatement, Expression, FrameBound, FromSource, FunctionCall, Identity, InsertSource, InsertStatement,
+    JoinConstraint, JoinKind, LikeOperator, OnConflict, OrderByItem, PrepareStatement, QueryId, References,
+    ReferentialAction, Returning, SelectItem, SelectStatement, SetOperation, SetOperator, SetStatement, Statement,
+    TableConstraint, TableReference, UnaryOperator, UpdateStatement, WhenClause, WindowSpec,
+};
+use crate::database::{numbered_name, CellType, Check, Column, Database, Prepared, PreparedStatement, QueryResult};
+use crate::lexer::{Token, TokenKind};
+use crate::pattern::Pattern;
+use crate::plan::{PlanNode, Profile, Stage};
 
 #[derive(Default)]
 pub struct Memory {
     tables: HashMap<String, Table>,
+    views: HashMap<String, View>,
+    recursion_limit: RecursionLimit,
+    // the statements of `prepare name as ...`
+    prepared_statements: HashMap<String, Prepared>,
+}
+
+/// How far a `with recursive` query can go before it is stopped with an error.
+#[derive(Debug, Clone, Copy)]
+pub struct RecursionLimit {
+    // the number of times the recursive part can run, `set max_recursion_depth = ...`
+    pub max_depth: usize,
+    // the number of rows the query can produce, `set max_recursion_rows = ...`
+    pub max_rows: usize,
+}
+
+impl Default for RecursionLimit {
+    fn default() -> Self {
+        RecursionLimit {
+            max_depth: 1000,
+            max_rows: 100_000,
+        }
+    }
 }
 
 impl Database for Memory {
     fn run_query(&mut self, query: &str) -> std::result::Result<Option<QueryResult>, Box<dyn std::error::Error>> {
-        let insert_stmt = InsertStatement::from_source(query)?;
-        let select_stmt = SelectStatement::from_source(query)?;
-        let create_stmt = CreateStatement::from_source(query)?;
-        if let Some(stmt) = insert_stmt {
-            self.insert(stmt)?;
-            Ok(None)
-        } else if let Some(stmt) = select_stmt {
-            let result = self.select(stmt)?;
-            Ok(Some(result))
-        } else if let Some(stmt) = create_stmt {
-            self.create_table(stmt)?;
-            Ok(None)
-        } else {
-            Err(Box::new(MemoryError::QueryNotValid))
+        match Statement::from_source(query)? {
+            Some(statement) => Ok(self.run_statement(statement)?),
+            None => Err(Box::new(MemoryError::QueryNotValid)),
         }
     }
 
-    fn insert(&mut self, insert_statement: InsertStatement) -> Result<()> {
-        let table = self.get_table_mut(&insert_statement.table.value)?;
-        let mut row = Vec::<CellValue>::new();
+    fn prepare(&self, query: &str) -> std::result::Result<PreparedStatement, Box<dyn std::error::Error>> {
+        let statement = match Statement::from_source(query)? {
+            Some(
+                statement @ (Statement::Select(_)
+                | Statement::Insert(_)
+                | Statement::Update(_)
+                | Statement::Delete(_)),
+            ) => statement,
+            Some(_) => return Err(Box::new(MemoryError::NotPreparable)),
+            None => return Err(Box::new(MemoryError::QueryNotValid)),
+        };
+        let prepared = self.prepared(statement, &[])?;
+        Ok(PreparedStatement::new(prepared))
+    }
 
-        for value_token in insert_statement.values {
-            row.push(CellValue::from(value_token));
+    fn execute_prepared(&mut self, prepared: &Prepared, parameters: &[CellValue]) -> Result<Option<QueryResult>> {
+        let types = &prepared.parameter_types;
+        if parameters.len() != types.len() {
+            return Err(MemoryError::WrongNumberOfParameters(types.len(), parameters.len()));
+        }
+        for (index, (value, cell_type)) in parameters.iter().zip(types).enumerate() {
+            if let Some(cell_type) = cell_type {
+                if !is_parameter_type(value, cell_type) {
+                    return Err(MemoryError::InvalidParameter(index + 1, value.to_string(), cell_type.clone()));
+                }
+            }
         }
 
-        table.insert_row(row);
+        match &prepared.statement {
+            Statement::Select(query) => {
+                let patterns = PatternCache::default();
+                let outer = RowContext {
+                    memory: Some(self),
+                    patterns: Some(&patterns),
+                    parameters,
+                    ..Default::default()
+                };
+                Ok(Some(self.query(query, &outer)?))
+            }
+            Statement::Insert(insert_statement) => self.run_insert(insert_statement, parameters, None),
+            Statement::Update(update_statement) => self.run_update(update_statement, parameters, None),
+            Statement::Delete(delete_statement) => self.run_delete(delete_statement, parameters, None),
+            _ => Err(MemoryError::NotPreparable),
+        }
+    }
 
-        Ok(())
+    fn insert(&mut self, insert_statement: InsertStatement) -> Result<Option<QueryResult>> {
+        self.run_insert(&insert_statement, &[], None)
     }
 
     fn select(&self, select_statement: SelectStatement) -> Result<QueryResult> {
-        let table = self.get_table(&select_statement.table_name.value)?;
+        let patterns = PatternCache::default();
+        let outer = RowContext {
+            memory: Some(self),
+            patterns: Some(&patterns),
+            ..Default::default()
+        };
+        self.query(&select_statement, &outer)
+    }
+
+    fn explain(&mut self, explain_statement: ExplainStatement) -> Result<QueryResult> {
+        let profile = Profile::default();
+        let profile = if explain_statement.is_analyze { Some(&profile) } else { None };
+        let (plan, execution_time) = match explain_statement.statement.as_ref() {
+            Statement::Select(query) => self.explain_query(query, profile)?,
+            statement => self.explain_write(statement, profile)?,
+        };
+        let lines = match explain_statement.format {
+            ExplainFormat::Text => plan.text_lines(execution_time),
+            ExplainFormat::Json => vec![plan.to_json(execution_time)],
+        };
+
+        Ok(text_result("QUERY PLAN", lines))
+    }
+
+    fn create_table(&mut self, create_statement: CreateStatement) -> Result<Option<QueryResult>> {
+        let table_name = create_statement.name.value.clone();
+        if let Err(err) = self.check_name_is_free(&table_name) {
+            return skip_if(create_statement.is_if_not_exists, err);
+        }
+
+        let table = match &create_statement.query {
+            Some(query) => self.create_table_as(&table_name, query)?,
+            None => Table::from_create_statement(create_statement, &self.tables)?,
+        };
+
+        self.insert_table(&table_name, table);
+
+        Ok(None)
+    }
+
+    fn update(&mut self, update_statement: UpdateStatement) -> Result<Option<QueryResult>> {
+        self.run_update(&update_statement, &[], None)
+    }
+
+    fn delete(&mut self, delete_statement: DeleteStatement) -> Result<Option<QueryResult>> {
+        self.run_delete(&delete_statement, &[], None)
+    }
+
+    fn create_view(&mut self, create_view_statement: CreateViewStatement) -> Result<Option<QueryResult>> {
+        let name = create_view_statement.name.value.clone();
+        if let Err(err) = self.check_name_is_free(&name) {
+            return skip_if(create_view_statement.is_if_not_exists, err);
+        }
+
+        // describing the query checks that the tables and columns it reads exist.
+        let view = View {
+            columns: create_view_statement.columns,
+            query: *create_view_statement.query,
+            relations: vec![],
+        };
+        let patterns = PatternCache::default();
+        let outer = RowContext {
+            memory: Some(self),
+            patterns: Some(&patterns),
+            ..Default::default()
+        };
+        rename_columns(self.describe(&view.query, &outer)?, &name, &view.columns)?;
+
+        let relations = query_relations(&view.query);
+        self.views.insert(name, View { relations, ..view });
+        Ok(None)
+    }
 
-        let mut rows = Vec::<Vec<Cell>>::new();
-        for (row_index, _) in table.rows.iter().enumerate() {
-            let mut row_cells = Vec::<Cell>::new();
-            for select_item in &select_statement.items {
-                let cell = table.get_cell(select_item, row_index)?;
-                row_cells.push(cell);
+    fn drop(&mut self, drop_statement: DropStatement) -> Result<Option<QueryResult>> {
+        let name = drop_statement.name.value;
+        // a table or view of the other kind isn't missing, `if exists` doesn't skip it.
+        let missing = match drop_statement.kind {
+            DropKind::Table if self.views.contains_key(&name) => {
+                return Err(MemoryError::WrongObjectKind(name, "table".to_string()))
             }
-            rows.push(row_cells);
+            DropKind::View if self.tables.contains_key(&name) => {
+                return Err(MemoryError::WrongObjectKind(name, "view".to_string()))
+            }
+            DropKind::Table => self.get_table(&name).err(),
+            DropKind::View if self.views.contains_key(&name) => None,
+            DropKind::View => Some(MemoryError::ViewNotFound(name.clone())),
+        };
+        if let Some(err) = missing {
+            return skip_if(drop_statement.is_if_exists, err);
         }
 
-        let mut columns = Vec::<Column>::new();
-        for select_item in select_statement.items {
-            let (column, _) = table.get_column_from_select_item(&select_item)?;
-            columns.push(column);
+        let dependents = self.dependent_views(&name);
+        if !dependents.is_empty() && !drop_statement.is_cascade {
+            return Err(MemoryError::DependentViews(name, dependents));
+        }
+        // a cascade drops the foreign keys of the other tables, not the tables.
+        let foreign_keys: Vec<String> = self
+            .referencing_keys(&name)
+            .into_iter()
+            .filter(|(table_name, _)| *table_name != name)
+            .map(|(_, foreign_key)| foreign_key.name)
+            .collect();
+        if !foreign_keys.is_empty() && !drop_statement.is_cascade {
+            return Err(MemoryError::DependentForeignKeys(name, foreign_keys));
+        }
+        for table in self.tables.values_mut() {
+            table.foreign_keys.retain(|foreign_key| foreign_key.referenced_table != name);
         }
+        for dependent in &dependents {
+            self.views.remove(dependent);
+        }
+        self.tables.remove(&name);
+        self.views.remove(&name);
+        Ok(None)
+    }
+}
 
-        Ok(QueryResult {
-            rows,
-            columns
+impl Memory {
+    // the plan of a select, `profile` is only given by `explain analyze`, which runs the query.
+    fn explain_query(
+        &self,
+        query: &SelectStatement,
+        profile: Option<&Profile>,
+    ) -> Result<(PlanNode, Option<Duration>)> {
+        let patterns = PatternCache::default();
+        let outer = RowContext {
+            memory: Some(self),
+            patterns: Some(&patterns),
+            profile,
+            ..Default::default()
+        };
+        self.describe(query, &outer)?;
+
+        let execution_time = if profile.is_some() {
+            let start = Instant::now();
+            self.query(query, &outer)?;
+            Some(start.elapsed())
+        } else {
+            None
+        };
+        Ok((self.plan(query, &[], profile), execution_time))
+    }
+
+    // like `explain_query` for an insert, update or delete. `explain analyze` runs the statement and
+    // then puts back every table as it was, including the ones the foreign key actions changed.
+    fn explain_write(
+        &mut self,
+        statement: &Statement,
+        profile: Option<&Profile>,
+    ) -> Result<(PlanNode, Option<Duration>)> {
+        let execution_time = match profile {
+            Some(profile) => {
+                let original_tables = self.tables.clone();
+                let start = Instant::now();
+                let result = match statement {
+                    Statement::Insert(insert_statement) => self.run_insert(insert_statement, &[], Some(profile)),
+                    Statement::Update(update_statement) => self.run_update(update_statement, &[], Some(profile)),
+                    Statement::Delete(delete_statement) => self.run_delete(delete_statement, &[], Some(profile)),
+                    _ => Err(MemoryError::QueryNotValid),
+                };
+                let execution_time = start.elapsed();
+                self.tables = original_tables;
+                result?;
+                Some(execution_time)
+            }
+            None => None,
+        };
+        Ok((self.plan_write(statement, profile)?, execution_time))
+    }
+
+    fn run_statement(&mut self, statement: Statement) -> Result<Option<QueryResult>> {
+        match statement {
+            Statement::Insert(stmt) => self.insert(stmt),
+            Statement::Select(stmt) => Ok(Some(self.select(*stmt)?)),
+            Statement::Create(stmt) => self.create_table(stmt),
+            Statement::Update(stmt) => self.update(stmt),
+            Statement::Delete(stmt) => self.delete(stmt),
+            Statement::Explain(stmt) => Ok(Some(self.explain(*stmt)?)),
+            Statement::CreateView(stmt) => self.create_view(stmt),
+            Statement::Drop(stmt) => self.drop(stmt),
+            Statement::Prepare(stmt) => self.prepare_statement(stmt),
+            Statement::Execute(stmt) => self.execute_statement(stmt),
+            Statement::Deallocate(stmt) => self.deallocate(stmt),
+            Statement::Set(stmt) => self.set(stmt),
+        }
+    }
+
+    // `parameters` are the values of the parameters of a prepared statement and `profile` is where
+    // `explain analyze` records the operators, like for update and delete.
+    fn run_insert(
+        &mut self,
+        insert_statement: &InsertStatement,
+        parameters: &[CellValue],
+        profile: Option<&Profile>,
+    ) -> Result<Option<QueryResult>> {
+        let start = Instant::now();
+        let table = self.get_table(&insert_statement.table.value)?;
+        let returning = self.returning_items(table, insert_statement.returning.as_ref())?;
+
+        // the values can't reference any columns, but they can be subqueries.
+        let patterns = PatternCache::default();
+        let context = RowContext {
+            memory: Some(self),
+            patterns: Some(&patterns),
+            parameters,
+            profile,
+            ..Default::default()
+        };
+        // a query is run to the end before any of its rows is stored, so it doesn't see them.
+        let rows = match &insert_statement.source {
+            InsertSource::Values(expressions) => {
+                let mut values = Vec::<CellValue>::new();
+                for value in expressions {
+                    values.push(context.evaluate(value)?);
+                }
+                record_write(profile, Stage::From(0), 1, start);
+                vec![values]
+            }
+            InsertSource::Query(query) => result_values(self.query(query, &context)?),
+        };
+
+        let table_name = &insert_statement.table.value;
+        let table = self.get_table_mut(table_name)?;
+        let original_row_count = table.rows.len();
+        let upsert = match &insert_statement.on_conflict {
+            Some(on_conflict) => Some(Upsert {
+                on_conflict,
+                keys: table.conflict_keys(&on_conflict.columns)?,
+                assignments: match &on_conflict.action {
+                    ConflictAction::Nothing => vec![],
+                    ConflictAction::Update { assignments, .. } => table.assigned_columns(assignments)?,
+                },
+                first_new_row: original_row_count,
+                patterns: &patterns,
+                parameters,
+            }),
+            None => None,
+        };
+
+        // either every row is stored or none of them, the rows an upsert updates are put back from their copies.
+        let mut updated_rows = Vec::<(usize, Vec<CellValue>)>::new();
+        let mut written_rows = Vec::<usize>::new();
+        let result = rows.into_iter().try_for_each(|values| {
+            let row = table.build_row(&insert_statement.columns, values)?;
+            if let Some(upsert) = &upsert {
+                written_rows.extend(table.upsert_row(row, upsert, &mut updated_rows)?);
+                return Ok(());
+            }
+            table.check_row(&row, None)?;
+            written_rows.push(table.rows.len());
+            table.insert_row(row);
+            Ok(())
+        });
+        let result = result.and_then(|()| {
+            let table = self.get_table(table_name)?;
+            let changes = Changes {
+                table: table_name.clone(),
+                inserted: table.rows[original_row_count..].to_vec(),
+                updated: updated_rows
+                    .iter()
+                    .map(|(row_index, row)| (row.clone(), table.rows[*row_index].clone()))
+                    .collect(),
+                ..Default::default()
+            };
+            self.enforce_foreign_keys(changes, |memory| {
+                let table = memory.get_table(table_name)?;
+                let rows: Vec<&[CellValue]> = written_rows
+                    .iter()
+                    .map(|&row_index| table.rows[row_index].as_slice())
+                    .collect();
+                memory.returning(table, returning.as_deref(), &rows, parameters)
+            })
+        });
+        if result.is_err() {
+            let table = self.get_table_mut(table_name)?;
+            table.rows.truncate(original_row_count);
+            for (row_index, row) in updated_rows {
+                table.rows[row_index] = row;
+            }
+        } else {
+            record_write(profile, Stage::Modify, written_rows.len(), start);
+        }
+        result
+    }
+
+    fn run_update(
+        &mut self,
+        update_statement: &UpdateStatement,
+        parameters: &[CellValue],
+        profile: Option<&Profile>,
+    ) -> Result<Option<QueryResult>> {
+        let start = Instant::now();
+        let table = self.get_table(&update_statement.table.value)?;
+        let returning = self.returning_items(table, update_statement.returning.as_ref())?;
+
+        let assignments = table.assigned_columns(&update_statement.assignments)?;
+
+        // the new rows are computed before any of them is stored, so subqueries see the table as it was.
+        let patterns = PatternCache::default();
+        let outer = RowContext {
+            memory: Some(self),
+            patterns: Some(&patterns),
+            parameters,
+            profile,
+            ..Default::default()
+        };
+        let new_rows = table.updated_rows(&assignments, update_statement.where_clause.as_ref(), &outer)?;
+        record_write(profile, Stage::From(0), new_rows.len(), start);
+
+        let row_indexes: Vec<usize> = new_rows.iter().map(|(row_index, _)| *row_index).collect();
+
+        // an update either changes every matching row or none of them.
+        let table_name = &update_statement.table.value;
+        let table = self.get_table_mut(table_name)?;
+        let original_rows = table.rows.clone();
+        let result = table.replace_rows(new_rows).and_then(|()| {
+            let table = self.get_table(table_name)?;
+            let changes = Changes {
+                table: table_name.clone(),
+                updated: row_indexes
+                    .iter()
+                    .map(|&row_index| (original_rows[row_index].clone(), table.rows[row_index].clone()))
+                    .collect(),
+                ..Default::default()
+            };
+            self.enforce_foreign_keys(changes, |memory| {
+                let table = memory.get_table(table_name)?;
+                let rows: Vec<&[CellValue]> = row_indexes
+                    .iter()
+                    .map(|&row_index| table.rows[row_index].as_slice())
+                    .collect();
+                memory.returning(table, returning.as_deref(), &rows, parameters)
+            })
+        });
+        if result.is_err() {
+            self.get_table_mut(table_name)?.rows = original_rows;
+        } else {
+            record_write(profile, Stage::Modify, row_indexes.len(), start);
+        }
+        result
+    }
+
+    fn run_delete(
+        &mut self,
+        delete_statement: &DeleteStatement,
+        parameters: &[CellValue],
+        profile: Option<&Profile>,
+    ) -> Result<Option<QueryResult>> {
+        let start = Instant::now();
+        let table = self.get_table(&delete_statement.table.value)?;
+        let returning = self.returning_items(table, delete_statement.returning.as_ref())?;
+
+        // the rows are found before any of them is removed, so subqueries see the table as it was.
+        let patterns = PatternCache::default();
+        let outer = RowContext {
+            memory: Some(self),
+            patterns: Some(&patterns),
+            parameters,
+            profile,
+            ..Default::default()
+        };
+        let row_indexes = table.matching_rows(delete_statement.where_clause.as_ref(), &outer)?;
+        record_write(profile, Stage::From(0), row_indexes.len(), start);
+
+        let table_name = &delete_statement.table.value;
+        let deleted_rows = self.get_table_mut(table_name)?.remove_rows(&row_indexes);
+        let changes = Changes {
+            table: table_name.clone(),
+            deleted: deleted_rows.clone(),
+            ..Default::default()
+        };
+        let result = self.enforce_foreign_keys(changes, |memory| {
+            let table = memory.get_table(table_name)?;
+            let rows: Vec<&[CellValue]> = deleted_rows.iter().map(Vec::as_slice).collect();
+            memory.returning(table, returning.as_deref(), &rows, parameters)
+        });
+        if result.is_err() {
+            // the rows go back where they were, the indexes are in increasing order.
+            let table = self.get_table_mut(table_name)?;
+            for (row_index, row) in row_indexes.into_iter().zip(deleted_rows) {
+                table.rows.insert(row_index, row);
+            }
+        } else {
+            record_write(profile, Stage::Modify, deleted_rows.len(), start);
+        }
+        result
+    }
+
+    fn prepare_statement(&mut self, prepare_statement: PrepareStatement) -> Result<Option<QueryResult>> {
+        let name = prepare_statement.name.value;
+        if self.prepared_statements.contains_key(&name) {
+            return Err(MemoryError::PreparedStatementAlreadyExists(name));
+        }
+        let prepared = self.prepared(*prepare_statement.statement, &prepare_statement.parameter_types)?;
+        self.prepared_statements.insert(name, prepared);
+        Ok(None)
+    }
+
+    fn execute_statement(&mut self, execute_statement: ExecuteStatement) -> Result<Option<QueryResult>> {
+        let name = &execute_statement.name.value;
+        let prepared = match self.prepared_statements.get(name) {
+            Some(prepared) => prepared.clone(),
+            None => return Err(MemoryError::PreparedStatementNotFound(name.clone())),
+        };
+
+        let patterns = PatternCache::default();
+        let context = RowContext {
+            memory: Some(self),
+            patterns: Some(&patterns),
+            ..Default::default()
+        };
+        let mut parameters = vec![];
+        for parameter in &execute_statement.parameters {
+            parameters.push(context.evaluate(parameter)?);
+        }
+        self.execute_prepared(&prepared, &parameters)
+    }
+
+    fn deallocate(&mut self, deallocate_statement: DeallocateStatement) -> Result<Option<QueryResult>> {
+        match deallocate_statement.name {
+            Some(name) if self.prepared_statements.remove(&name.value).is_none() => {
+                Err(MemoryError::PreparedStatementNotFound(name.value))
+            }
+            Some(_) => Ok(None),
+            None => {
+                self.prepared_statements.clear();
+                Ok(None)
+            }
+        }
+    }
+
+    fn set(&mut self, set_statement: SetStatement) -> Result<Option<QueryResult>> {
+        let name = set_statement.name.value;
+        let value = evaluate_row_count(Some(&set_statement.value), &name, &[])?
+            .ok_or_else(|| MemoryError::InvalidRowCount(name.clone(), CellValue::Null.to_string()))?;
+        match name.as_str() {
+            "max_recursion_depth" => self.recursion_limit.max_depth = value,
+            "max_recursion_rows" => self.recursion_limit.max_rows = value,
+            _ => return Err(MemoryError::SettingNotFound(name)),
+        }
+        Ok(None)
+    }
+
+    // counts the parameters of `statement` and finds their types. `declared_types` are the types of
+    // the first parameters, the others get the type of what they are stored in or compared to.
+    fn prepared(&self, statement: Statement, declared_types: &[Token]) -> Result<Prepared> {
+        let mut parameter_types = ParameterTypes::default();
+        for token in declared_types {
+            parameter_types.types.push(Some(CellType::parse_token(token)?));
+            parameter_types.is_known.push(true);
+        }
+        let outer = RowContext {
+            memory: Some(self),
+            ..Default::default()
+        };
+        match &statement {
+            Statement::Select(query) => self.infer_query_parameters(query, &outer, &mut parameter_types)?,
+            Statement::Insert(insert_statement) => {
+                let table = self.get_table(&insert_statement.table.value)?;
+                match &insert_statement.source {
+                    InsertSource::Values(values) => {
+                        for (index, value) in values.iter().enumerate() {
+                            let column = match insert_statement.columns.get(index) {
+                                Some(name) => &table.columns[table.get_column_index(&name.value)?],
+                                None if insert_statement.columns.is_empty() && index < table.columns.len() => {
+                                    &table.columns[index]
+                                }
+                                None => break,
+                            };
+                            infer_parameter(value, &column.column_type, &mut parameter_types);
+                        }
+                        for value in values {
+                            self.infer_parameters(value, &outer, &mut parameter_types)?;
+                        }
+                    }
+                    InsertSource::Query(query) => self.infer_query_parameters(query, &outer, &mut parameter_types)?,
+                }
+
+                let columns = qualify_columns(&table.columns, &table.name);
+                let input = outer.nested(&columns);
+                if let Some(OnConflict {
+                    action:
+                        ConflictAction::Update {
+                            assignments,
+                            where_clause,
+                        },
+                    ..
+                }) = &insert_statement.on_conflict
+                {
+                    for assignment in assignments {
+                        let column = &table.columns[table.get_column_index(&assignment.column.value)?];
+                        infer_parameter(&assignment.value, &column.column_type, &mut parameter_types);
+                    }
+                    if let Some(where_clause) = where_clause {
+                        self.infer_parameters(where_clause, &input, &mut parameter_types)?;
+                    }
+                }
+                self.infer_returning_parameters(insert_statement.returning.as_ref(), &input, &mut parameter_types)?;
+            }
+            Statement::Update(update_statement) => {
+                let table = self.get_table(&update_statement.table.value)?;
+                let columns = qualify_columns(&table.columns, &table.name);
+                let input = outer.nested(&columns);
+                for assignment in &update_statement.assignments {
+                    let column = &table.columns[table.get_column_index(&assignment.column.value)?];
+                    infer_parameter(&assignment.value, &column.column_type, &mut parameter_types);
+                    self.infer_parameters(&assignment.value, &input, &mut parameter_types)?;
+                }
+                if let Some(where_clause) = &update_statement.where_clause {
+                    self.infer_parameters(where_clause, &input, &mut parameter_types)?;
+                }
+                self.infer_returning_parameters(update_statement.returning.as_ref(), &input, &mut parameter_types)?;
+            }
+            Statement::Delete(delete_statement) => {
+                let table = self.get_table(&delete_statement.table.value)?;
+                let columns = qualify_columns(&table.columns, &table.name);
+                let input = outer.nested(&columns);
+                if let Some(where_clause) = &delete_statement.where_clause {
+                    self.infer_parameters(where_clause, &input, &mut parameter_types)?;
+                }
+                self.infer_returning_parameters(delete_statement.returning.as_ref(), &input, &mut parameter_types)?;
+            }
+            _ => return Err(MemoryError::NotPreparable),
+        }
+        // like in postgres, the parameters before the last one have to be used or declared.
+        if let Some(index) = parameter_types.is_known.iter().position(|is_known| !is_known) {
+            return Err(MemoryError::UnknownParameterType(index + 1));
+        }
+        Ok(Prepared {
+            statement,
+            parameter_types: parameter_types.types,
         })
     }
 
-    fn create_table(&mut self, create_statement: CreateStatement) -> Result<()> {
-        let table_name = create_statement.name.value.clone();
-        let table_search_result = self.get_table(&table_name);
-        if table_search_result.is_ok() {
-            return Err(MemoryError::TableAlreadyExists(table_name.clone()));
+    fn infer_query_parameters(
+        &self,
+        query: &SelectStatement,
+        outer: &RowContext,
+        types: &mut ParameterTypes,
+    ) -> Result<()> {
+        // like when it runs, each query of the with clause can read the ones before it, and itself when recursive.
+        let common_tables = self.describe_common_tables(&query.with, outer)?;
+        for (index, expression) in query.with.iter().enumerate() {
+            let visible = if expression.is_recursive { index + 1 } else { index };
+            let scope = outer.with_common_tables(&common_tables[..visible]);
+            self.infer_query_parameters(&expression.query, &scope, types)?;
         }
+        let scope = outer.with_common_tables(&common_tables);
 
-        let table = Table::from_create_statement(create_statement)?;
+        let columns = self.source_columns(query, &scope)?;
+        let input = scope.nested(&columns);
+        let mut expressions = query_expressions(query);
+        add_from_expressions(query.from.as_ref(), &mut expressions);
+        for expression in expressions {
+            self.infer_parameters(expression, &input, types)?;
+        }
+        for expression in query.limit.iter().chain(&query.offset) {
+            infer_parameter(expression, &CellType::Int, types);
+        }
+        if let Some(from) = &query.from {
+            add_subquery_sources(from, &mut |subquery| self.infer_query_parameters(subquery, &scope, types))?;
+        }
+        for set_operation in &query.set_operations {
+            self.infer_query_parameters(&set_operation.query, &scope, types)?;
+        }
+        Ok(())
+    }
 
-        self.insert_table(&table_name, table);
+    fn infer_returning_parameters(
+        &self,
+        returning: Option<&Returning>,
+        input: &RowContext,
+        types: &mut ParameterTypes,
+    ) -> Result<()> {
+        if let Some(Returning::Items(items)) = returning {
+            for item in items {
+                self.infer_parameters(&item.expression, input, types)?;
+            }
+        }
+        Ok(())
+    }
+
+    // gives the parameters of `expression` that are compared to a value the type of that value.
+    fn infer_parameters(
+        &self,
+        expression: &Expression,
+        input: &RowContext,
+        types: &mut ParameterTypes,
+    ) -> Result<()> {
+        let mut pairs = Vec::<(&Expression, &Expression)>::new();
+        match expression {
+            Expression::Parameter(index) => add_parameter(*index, types),
+            // the frame offsets aren't children, they are row counts.
+            Expression::Window {
+                window: WindowSpec { frame: Some(frame), .. },
+                ..
+            } => {
+                for bound in [&frame.start, &frame.end].iter() {
+                    if let FrameBound::Preceding(offset) | FrameBound::Following(offset) = bound {
+                        infer_parameter(offset, &CellType::Int, types);
+                    }
+                }
+            }
+            Expression::Binary { left, right, .. } | Expression::IsDistinctFrom { left, right, .. } => {
+                pairs.push((left, right))
+            }
+            Expression::Between {
+                expression, low, high, ..
+            } => {
+                pairs.push((expression, low));
+                pairs.push((expression, high));
+            }
+            Expression::InList { expression, list, .. } => {
+                pairs.extend(list.iter().map(|item| (expression.as_ref(), item)));
+            }
+            Expression::Like {
+                expression,
+                pattern,
+                escape,
+                ..
+            } => {
+                infer_parameter(expression, &CellType::Text, types);
+                infer_parameter(pattern, &CellType::Text, types);
+                if let Some(escape) = escape {
+                    infer_parameter(escape, &CellType::Text, types);
+                }
+            }
+            Expression::Subquery(subquery) | Expression::Exists(subquery) => {
+                self.infer_query_parameters(subquery, input, types)?
+            }
+            Expression::InSubquery {
+                expression, subquery, ..
+            } => {
+                if let [column] = self.describe(subquery, input)?.as_slice() {
+                    infer_parameter(expression, &column.column_type, types);
+                }
+                self.infer_query_parameters(subquery, input, types)?
+            }
+            _ => {}
+        }
+        for (left, right) in pairs {
+            if let Some(right_type) = operand_type(right, input, types) {
+                infer_parameter(left, &right_type, types);
+            }
+            if let Some(left_type) = operand_type(left, input, types) {
+                infer_parameter(right, &left_type, types);
+            }
+        }
 
+        for child in expression.children() {
+            self.infer_parameters(child, input, types)?;
+        }
         Ok(())
     }
 }
 
+// the types of the parameters of a statement being prepared.
 #[derive(Default)]
+struct ParameterTypes {
+    // `None` until a type is inferred or declared
+    types: Vec<Option<CellType>>,
+    // whether each parameter is used or declared, the statement can't skip one
+    is_known: Vec<bool>,
+}
+
+// gives `expression` the type `cell_type` if it is a parameter without a type.
+fn infer_parameter(expression: &Expression, cell_type: &CellType, types: &mut ParameterTypes) {
+    if let Expression::Parameter(index) = expression {
+        add_parameter(*index, types);
+        types.types[index - 1].get_or_insert_with(|| cell_type.clone());
+    }
+}
+
+// counts the parameter `$index`, the statement has at least `index` of them.
+fn add_parameter(index: usize, types: &mut ParameterTypes) {
+    if types.types.len() < index {
+        types.types.resize(index, None);
+        types.is_known.resize(index, false);
+    }
+    types.is_known[index - 1] = true;
+}
+
+// the type a parameter compared to `expression` gets, none when it is a parameter without a type.
+fn operand_type(expression: &Expression, input: &RowContext, types: &ParameterTypes) -> Option<CellType> {
+    match expression {
+        Expression::Parameter(index) => types.types.get(index - 1).cloned().flatten(),
+        _ => expression_type(expression, input).ok(),
+    }
+}
+
+// a value can be bound to a parameter of its own type and null to any of them. numbers are
+// converted to each other like they are when they are stored or compared.
+fn is_parameter_type(value: &CellValue, cell_type: &CellType) -> bool {
+    matches!(
+        (value, cell_type),
+        (CellValue::Null, _)
+            | (CellValue::Int(_) | CellValue::Float(_), CellType::Int | CellType::Float)
+            | (CellValue::Text(_), CellType::Text)
+            | (CellValue::Bool(_), CellType::Bool)
+    )
+}
+
+// records an operator of the insert, update or delete `explain analyze` runs, which started at `start`.
+fn record_write(profile: Option<&Profile>, stage: Stage, rows: usize, start: Instant) {
+    if let Some(profile) = profile {
+        profile.record_write(stage, rows, start.elapsed());
+    }
+}
+
+// a statement with `if [ not ] exists` is skipped with a notice instead of failing with `err`.
+fn skip_if(is_skipped: bool, err: MemoryError) -> Result<Option<QueryResult>> {
+    if !is_skipped {
+        return Err(err);
+    }
+    Ok(Some(text_result("notice", vec![format!("{}, skipping", err)])))
+}
+
+// a named query, it is run in place of the view whenever a query reads it.
+pub struct View {
+    // names for the columns of the query
+    pub columns: Vec<Token>,
+    pub query: SelectStatement,
+    // the tables and views the query reads, they can't be dropped while the view exists
+    pub relations: Vec<String>,
+}
+
+// how an insert with `on conflict` handles the rows that duplicate a key.
+struct Upsert<'a> {
+    on_conflict: &'a OnConflict,
+    // the indexes of the table keys that are checked for duplicates
+    keys: Vec<usize>,
+    // the columns `do update` sets and their values
+    assignments: Vec<(usize, &'a Expression)>,
+    // the rows from this one on were inserted by the same statement
+    first_new_row: usize,
+    patterns: &'a PatternCache,
+    parameters: &'a [CellValue],
+}
+
+// the rows a statement changed in a table, the foreign keys are enforced on them once it ran.
+#[derive(Default)]
+struct Changes {
+    table: String,
+    inserted: Vec<Vec<CellValue>>,
+    // the rows as they were and as they are now
+    updated: Vec<(Vec<CellValue>, Vec<CellValue>)>,
+    deleted: Vec<Vec<CellValue>>,
+    // made by the action of a foreign key, the references of the updated rows are checked even when
+    // they didn't change, e.g. a set default can keep the value of a key that was deleted
+    is_action: bool,
+}
+
+#[derive(Default, Clone)]
 pub struct Table {
     pub name: String,
     pub columns: Vec<Column>,
     pub rows: Vec<Vec<CellValue>>,
+    pub keys: Vec<UniqueKey>,
+    // checks declared as table constraints, column checks are on the columns.
+    pub checks: Vec<Check>,
+    pub foreign_keys: Vec<ForeignKey>,
+    // the last value generated for each identity column, by column index. Like in postgres, the values
+    // used by a failed insert are not given out again.
+    pub identity_values: HashMap<usize, i64>,
+}
+
+#[derive(Debug, Clone)]
+pub struct UniqueKey {
+    pub name: String,
+    // indexes of the key columns in the table
+    pub columns: Vec<usize>,
+    pub is_primary_key: bool,
+}
+
+// the values of `columns` have to be the key of a row of `referenced_table`, unless one of them is null.
+#[derive(Debug, Clone)]
+pub struct ForeignKey {
+    pub name: String,
+    // indexes of the referencing columns in the table
+    pub columns: Vec<usize>,
+    pub referenced_table: String,
+    // indexes of the referenced columns in the referenced table, in the order of `columns`
+    pub referenced_columns: Vec<usize>,
+    pub on_delete: ReferentialAction,
+    pub on_update: ReferentialAction,
 }
 
 impl Table {
@@ -93,114 +897,6783 @@ impl Table {
         self.rows.push(row)
     }
 
-    pub fn get_cell(&self, select_item: &SelectItem, row_index: usize) -> Result<Cell> {
-        let (column, col_index) = self.get_column_from_select_item(select_item)?;
-         Ok(Cell {
-                value: self.rows[row_index][col_index].clone(),
-                cell_type: column.column_type,
-                column_name: select_item.clone()
-                    .as_name
-                    .map_or(column.name, |name_token| name_token.value),
-        })
+    pub fn get_column_index(&self, column_name: &str) -> Result<usize> {
+        self.columns
+            .iter()
+            .position(|column| column.name == column_name)
+            .ok_or_else(|| MemoryError::ColumnNotFound(column_name.to_string()))
     }
 
-    // returns columns and column index
-    pub fn get_column_from_select_item(&self, select_item: &SelectItem) -> Result<(Column, usize)> {
-        for (col_index, column) in self.columns.iter().enumerate() {
-            if column.name == select_item.name.value {
-                return Ok((Column{
-                    name: select_item.clone()
-                    .as_name
-                    .map_or(column.name.clone(), |name_token| name_token.value),
-                    column_type: column.column_type.clone()
-                }, col_index));
+    // orders `values` like the table's columns, the missing columns get their default or the next
+    // value of their identity.
+    pub fn build_row(&mut self, column_names: &[Token], values: Vec<CellValue>) -> Result<Vec<CellValue>> {
+        let mut given_values: Vec<Option<CellValue>> = vec![None; self.columns.len()];
+        if column_names.is_empty() {
+            if values.len() != self.columns.len() {
+                return Err(MemoryError::WrongNumberOfValues(self.columns.len(), values.len()));
+            }
+            given_values = values.into_iter().map(Some).collect();
+        } else {
+            if values.len() != column_names.len() {
+                return Err(MemoryError::WrongNumberOfValues(column_names.len(), values.len()));
+            }
+            for (name, value) in column_names.iter().zip(values) {
+                let col_index = self.get_column_index(&name.value)?;
+                if given_values[col_index].is_some() {
+                    return Err(MemoryError::DuplicateColumn(name.value.clone()));
+                }
+                given_values[col_index] = Some(value);
             }
         }
-        Err(MemoryError::ColumnNotFound(select_item.name.value.clone()))
+
+        let context = RowContext::default();
+        let mut row = Vec::<CellValue>::new();
+        for (col_index, value) in given_values.into_iter().enumerate() {
+            let column = &self.columns[col_index];
+            row.push(match value {
+                Some(_) if column.identity == Some(Identity::Always) => {
+                    return Err(MemoryError::GeneratedAlways(column.name.clone()));
+                }
+                Some(value) => value,
+                None if column.identity.is_some() => self.next_identity_value(col_index)?,
+                None => match &column.default {
+                    Some(default) => context.evaluate(default)?,
+                    None => CellValue::Null,
+                },
+            });
+        }
+        self.coerce_row(row)
     }
-}
 
-impl Table {
-    pub fn from_create_statement(create_statement: CreateStatement) -> Result<Self> {
-        let mut columns = vec![];
-        for column_token in create_statement.cols {
-            columns.push(Column::parse_token(&column_token)?);
+    fn next_identity_value(&mut self, col_index: usize) -> Result<CellValue> {
+        let value = self.identity_values.entry(col_index).or_insert(0);
+        *value = value.checked_add(1).ok_or(MemoryError::IntegerOverflow)?;
+        Ok(CellValue::Int(*value))
+    }
+
+    // the index of a column an update can set, the values of `generated always` columns can't be given.
+    fn assigned_column_index(&self, column_name: &str) -> Result<usize> {
+        let col_index = self.get_column_index(column_name)?;
+        if self.columns[col_index].identity == Some(Identity::Always) {
+            return Err(MemoryError::GeneratedAlways(column_name.to_string()));
         }
-        Ok(Table{
-            name: create_statement.name.value,
-            columns,
-            rows: vec![]
-        })
+        Ok(col_index)
     }
-}
 
-#[derive(Default, Debug)]
-pub struct Cell {
-    pub value: CellValue,
-    pub column_name: String,
-    pub cell_type: CellType,
-}
+    // the index of the column each assignment sets and its value, a column can only be set once.
+    fn assigned_columns<'e>(&self, assignments: &'e [Assignment]) -> Result<Vec<(usize, &'e Expression)>> {
+        let mut assigned = Vec::<(usize, &Expression)>::new();
+        for assignment in assignments {
+            let col_index = self.assigned_column_index(&assignment.column.value)?;
+            if assigned.iter().any(|(assigned_index, _)| *assigned_index == col_index) {
+                return Err(MemoryError::DuplicateAssignment(assignment.column.value.clone()));
+            }
+            assigned.push((col_index, &assignment.value));
+        }
+        Ok(assigned)
+    }
 
-#[derive(Default, Clone, Debug)]
-pub struct CellValue(String);
+    // converts each value to the type of its column, see `CellValue::coerce`.
+    fn coerce_row(&self, row: Vec<CellValue>) -> Result<Vec<CellValue>> {
+        self.columns
+            .iter()
+            .zip(row)
+            .map(|(column, value)| value.coerce(&column.column_type))
+            .collect()
+    }
 
-impl std::fmt::Display for CellValue {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{}", self.0)
+    /// Checks that `row` can be stored without breaking any of the table's constraints,
+    /// `skip_row` is the index of the row it replaces, if any.
+    pub fn check_row(&self, row: &[CellValue], skip_row: Option<usize>) -> Result<()> {
+        for (column, value) in self.columns.iter().zip(row) {
+            if column.is_not_null && matches!(value, CellValue::Null) {
+                return Err(MemoryError::NotNullViolation(column.name.clone()));
+            }
+        }
+
+        // like a where clause, except that a null result passes.
+        let context = RowContext {
+            columns: &self.columns,
+            row,
+            ..Default::default()
+        };
+        let column_checks = self.columns.iter().flat_map(|column| &column.checks);
+        for check in column_checks.chain(&self.checks) {
+            if context.evaluate(&check.expression)?.as_bool("check")? == Some(false) {
+                return Err(MemoryError::CheckViolation(check.name.clone()));
+            }
+        }
+
+        self.check_keys(row, skip_row)
     }
-}
 
-impl From<Token> for CellValue {
-    fn from(token: Token) -> Self {
-        CellValue(token.value)
+    fn check_keys(&self, row: &[CellValue], skip_row: Option<usize>) -> Result<()> {
+        for key in &self.keys {
+            if self.find_duplicate(key, row, skip_row).is_some() {
+                return Err(MemoryError::UniqueViolation(key.name.clone(), self.describe_key(key, row)));
+            }
+        }
+        Ok(())
     }
-}
 
-impl Memory {
-    pub fn get_table(&self, table_name: &str) -> Result<&Table> {
-        let table = self.tables.get(table_name);
-        table.map_or(
-            Err(MemoryError::TableNotFound(table_name.to_string())),
-            |table| Ok(table),
-        )
+    // the index of a stored row with the same value as `row` on `key`.
+    fn find_duplicate(&self, key: &UniqueKey, row: &[CellValue], skip_row: Option<usize>) -> Option<usize> {
+        // nulls are never equal to each other, so they can't be duplicates.
+        if key.columns.iter().any(|&col_index| matches!(row[col_index], CellValue::Null)) {
+            return None;
+        }
+
+        self.rows
+            .iter()
+            .enumerate()
+            .filter(|(row_index, _)| Some(*row_index) != skip_row)
+            .find(|(_, existing_row)| {
+                key.columns
+                    .iter()
+                    .all(|&col_index| existing_row[col_index].is_same_as(&row[col_index]))
+            })
+            .map(|(row_index, _)| row_index)
     }
 
-    pub fn get_table_mut(&mut self, table_name: &str) -> Result<&mut Table> {
-        let table = self.tables.get_mut(table_name);
-        table.map_or(
-            Err(MemoryError::TableNotFound(table_name.to_string())),
-            |table| Ok(table),
-        )
+    // the indexes of the keys an `on conflict` with these columns is about, all of them without columns.
+    fn conflict_keys(&self, column_names: &[Token]) -> Result<Vec<usize>> {
+        if column_names.is_empty() {
+            return Ok((0..self.keys.len()).collect());
+        }
+
+        let mut columns = self.get_column_indexes(column_names)?;
+        columns.sort_unstable();
+        let position = self.keys.iter().position(|key| {
+            let mut key_columns = key.columns.clone();
+            key_columns.sort_unstable();
+            key_columns == columns
+        });
+        match position {
+            Some(key_index) => Ok(vec![key_index]),
+            None => {
+                let names: Vec<&str> = column_names.iter().map(|name| name.value.as_str()).collect();
+                Err(MemoryError::ConflictKeyNotFound(names.join(", ")))
+            }
+        }
     }
 
-    fn insert_table(&mut self, table_name: &str, table: Table) {
-        self.tables.insert(table_name.to_string(), table);
+    // inserts `row`, unless it duplicates a stored row on one of the upsert's keys, that row is then
+    // left as it is or updated. `updated_rows` gets each updated row as it was before, and the index
+    // of the inserted or updated row is returned.
+    fn upsert_row(
+        &mut self,
+        row: Vec<CellValue>,
+        upsert: &Upsert,
+        updated_rows: &mut Vec<(usize, Vec<CellValue>)>,
+    ) -> Result<Option<usize>> {
+        let duplicate = upsert
+            .keys
+            .iter()
+            .find_map(|&key_index| self.find_duplicate(&self.keys[key_index], &row, None));
+        let row_index = match duplicate {
+            Some(row_index) => row_index,
+            None => {
+                self.check_row(&row, None)?;
+                self.insert_row(row);
+                return Ok(Some(self.rows.len() - 1));
+            }
+        };
+        let where_clause = match &upsert.on_conflict.action {
+            ConflictAction::Nothing => return Ok(None),
+            ConflictAction::Update { where_clause, .. } => where_clause,
+        };
+        // the result would depend on the order of the rows.
+        if row_index >= upsert.first_new_row || updated_rows.iter().any(|(updated, _)| *updated == row_index) {
+            return Err(MemoryError::RowUpdatedTwice(self.name.clone()));
+        }
+
+        // the stored row is read through the table's name and the one that wasn't inserted as `excluded`.
+        let mut columns = qualify_columns(&self.columns, &self.name);
+        columns.extend(qualify_columns(&self.columns, "excluded"));
+        let mut input_row = self.rows[row_index].clone();
+        input_row.extend(row);
+        let context = RowContext {
+            columns: &columns,
+            row: &input_row,
+            patterns: Some(upsert.patterns),
+            parameters: upsert.parameters,
+            ..Default::default()
+        };
+        if !context.matches(where_clause.as_ref(), "where")? {
+            return Ok(None);
+        }
+
+        let mut new_row = self.rows[row_index].clone();
+        for (col_index, value) in &upsert.assignments {
+            new_row[*col_index] = context.evaluate(value)?.coerce(&self.columns[*col_index].column_type)?;
+        }
+        self.check_row(&new_row, Some(row_index))?;
+        updated_rows.push((row_index, std::mem::replace(&mut self.rows[row_index], new_row)));
+        Ok(Some(row_index))
     }
-}
 
-pub type Result<T> = std::result::Result<T, MemoryError>;
+    // formats the key's value in `row`, e.g. `(id, name)=(1, 'one')`
+    fn describe_key(&self, key: &UniqueKey, row: &[CellValue]) -> String {
+        self.describe_columns(&key.columns, row)
+    }
 
-#[derive(Debug)]
-pub enum MemoryError {
-    // the String is the table name
-    TableNotFound(String),
-    ColumnNotFound(String),
-    TableAlreadyExists(String),
-    InvalidType(String),
-    QueryNotValid
-}
+    fn describe_columns(&self, columns: &[usize], row: &[CellValue]) -> String {
+        let names: Vec<&str> = columns
+            .iter()
+            .map(|&col_index| self.columns[col_index].name.as_str())
+            .collect();
+        let values: Vec<String> = columns.iter().map(|&col_index| row[col_index].to_string()).collect();
+        format!("({})=({})", names.join(", "), values.join(", "))
+    }
 
-impl std::fmt::Display for MemoryError {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        match self {
-            MemoryError::TableNotFound(name) => f.write_fmt(format_args!("table '{}' not found", name)),
-            // TODO: add table name too
-            MemoryError::ColumnNotFound(name) => f.write_fmt(format_args!("column '{}' not found", name)),
-            MemoryError::TableAlreadyExists(name) => f.write_fmt(format_args!("table '{}' already exists", name)),
-            MemoryError::InvalidType(type_name) => f.write_fmt(format_args!("type '{}' is not valid", type_name)),
-            MemoryError::QueryNotValid => f.write_str("Query not valid")
+    // the rows matching `where_clause` with the assignments applied, next to their index.
+    fn updated_rows(
+        &self,
+        assignments: &[(usize, &Expression)],
+        where_clause: Option<&Expression>,
+        outer: &RowContext,
+    ) -> Result<Vec<(usize, Vec<CellValue>)>> {
+        let columns = qualify_columns(&self.columns, &self.name);
+        let input = outer.nested(&columns);
+
+        let mut new_rows = vec![];
+        for (row_index, row) in self.rows.iter().enumerate() {
+            let context = input.with_row(row);
+            if !context.matches(where_clause, "where")? {
+                continue;
+            }
+
+            let mut new_row = row.clone();
+            for (col_index, value) in assignments {
+                new_row[*col_index] = context.evaluate(value)?.coerce(&self.columns[*col_index].column_type)?;
+            }
+            new_rows.push((row_index, new_row));
+        }
+        Ok(new_rows)
+    }
+
+    // the indexes of the rows matching `where_clause`.
+    fn matching_rows(&self, where_clause: Option<&Expression>, outer: &RowContext) -> Result<Vec<usize>> {
+        let columns = qualify_columns(&self.columns, &self.name);
+        let input = outer.nested(&columns);
+
+        let mut row_indexes = vec![];
+        for (row_index, row) in self.rows.iter().enumerate() {
+            if input.with_row(row).matches(where_clause, "where")? {
+                row_indexes.push(row_index);
+            }
+        }
+        Ok(row_indexes)
+    }
+
+    // removes the rows at `row_indexes`, which are in increasing order, and returns them.
+    fn remove_rows(&mut self, row_indexes: &[usize]) -> Vec<Vec<CellValue>> {
+        let mut row_indexes = row_indexes.iter().peekable();
+        let mut removed_rows = vec![];
+        let mut kept_rows = vec![];
+        for (row_index, row) in std::mem::take(&mut self.rows).into_iter().enumerate() {
+            if row_indexes.next_if_eq(&&row_index).is_some() {
+                removed_rows.push(row);
+            } else {
+                kept_rows.push(row);
+            }
+        }
+        self.rows = kept_rows;
+        removed_rows
+    }
+
+    // stores the rows one by one, so each one is checked against the ones stored before it.
+    fn replace_rows(&mut self, new_rows: Vec<(usize, Vec<CellValue>)>) -> Result<()> {
+        for (row_index, new_row) in new_rows {
+            self.check_row(&new_row, Some(row_index))?;
+            self.rows[row_index] = new_row;
         }
+        Ok(())
     }
 }
 
-impl std::error::Error for MemoryError {}
\ No newline at end of file
+impl Table {
+    /// `tables` are the tables the foreign keys can reference, besides the new table itself.
+    pub fn from_create_statement(create_statement: CreateStatement, tables: &HashMap<String, Table>) -> Result<Self> {
+        let table_name = create_statement.name.value;
+        let mut columns = vec![];
+        for column_token in &create_statement.cols {
+            columns.push(Column::parse_token(column_token, &table_name)?);
+        }
+        let mut table = Table {
+            name: table_name,
+            columns,
+            rows: vec![],
+            keys: vec![],
+            checks: vec![],
+            foreign_keys: vec![],
+            identity_values: HashMap::new(),
+        };
+
+        let mut primary_keys = Vec::<Vec<usize>>::new();
+        let mut unique_keys = Vec::<Vec<usize>>::new();
+        // resolved once the keys exist, a table can reference itself.
+        let mut references = Vec::<(Vec<usize>, References)>::new();
+        for (col_index, column) in create_statement.cols.iter().enumerate() {
+            if column.is_primary_key {
+                primary_keys.push(vec![col_index]);
+            }
+            if column.is_unique {
+                unique_keys.push(vec![col_index]);
+            }
+            if let Some(column_references) = &column.references {
+                references.push((vec![col_index], column_references.clone()));
+            }
+        }
+        for constraint in create_statement.constraints {
+            match constraint {
+                TableConstraint::PrimaryKey(names) => {
+                    let key_columns = table.get_column_indexes(&names)?;
+                    for &col_index in &key_columns {
+                        table.columns[col_index].is_not_null = true;
+                    }
+                    primary_keys.push(key_columns);
+                }
+                TableConstraint::Unique(names) => {
+                    unique_keys.push(table.get_column_indexes(&names)?);
+                }
+                TableConstraint::Check(expression) => {
+                    let name = numbered_name(format!("{}_check", table.name), table.checks.len());
+                    table.checks.push(Check { name, expression });
+                }
+                TableConstraint::ForeignKey(names, table_references) => {
+                    references.push((table.get_column_indexes(&names)?, table_references));
+                }
+            }
+        }
+
+        if primary_keys.len() > 1 {
+            return Err(MemoryError::MultiplePrimaryKeys(table.name));
+        }
+        if let Some(key_columns) = primary_keys.pop() {
+            table.keys.push(UniqueKey {
+                name: format!("{}_pkey", table.name),
+                columns: key_columns,
+                is_primary_key: true,
+            });
+        }
+        for key_columns in unique_keys {
+            let column_names: Vec<&str> = key_columns
+                .iter()
+                .map(|&col_index| table.columns[col_index].name.as_str())
+                .collect();
+            let name = format!("{}_{}_key", table.name, column_names.join("_"));
+            table.keys.push(UniqueKey {
+                name,
+                columns: key_columns,
+                is_primary_key: false,
+            });
+        }
+        for (columns, references) in references {
+            let referenced_table = if references.table.value == table.name {
+                &table
+            } else {
+                tables
+                    .get(&references.table.value)
+                    .ok_or_else(|| MemoryError::TableNotFound(references.table.value.clone()))?
+            };
+            let foreign_key = table.foreign_key(columns, &references, referenced_table)?;
+            table.foreign_keys.push(foreign_key);
+        }
+
+        table.validate_expressions()?;
+
+        Ok(table)
+    }
+
+    fn get_column_indexes(&self, names: &[Token]) -> Result<Vec<usize>> {
+        names.iter().map(|name| self.get_column_index(&name.value)).collect()
+    }
+
+    // the indexes of the stored rows with the values `row` has at `row_columns` in their `columns`.
+    fn rows_with_key(&self, columns: &[usize], row: &[CellValue], row_columns: &[usize]) -> Vec<usize> {
+        self.rows
+            .iter()
+            .enumerate()
+            .filter(|(_, stored_row)| {
+                columns
+                    .iter()
+                    .zip(row_columns)
+                    .all(|(&col_index, &row_index)| stored_row[col_index].is_same_as(&row[row_index]))
+            })
+            .map(|(row_index, _)| row_index)
+            .collect()
+    }
+
+    // the referenced columns have to be a key of the referenced table, so a row references at most one row.
+    fn foreign_key(
+        &self,
+        columns: Vec<usize>,
+        references: &References,
+        referenced_table: &Table,
+    ) -> Result<ForeignKey> {
+        let referenced_columns = if references.columns.is_empty() {
+            let primary_key = referenced_table.keys.iter().find(|key| key.is_primary_key);
+            let primary_key = primary_key.ok_or_else(|| MemoryError::NoReferencedKey(referenced_table.name.clone()))?;
+            primary_key.columns.clone()
+        } else {
+            referenced_table.get_column_indexes(&references.columns)?
+        };
+        if referenced_columns.len() != columns.len() {
+            return Err(MemoryError::WrongNumberOfReferencedColumns(columns.len(), referenced_columns.len()));
+        }
+        let mut sorted_columns = referenced_columns.clone();
+        sorted_columns.sort_unstable();
+        let is_key = referenced_table.keys.iter().any(|key| {
+            let mut key_columns = key.columns.clone();
+            key_columns.sort_unstable();
+            key_columns == sorted_columns
+        });
+        if !is_key {
+            return Err(MemoryError::NoReferencedKey(referenced_table.name.clone()));
+        }
+        for (&col_index, &referenced_index) in columns.iter().zip(&referenced_columns) {
+            let column_type = &self.columns[col_index].column_type;
+            let referenced_type = &referenced_table.columns[referenced_index].column_type;
+            if column_type != referenced_type {
+                return Err(MemoryError::IncompatibleTypes(
+                    String::from("foreign key"),
+                    column_type.clone(),
+                    referenced_type.clone(),
+                ));
+            }
+        }
+
+        let names: Vec<&str> = columns.iter().map(|&col_index| self.columns[col_index].name.as_str()).collect();
+        Ok(ForeignKey {
+            name: format!("{}_{}_fkey", self.name, names.join("_")),
+            columns,
+            referenced_table: referenced_table.name.clone(),
+            referenced_columns,
+            on_delete: references.on_delete,
+            on_update: references.on_update,
+        })
+    }
+
+    // defaults can't reference columns and checks can only reference the table's columns,
+    // neither of them can have subqueries.
+    fn validate_expressions(&self) -> Result<()> {
+        let empty_context = RowContext::default();
+        for column in &self.columns {
+            if let Some(default) = &column.default {
+                empty_context.evaluate(default)?;
+            }
+        }
+
+        let null_row = vec![CellValue::Null; self.columns.len()];
+        let null_context = RowContext {
+            columns: &self.columns,
+            row: &null_row,
+            ..Default::default()
+        };
+        let column_checks = self.columns.iter().flat_map(|column| &column.checks);
+        for check in column_checks.chain(&self.checks) {
+            if let Err(err @ (MemoryError::ColumnNotFound(_) | MemoryError::SubqueryNotAllowed)) =
+                null_context.evaluate(&check.expression)
+            {
+                return Err(err);
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Memory {
+    /// Checks that the rows a statement inserted or updated reference existing rows, and runs the actions
+    /// of the foreign keys referencing the rows it deleted or whose key it updated, which change other
+    /// rows in turn. Then `finish` computes the statement's result, e.g. its returning clause. On failure
+    /// the rows changed by the actions are put back, the statement undoes its own.
+    fn enforce_foreign_keys<T>(&mut self, changes: Changes, finish: impl FnOnce(&Self) -> Result<T>) -> Result<T> {
+        let mut original_tables = HashMap::<String, Vec<Vec<CellValue>>>::new();
+        let result = self
+            .apply_foreign_keys(changes, &mut original_tables)
+            .and_then(|()| finish(self));
+        if result.is_err() {
+            for (table_name, rows) in original_tables {
+                if let Some(table) = self.tables.get_mut(&table_name) {
+                    table.rows = rows;
+                }
+            }
+        }
+        result
+    }
+
+    fn apply_foreign_keys(
+        &mut self,
+        changes: Changes,
+        original_tables: &mut HashMap<String, Vec<Vec<CellValue>>>,
+    ) -> Result<()> {
+        // the references are checked once every action ran, as an action can fix them, e.g. a cascade
+        // on a table referencing itself. They are the table, the index of its foreign key and the row.
+        let mut references = Vec::<(String, usize, Vec<CellValue>)>::new();
+        let mut unapplied = vec![changes];
+        while let Some(changes) = unapplied.pop() {
+            let table = self.get_table(&changes.table)?;
+            for (key_index, foreign_key) in table.foreign_keys.iter().enumerate() {
+                let updated_rows = changes
+                    .updated
+                    .iter()
+                    .filter(|(old_row, new_row)| {
+                        changes.is_action || !is_same_key(&foreign_key.columns, old_row, new_row)
+                    })
+                    .map(|(_, new_row)| new_row);
+                for row in changes.inserted.iter().chain(updated_rows) {
+                    references.push((changes.table.clone(), key_index, row.clone()));
+                }
+            }
+
+            for (table_name, foreign_key) in self.referencing_keys(&changes.table) {
+                let deleted_keys = changes.deleted.iter().map(|old_row| (old_row, None));
+                let updated_keys = changes
+                    .updated
+                    .iter()
+                    .filter(|(old_row, new_row)| !is_same_key(&foreign_key.referenced_columns, old_row, new_row))
+                    .map(|(old_row, new_row)| (old_row, Some(new_row.as_slice())));
+                for (old_row, new_row) in deleted_keys.chain(updated_keys) {
+                    let action = if new_row.is_some() {
+                        foreign_key.on_update
+                    } else {
+                        foreign_key.on_delete
+                    };
+                    unapplied.extend(self.apply_action(
+                        &table_name,
+                        &foreign_key,
+                        action,
+                        old_row,
+                        new_row,
+                        original_tables,
+                    )?);
+                }
+            }
+        }
+
+        for (table_name, key_index, row) in references {
+            let table = self.get_table(&table_name)?;
+            let foreign_key = &table.foreign_keys[key_index];
+            // a null doesn't reference anything.
+            if foreign_key.columns.iter().any(|&col_index| matches!(row[col_index], CellValue::Null)) {
+                continue;
+            }
+            let referenced_table = self.get_table(&foreign_key.referenced_table)?;
+            let referenced_rows =
+                referenced_table.rows_with_key(&foreign_key.referenced_columns, &row, &foreign_key.columns);
+            if referenced_rows.is_empty() {
+                return Err(MemoryError::ForeignKeyViolation(
+                    foreign_key.name.clone(),
+                    table.describe_columns(&foreign_key.columns, &row),
+                    foreign_key.referenced_table.clone(),
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    // runs `action` on the rows of `table_name` referencing the key of `old_row`, which was deleted or
+    // updated to the key of `new_row`, and returns what it changed.
+    fn apply_action(
+        &mut self,
+        table_name: &str,
+        foreign_key: &ForeignKey,
+        action: ReferentialAction,
+        old_row: &[CellValue],
+        new_row: Option<&[CellValue]>,
+        original_tables: &mut HashMap<String, Vec<Vec<CellValue>>>,
+    ) -> Result<Option<Changes>> {
+        let referenced_table = self.get_table(&foreign_key.referenced_table)?;
+        let table = self.get_table(table_name)?;
+        let row_indexes = table.rows_with_key(&foreign_key.columns, old_row, &foreign_key.referenced_columns);
+        if row_indexes.is_empty() {
+            return Ok(None);
+        }
+
+        let still_referenced = || {
+            MemoryError::StillReferenced(
+                foreign_key.name.clone(),
+                referenced_table.describe_columns(&foreign_key.referenced_columns, old_row),
+                table_name.to_string(),
+            )
+        };
+        let values: Vec<CellValue> = match (action, new_row) {
+            // the rows are still fine when another row has the key now.
+            (ReferentialAction::NoAction, _) => {
+                let key_columns = &foreign_key.referenced_columns;
+                if referenced_table.rows_with_key(key_columns, old_row, key_columns).is_empty() {
+                    return Err(still_referenced());
+                }
+                return Ok(None);
+            }
+            (ReferentialAction::Restrict, _) => return Err(still_referenced()),
+            (ReferentialAction::Cascade, None) => {
+                original_tables.entry(table_name.to_string()).or_insert_with(|| table.rows.clone());
+                let deleted = self.get_table_mut(table_name)?.remove_rows(&row_indexes);
+                return Ok(Some(Changes {
+                    table: table_name.to_string(),
+                    deleted,
+                    ..Default::default()
+                }));
+            }
+            (ReferentialAction::Cascade, Some(new_row)) => foreign_key
+                .referenced_columns
+                .iter()
+                .map(|&col_index| new_row[col_index].clone())
+                .collect(),
+            (ReferentialAction::SetNull, _) => vec![CellValue::Null; foreign_key.columns.len()],
+            (ReferentialAction::SetDefault, _) => {
+                let context = RowContext::default();
+                let mut defaults = vec![];
+                for &col_index in &foreign_key.columns {
+                    let column = &table.columns[col_index];
+                    defaults.push(match &column.default {
+                        Some(default) => context.evaluate(default)?.coerce(&column.column_type)?,
+                        None => CellValue::Null,
+                    });
+                }
+                defaults
+            }
+        };
+
+        let mut updated = vec![];
+        let mut new_rows = vec![];
+        for row_index in row_indexes {
+            let mut new_row = table.rows[row_index].clone();
+            for (&col_index, value) in foreign_key.columns.iter().zip(&values) {
+                new_row[col_index] = value.clone();
+            }
+            updated.push((table.rows[row_index].clone(), new_row.clone()));
+            new_rows.push((row_index, new_row));
+        }
+        original_tables.entry(table_name.to_string()).or_insert_with(|| table.rows.clone());
+        self.get_table_mut(table_name)?.replace_rows(new_rows)?;
+        Ok(Some(Changes {
+            table: table_name.to_string(),
+            updated,
+            is_action: true,
+            ..Default::default()
+        }))
+    }
+
+    // the foreign keys referencing `table_name` and the tables they are on, sorted by the table names.
+    fn referencing_keys(&self, table_name: &str) -> Vec<(String, ForeignKey)> {
+        let mut foreign_keys = vec![];
+        for table in self.tables.values() {
+            for foreign_key in &table.foreign_keys {
+                if foreign_key.referenced_table == table_name {
+                    foreign_keys.push((table.name.clone(), foreign_key.clone()));
+                }
+            }
+        }
+        foreign_keys.sort_by(|(left, _), (right, _)| left.cmp(right));
+        foreign_keys
+    }
+}
+
+// whether the rows have the same values in `columns`.
+fn is_same_key(columns: &[usize], left: &[CellValue], right: &[CellValue]) -> bool {
+    columns.iter().all(|&col_index| left[col_index].is_same_as(&right[col_index]))
+}
+
+// a row and the columns describing it, expressions are evaluated against it.
+#[derive(Default, Clone, Copy)]
+pub struct RowContext<'a> {
+    pub columns: &'a [Column],
+    pub row: &'a [CellValue],
+    // the database subqueries read, they aren't allowed without it
+    pub memory: Option<&'a Memory>,
+    // the row of the enclosing query, columns that aren't found in this row are looked up there
+    pub outer: Option<&'a RowContext<'a>>,
+    // the tables of the query's with clause, the ones of enclosing queries are found through `outer`
+    pub common_tables: &'a [CommonTable],
+    // the patterns compiled by the statement, without it they are compiled each time they are used
+    pub patterns: Option<&'a PatternCache>,
+    // where `explain analyze` collects what each operator did
+    pub profile: Option<&'a Profile>,
+    // the values of the parameters of a prepared statement, `$1` is the first one
+    pub parameters: &'a [CellValue],
+}
+
+// the operator, pattern and escape character a pattern was compiled from.
+type PatternKey = (LikeOperator, String, Option<char>);
+
+// the patterns of a statement, each one is only compiled once.
+#[derive(Default)]
+pub struct PatternCache {
+    patterns: RefCell<HashMap<PatternKey, Pattern>>,
+}
+
+// the rows of a query in a with clause, the query it belongs to and its subqueries can read them by name.
+pub struct CommonTable {
+    pub name: String,
+    pub columns: Vec<Column>,
+    pub rows: Vec<Vec<CellValue>>,
+}
+
+impl<'a> RowContext<'a> {
+    // the context of the rows of a query nested in this one, before a row is set.
+    fn nested(&'a self, columns: &'a [Column]) -> Self {
+        RowContext {
+            columns,
+            row: &[],
+            memory: self.memory,
+            outer: Some(self),
+            common_tables: &[],
+            patterns: self.patterns,
+            profile: self.profile,
+            parameters: self.parameters,
+        }
+    }
+
+    // the scope of a query with a with clause, nested in this one.
+    fn with_common_tables(&'a self, common_tables: &'a [CommonTable]) -> Self {
+        RowContext {
+            common_tables,
+            ..self.nested(&[])
+        }
+    }
+
+    fn find_common_table(&self, name: &str) -> Option<&'a CommonTable> {
+        match self.common_tables.iter().find(|common_table| common_table.name == name) {
+            Some(common_table) => Some(common_table),
+            None => self.outer?.find_common_table(name),
+        }
+    }
+
+    fn with_row(&self, row: &'a [CellValue]) -> Self {
+        RowContext { row, ..*self }
+    }
+
+    // the scope of a view's query, which can't see the columns or with clauses of the query reading it.
+    fn view_scope(&self) -> Self {
+        RowContext {
+            memory: self.memory,
+            patterns: self.patterns,
+            profile: self.profile,
+            ..Default::default()
+        }
+    }
+
+    // adds a run of one of the operators of `query` to the profile, if there is one. the times of
+    // operators include the ones of their inputs, so `start` is when the first of them started.
+    fn record(&self, query: &SelectStatement, stage: Stage, rows: usize, start: Instant) {
+        if let Some(profile) = self.profile {
+            profile.record(query.id, stage, rows, start.elapsed());
+        }
+    }
+
+    pub fn evaluate(&self, expression: &Expression) -> Result<CellValue> {
+        match expression {
+            Expression::Literal(token) => CellValue::from_literal(token),
+            Expression::Parameter(index) => match self.parameters.get(index - 1) {
+                Some(value) => Ok(value.clone()),
+                None => Err(MemoryError::UnboundParameter(*index)),
+            },
+            Expression::Column { table, name } => {
+                match (resolve_column(self.columns, table.as_ref(), name), self.outer) {
+                    (Ok(col_index), _) => Ok(self.row[col_index].clone()),
+                    (Err(MemoryError::ColumnNotFound(_)), Some(outer)) => outer.evaluate(expression),
+                    (Err(err), _) => Err(err),
+                }
+            }
+            // aggregates only exist after grouping, where they are replaced by group row columns.
+            Expression::Function(call) if is_aggregate(call) => {
+                Err(MemoryError::AggregateNotAllowed(call.name.value.clone()))
+            }
+            Expression::Function(call) => self.evaluate_function(call),
+            // likewise window functions, which are replaced by columns of the rows the window plan adds them to.
+            Expression::Window { function, .. } => Err(MemoryError::WindowNotAllowed(function.name.value.clone())),
+            Expression::Unary { operator, operand } => {
+                let value = self.evaluate(operand)?;
+                match operator {
+                    UnaryOperator::Minus => value.negate(),
+                    UnaryOperator::Not => Ok(match value.as_bool("not")? {
+                        Some(value) => CellValue::Bool(!value),
+                        None => CellValue::Null,
+                    }),
+                }
+            }
+            Expression::Binary {
+                left,
+                operator,
+                right,
+            } => self.evaluate_binary(left, *operator, right),
+            Expression::Subquery(query) => {
+                let result = self.run_subquery(query)?;
+                match result.rows.len() {
+                    0 => Ok(CellValue::Null),
+                    1 => Ok(result.rows[0][0].value.clone()),
+                    _ => Err(MemoryError::MoreThanOneRow),
+                }
+            }
+            Expression::Exists(query) => {
+                let memory = self.memory.ok_or(MemoryError::SubqueryNotAllowed)?;
+                Ok(CellValue::Bool(!memory.query(query, self)?.rows.is_empty()))
+            }
+            Expression::InSubquery {
+                expression,
+                subquery,
+                is_negated,
+            } => {
+                let value = self.evaluate(expression)?;
+                let result = self.run_subquery(subquery)?;
+                let candidates = result.rows.into_iter().map(|mut row| Ok(row.remove(0).value));
+                is_in(&value, candidates, *is_negated)
+            }
+            // the list is only evaluated up to the first equal value.
+            Expression::InList {
+                expression,
+                list,
+                is_negated,
+            } => {
+                let value = self.evaluate(expression)?;
+                is_in(&value, list.iter().map(|item| self.evaluate(item)), *is_negated)
+            }
+            Expression::Between {
+                expression,
+                low,
+                high,
+                is_negated,
+            } => {
+                let value = self.evaluate(expression)?;
+                let is_above_low = value.compare(&self.evaluate(low)?)?.map(|ordering| ordering != Ordering::Less);
+                let is_below_high =
+                    value.compare(&self.evaluate(high)?)?.map(|ordering| ordering != Ordering::Greater);
+                Ok(match (is_above_low, is_below_high) {
+                    (Some(false), _) | (_, Some(false)) => CellValue::Bool(*is_negated),
+                    (Some(true), Some(true)) => CellValue::Bool(!is_negated),
+                    _ => CellValue::Null,
+                })
+            }
+            Expression::IsNull {
+                expression,
+                is_negated,
+            } => {
+                let is_null = matches!(self.evaluate(expression)?, CellValue::Null);
+                Ok(CellValue::Bool(is_null != *is_negated))
+            }
+            Expression::IsDistinctFrom {
+                left,
+                right,
+                is_negated,
+            } => {
+                let left = self.evaluate(left)?;
+                let right = self.evaluate(right)?;
+                let is_distinct = match (&left, &right) {
+                    (CellValue::Null, CellValue::Null) => false,
+                    (CellValue::Null, _) | (_, CellValue::Null) => true,
+                    _ => left.compare(&right)? != Some(Ordering::Equal),
+                };
+                Ok(CellValue::Bool(is_distinct != *is_negated))
+            }
+            // only the branch that is taken is evaluated.
+            Expression::Case {
+                operand,
+                branches,
+                else_result,
+            } => {
+                let operand = match operand {
+                    Some(operand) => Some(self.evaluate(operand)?),
+                    None => None,
+                };
+                for branch in branches {
+                    let condition = self.evaluate(&branch.condition)?;
+                    let is_taken = match &operand {
+                        Some(operand) => operand.compare(&condition)? == Some(Ordering::Equal),
+                        None => condition.as_bool("case")? == Some(true),
+                    };
+                    if is_taken {
+                        return self.evaluate(&branch.result);
+                    }
+                }
+                match else_result {
+                    Some(else_result) => self.evaluate(else_result),
+                    None => Ok(CellValue::Null),
+                }
+            }
+            Expression::Like {
+                expression,
+                operator,
+                pattern,
+                escape,
+                is_negated,
+            } => {
+                let operator_name = operator.name();
+                let value = self.evaluate(expression)?;
+                let pattern = self.evaluate(pattern)?;
+                // like postgres, a backslash escapes unless the query says otherwise.
+                let escape = match escape {
+                    Some(escape) => self.evaluate(escape)?,
+                    None => CellValue::Text("\\".to_string()),
+                };
+                match (
+                    value.as_text(operator_name)?,
+                    pattern.as_text(operator_name)?,
+                    escape.as_text(operator_name)?,
+                ) {
+                    (Some(value), Some(pattern), Some(escape)) => {
+                        let is_match = self.matches_pattern(*operator, value, pattern, escape)?;
+                        Ok(CellValue::Bool(is_match != *is_negated))
+                    }
+                    _ => Ok(CellValue::Null),
+                }
+            }
+            Expression::Cast { expression, data_type } => {
+                self.evaluate(expression)?.cast(&CellType::parse_token(data_type)?)
+            }
+        }
+    }
+
+    fn matches_pattern(&self, operator: LikeOperator, value: &str, pattern: &str, escape: &str) -> Result<bool> {
+        let mut escape_chars = escape.chars();
+        let escape_char = escape_chars.next();
+        if escape_chars.next().is_some() {
+            return Err(MemoryError::InvalidPattern(
+                escape.to_string(),
+                "the escape must be a single character".to_string(),
+            ));
+        }
+        let compile = || {
+            match operator {
+                LikeOperator::Like => Pattern::like(pattern, escape_char, false),
+                LikeOperator::ILike => Pattern::like(pattern, escape_char, true),
+                LikeOperator::SimilarTo => Pattern::similar(pattern, escape_char),
+            }
+            .map_err(|reason| MemoryError::InvalidPattern(pattern.to_string(), reason.to_string()))
+        };
+
+        let cache = match self.patterns {
+            Some(cache) => cache,
+            None => return Ok(compile()?.is_match(value)),
+        };
+        let mut patterns = cache.patterns.borrow_mut();
+        let key = (operator, pattern.to_string(), escape_char);
+        if !patterns.contains_key(&key) {
+            patterns.insert(key.clone(), compile()?);
+        }
+        Ok(patterns[&key].is_match(value))
+    }
+
+    // the arguments are only evaluated when they are needed, so the others can't fail.
+    fn evaluate_function(&self, call: &FunctionCall) -> Result<CellValue> {
+        check_scalar_function(call)?;
+        match call.name.value.as_str() {
+            "coalesce" => {
+                for arg in &call.args {
+                    let value = self.evaluate(arg)?;
+                    if !matches!(value, CellValue::Null) {
+                        return Ok(value);
+                    }
+                }
+                Ok(CellValue::Null)
+            }
+            "nullif" => {
+                let value = self.evaluate(&call.args[0])?;
+                if value.compare(&self.evaluate(&call.args[1])?)? == Some(Ordering::Equal) {
+                    Ok(CellValue::Null)
+                } else {
+                    Ok(value)
+                }
+            }
+            name @ ("upper" | "lower") => match self.evaluate(&call.args[0])? {
+                CellValue::Text(text) if name == "upper" => Ok(CellValue::Text(text.to_uppercase())),
+                CellValue::Text(text) => Ok(CellValue::Text(text.to_lowercase())),
+                CellValue::Null => Ok(CellValue::Null),
+                _ => Err(MemoryError::InvalidArguments(name.to_string())),
+            },
+            // nulls are skipped, the result is only null when every argument is.
+            name => {
+                let wanted = if name == "greatest" {
+                    Ordering::Greater
+                } else {
+                    Ordering::Less
+                };
+                let mut result = CellValue::Null;
+                for arg in &call.args {
+                    let value = self.evaluate(arg)?;
+                    if matches!(result, CellValue::Null) || value.compare(&result)? == Some(wanted) {
+                        result = value;
+                    }
+                }
+                Ok(result)
+            }
+        }
+    }
+
+    // runs a subquery with this row as its outer row, its result has to be a single column.
+    fn run_subquery(&self, query: &SelectStatement) -> Result<QueryResult> {
+        let memory = self.memory.ok_or(MemoryError::SubqueryNotAllowed)?;
+        let result = memory.query(query, self)?;
+        check_subquery_columns(&result.columns)?;
+        Ok(result)
+    }
+
+    // the type of a subquery's single column, without running it.
+    fn subquery_type(&self, query: &SelectStatement) -> Result<CellType> {
+        let memory = self.memory.ok_or(MemoryError::SubqueryNotAllowed)?;
+        let columns = memory.describe(query, self)?;
+        check_subquery_columns(&columns)?;
+        Ok(columns[0].column_type.clone())
+    }
+
+    // whether the row passes the condition of `clause`, e.g. where, a null result filters the row out.
+    pub fn matches(&self, condition: Option<&Expression>, clause: &str) -> Result<bool> {
+        match condition {
+            Some(expression) => Ok(self.evaluate(expression)?.as_bool(clause)? == Some(true)),
+            None => Ok(true),
+        }
+    }
+
+    fn evaluate_binary(
+        &self,
+        left: &Expression,
+        operator: BinaryOperator,
+        right: &Expression,
+    ) -> Result<CellValue> {
+        let left = self.evaluate(left)?;
+
+        // and/or use three-valued logic and skip the right side when the left one decides.
+        match operator {
+            BinaryOperator::And | BinaryOperator::Or => {
+                let is_and = operator == BinaryOperator::And;
+                let operator_name = if is_and { "and" } else { "or" };
+                let left = left.as_bool(operator_name)?;
+                if left == Some(!is_and) {
+                    return Ok(CellValue::Bool(!is_and));
+                }
+                let right = self.evaluate(right)?.as_bool(operator_name)?;
+                return Ok(match (left, right) {
+                    (_, Some(value)) if value != is_and => CellValue::Bool(value),
+                    (Some(_), Some(value)) => CellValue::Bool(value),
+                    _ => CellValue::Null,
+                });
+            }
+            _ => {}
+        }
+
+        let right = self.evaluate(right)?;
+        match operator {
+            BinaryOperator::Eq
+            | BinaryOperator::Neq
+            | BinaryOperator::Lt
+            | BinaryOperator::Lte
+            | BinaryOperator::Gt
+            | BinaryOperator::Gte => {
+                let ordering = match left.compare(&right)? {
+                    Some(ordering) => ordering,
+                    None => return Ok(CellValue::Null),
+                };
+                Ok(CellValue::Bool(match operator {
+                    BinaryOperator::Eq => ordering == Ordering::Equal,
+                    BinaryOperator::Neq => ordering != Ordering::Equal,
+                    BinaryOperator::Lt => ordering == Ordering::Less,
+                    BinaryOperator::Lte => ordering != Ordering::Greater,
+                    BinaryOperator::Gt => ordering == Ordering::Greater,
+                    _ => ordering != Ordering::Less,
+                }))
+            }
+            _ => left.arithmetic(operator, &right),
+        }
+    }
+}
+
+// finds the column a name refers to, an unqualified name has to match exactly one column.
+fn resolve_column(columns: &[Column], table: Option<&Token>, name: &Token) -> Result<usize> {
+    let mut matches = columns.iter().enumerate().filter(|(_, column)| {
+        column.name == name.value
+            && match table {
+                Some(table) => column.table.as_deref() == Some(table.value.as_str()),
+                None => !column.is_hidden,
+            }
+    });
+
+    match (matches.next(), matches.next()) {
+        (Some((col_index, _)), None) => Ok(col_index),
+        (Some(_), Some(_)) => Err(MemoryError::AmbiguousColumn(name.value.clone())),
+        (None, _) => Err(MemoryError::ColumnNotFound(match table {
+            Some(table) => format!("{}.{}", table.value, name.value),
+            None => name.value.clone(),
+        })),
+    }
+}
+
+fn check_subquery_columns(columns: &[Column]) -> Result<()> {
+    if columns.len() != 1 {
+        return Err(MemoryError::SubqueryColumns(columns.len()));
+    }
+    Ok(())
+}
+
+// the rows coming out of a from clause, table scans borrow the rows of the table.
+struct Relation<'a> {
+    columns: Vec<Column>,
+    rows: Cow<'a, [Vec<CellValue>]>,
+}
+
+// the rows a select reads from its from clause, the ones of a streamed view or subquery are made as
+// they are read.
+type Rows<'a> = Box<dyn Iterator<Item = Result<Cow<'a, [CellValue]>>> + 'a>;
+
+// a select that only filters and projects the rows of its from clause, each of its rows can be made
+// on its own. Reading it as a view or subquery then stops with the query reading it, e.g. at its limit.
+fn is_streamable(query: &SelectStatement) -> bool {
+    query.with.is_empty()
+        && query.set_operations.is_empty()
+        && query.distinct.is_none()
+        && query.order_by.is_empty()
+        && query.limit.is_none()
+        && query.offset.is_none()
+        && !AggregatePlan::is_needed(query)
+        && !query.items.iter().any(|item| find_window(&item.expression).is_some())
+}
+
+// the rows of a streamed view or subquery, see `Memory::stream`.
+struct DerivedRows<'a> {
+    query: Cow<'a, SelectStatement>,
+    scope: RowContext<'a>,
+    // the columns of the rows of `source`
+    columns: Vec<Column>,
+    source: Rows<'a>,
+    // the query reading the rows and the position of the view or subquery in its from clause
+    scanned_as: (QueryId, usize),
+    // the rows made so far, for `explain analyze`
+    rows: usize,
+    start: Instant,
+}
+
+impl<'a> Iterator for DerivedRows<'a> {
+    type Item = Result<Cow<'a, [CellValue]>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let input = self.scope.nested(&self.columns);
+        for row in &mut self.source {
+            let row = match row {
+                Ok(row) => row,
+                Err(err) => return Some(Err(err)),
+            };
+            let context = input.with_row(&row);
+            match project_row(&self.query, &context) {
+                Ok(Some(values)) => {
+                    self.rows += 1;
+                    return Some(Ok(Cow::Owned(values)));
+                }
+                Ok(None) => continue,
+                Err(err) => return Some(Err(err)),
+            }
+        }
+        None
+    }
+}
+
+// the operators of a stream are recorded once the query reading it stops, they only ran that far.
+impl Drop for DerivedRows<'_> {
+    fn drop(&mut self) {
+        self.scope.record(&self.query, Stage::Project, self.rows, self.start);
+        if let Some(profile) = self.scope.profile {
+            let (query, index) = self.scanned_as;
+            profile.record(query, Stage::From(index), self.rows, self.start.elapsed());
+        }
+    }
+}
+
+// the values of the select items of `query` for the row of `context`, none if the row doesn't pass
+// its where clause.
+fn project_row(query: &SelectStatement, context: &RowContext) -> Result<Option<Vec<CellValue>>> {
+    if !context.matches(query.where_clause.as_ref(), "where")? {
+        return Ok(None);
+    }
+    let mut values = vec![];
+    for item in &query.items {
+        values.push(context.evaluate(&item.expression)?);
+    }
+    Ok(Some(values))
+}
+
+impl Memory {
+    // the select items of a write statement's returning clause, checked against the columns of `table`
+    // before the statement changes anything.
+    fn returning_items(&self, table: &Table, returning: Option<&Returning>) -> Result<Option<Vec<SelectItem>>> {
+        let items: Vec<SelectItem> = match returning {
+            Some(Returning::AllColumns) => table
+                .columns
+                .iter()
+                .map(|column| SelectItem {
+                    expression: synthetic_column(&column.name),
+                    as_name: None,
+                })
+                .collect(),
+            Some(Returning::Items(items)) => items.clone(),
+            None => return Ok(None),
+        };
+
+        // the rows are returned one by one, there is nothing to aggregate them or compute windows over.
+        for item in &items {
+            if let Some(aggregate) = find_aggregate(&item.expression) {
+                return Err(MemoryError::AggregateNotAllowed(aggregate.name.value.clone()));
+            }
+            if let Some(function) = find_window(&item.expression) {
+                return Err(MemoryError::WindowNotAllowed(function.name.value.clone()));
+            }
+        }
+        let outer = RowContext {
+            memory: Some(self),
+            ..Default::default()
+        };
+        let columns = qualify_columns(&table.columns, &table.name);
+        Projection::new(&items, None, &[], None, None, &[])?.result_columns(&outer.nested(&columns))?;
+        Ok(Some(items))
+    }
+
+    // the returning clause of a write statement evaluated over the rows it wrote or deleted from `table`.
+    fn returning(
+        &self,
+        table: &Table,
+        items: Option<&[SelectItem]>,
+        rows: &[&[CellValue]],
+        parameters: &[CellValue],
+    ) -> Result<Option<QueryResult>> {
+        let items = match items {
+            Some(items) => items,
+            None => return Ok(None),
+        };
+
+        let patterns = PatternCache::default();
+        let outer = RowContext {
+            memory: Some(self),
+            patterns: Some(&patterns),
+            parameters,
+            ..Default::default()
+        };
+        let columns = qualify_columns(&table.columns, &table.name);
+        let projection = Projection::new(items, None, &[], None, None, parameters)?;
+        Ok(Some(projection.run(&outer.nested(&columns), rows.iter().copied().map(Ok), None, None)?))
+    }
+
+    // runs a select, `outer` is the row of the enclosing query for subqueries.
+    fn query(&self, select_statement: &SelectStatement, outer: &RowContext) -> Result<QueryResult> {
+        let common_tables = self.common_tables(select_statement, outer)?;
+        let scope = outer.with_common_tables(&common_tables);
+        if select_statement.set_operations.is_empty() {
+            let projection = Projection::new(
+                &select_statement.items,
+                select_statement.distinct.as_ref(),
+                &select_statement.order_by,
+                select_statement.limit.as_ref(),
+                select_statement.offset.as_ref(),
+                scope.parameters,
+            )?;
+            return self.query_core(select_statement, projection, &scope);
+        }
+
+        let projection = Projection::new(
+            &select_statement.items,
+            select_statement.distinct.as_ref(),
+            &[],
+            None,
+            None,
+            &[],
+        )?;
+        let start = Instant::now();
+        let result = self.query_core(select_statement, projection, &scope)?;
+        let mut columns = result.columns.clone();
+        let mut rows = result_values(result);
+        for (index, set_operation) in select_statement.set_operations.iter().enumerate() {
+            let result = self.query(&set_operation.query, &scope)?;
+            columns = combine_columns(columns, &result.columns, set_operation.operator)?;
+            rows = set_operation.combine(rows, result_values(result));
+            scope.record(select_statement, Stage::SetOperation(index), rows.len(), start);
+        }
+        widen_values(&mut rows, &columns);
+        project_combined_rows(select_statement, &columns, &rows, &scope, start)
+    }
+
+    // the select list, from, where, group by and having of a select.
+    fn query_core(
+        &self,
+        select_statement: &SelectStatement,
+        mut projection: Projection,
+        scope: &RowContext,
+    ) -> Result<QueryResult> {
+        let start = Instant::now();
+        let (columns, rows) = self.scan_source(select_statement, scope, start)?;
+        let input = scope.nested(&columns);
+
+        if !AggregatePlan::is_needed(select_statement) {
+            let where_clause = select_statement.where_clause.as_ref();
+            let filter = where_clause.map(|where_clause| (where_clause, "where"));
+            return projection.run_with_windows(select_statement, &input, rows, filter, start);
+        }
+
+        let (plan, having) = AggregatePlan::apply(select_statement, &mut projection, &input)?;
+        let rows = rows.collect::<Result<Vec<_>>>()?;
+        let rows = rows.iter().map(AsRef::as_ref);
+        let groups = plan.group_rows(&input, rows, select_statement.where_clause.as_ref())?;
+        input.record(select_statement, Stage::Aggregate, groups.len(), start);
+        let group_input = RowContext {
+            columns: &plan.columns,
+            ..input
+        };
+        let group_rows = groups.iter().map(Vec::as_slice).map(Ok);
+        let filter = having.as_ref().map(|having| (having, "having"));
+        projection.run_with_windows(select_statement, &group_input, group_rows, filter, start)
+    }
+
+    // the columns `query` would return, without reading any rows.
+    fn describe(&self, select_statement: &SelectStatement, outer: &RowContext) -> Result<Vec<Column>> {
+        let common_tables = self.describe_common_tables(&select_statement.with, outer)?;
+        let scope = outer.with_common_tables(&common_tables);
+
+        let mut columns = self.describe_core(select_statement, &scope)?;
+        for set_operation in &select_statement.set_operations {
+            let right_columns = self.describe(&set_operation.query, &scope)?;
+            columns = combine_columns(columns, &right_columns, set_operation.operator)?;
+        }
+        Ok(columns)
+    }
+
+    fn describe_core(&self, select_statement: &SelectStatement, scope: &RowContext) -> Result<Vec<Column>> {
+        let columns = self.source_columns(select_statement, scope)?;
+        let input = scope.nested(&columns);
+        if let Some(where_clause) = &select_statement.where_clause {
+            expression_type(where_clause, &input)?;
+        }
+
+        let mut projection = Projection::new(&select_statement.items, None, &[], None, None, &[])?;
+        if !AggregatePlan::is_needed(select_statement) {
+            return projection.result_columns(&input);
+        }
+
+        let (plan, _) = AggregatePlan::apply(select_statement, &mut projection, &input)?;
+        projection.result_columns(&RowContext {
+            columns: &plan.columns,
+            ..input
+        })
+    }
+
+    // runs the queries of a select's with clause, each one can read the ones before it.
+    fn common_tables(&self, select_statement: &SelectStatement, outer: &RowContext) -> Result<Vec<CommonTable>> {
+        let mut common_tables = Vec::<CommonTable>::new();
+        for (index, expression) in select_statement.with.iter().enumerate() {
+            let start = Instant::now();
+            let scope = outer.with_common_tables(&common_tables);
+            let (query, recursive_term) = expression.recursive_parts();
+            let result = self.query(&query, &scope)?;
+            let mut common_table = CommonTable {
+                name: expression.name.value.clone(),
+                columns: rename_columns(result.columns.clone(), &expression.name.value, &expression.columns)?,
+                rows: result_values(result),
+            };
+            if let Some(recursive_term) = recursive_term {
+                self.recurse(&mut common_table, recursive_term, &scope)?;
+                let rows = common_table.rows.len();
+                scope.record(select_statement, Stage::CommonTable(index), rows, start);
+            }
+            common_tables.push(common_table);
+        }
+        Ok(common_tables)
+    }
+
+    // the tables `common_tables` would return, without any rows.
+    fn describe_common_tables(
+        &self,
+        with: &[CommonTableExpression],
+        outer: &RowContext,
+    ) -> Result<Vec<CommonTable>> {
+        let mut common_tables = Vec::<CommonTable>::new();
+        for expression in with {
+            let scope = outer.with_common_tables(&common_tables);
+            let columns = self.describe(&expression.recursive_parts().0, &scope)?;
+            common_tables.push(CommonTable {
+                name: expression.name.value.clone(),
+                columns: rename_columns(columns, &expression.name.value, &expression.columns)?,
+                rows: vec![],
+            });
+        }
+        Ok(common_tables)
+    }
+
+    // runs the recursive term over the rows it added last, until it adds none.
+    fn recurse(
+        &self,
+        common_table: &mut CommonTable,
+        recursive_term: &SetOperation,
+        scope: &RowContext,
+    ) -> Result<()> {
+        let limit = self.recursion_limit;
+
+        // a union without all skips the rows that are already there.
+        let mut seen = if recursive_term.is_all {
+            None
+        } else {
+            Some(HashSet::<Vec<CellValue>>::new())
+        };
+        if let Some(seen) = &mut seen {
+            common_table.rows.retain(|row| seen.insert(row.clone()));
+        }
+
+        let mut working_table = CommonTable {
+            name: common_table.name.clone(),
+            columns: common_table.columns.clone(),
+            rows: common_table.rows.clone(),
+        };
+        let mut depth = 0;
+        while !working_table.rows.is_empty() {
+            if depth == limit.max_depth {
+                return Err(MemoryError::RecursionDepthExceeded(common_table.name.clone(), limit.max_depth));
+            }
+            depth += 1;
+
+            let working_scope = scope.with_common_tables(std::slice::from_ref(&working_table));
+            let result = self.query(&recursive_term.query, &working_scope)?;
+            let columns = common_table.columns.clone();
+            common_table.columns = combine_columns(columns, &result.columns, SetOperator::Union)?;
+
+            let mut rows = result_values(result);
+            if let Some(seen) = &mut seen {
+                rows.retain(|row| seen.insert(row.clone()));
+            }
+            if common_table.rows.len() + rows.len() > limit.max_rows {
+                return Err(MemoryError::RecursionRowsExceeded(common_table.name.clone(), limit.max_rows));
+            }
+            common_table.rows.extend(rows.iter().cloned());
+            working_table.rows = rows;
+        }
+        widen_values(&mut common_table.rows, &common_table.columns);
+        Ok(())
+    }
+
+    // `reference` is the node at `index` of the from clause of `query`, counted in preorder.
+    fn scan<'a>(
+        &'a self,
+        query: &SelectStatement,
+        reference: &TableReference,
+        index: usize,
+        outer: &RowContext<'a>,
+    ) -> Result<Relation<'a>> {
+        let start = Instant::now();
+        let relation = self.scan_reference(query, reference, index, outer)?;
+        outer.record(query, Stage::From(index), relation.rows.len(), start);
+        Ok(relation)
+    }
+
+    // the rows of the from clause of `query`, a single row without columns when it has none. Unlike
+    // with `scan`, a view or subquery that is the whole from clause is streamed when it can be.
+    fn scan_source<'a>(
+        &'a self,
+        query: &SelectStatement,
+        scope: &RowContext<'a>,
+        start: Instant,
+    ) -> Result<(Vec<Column>, Rows<'a>)> {
+        let reference = match &query.from {
+            Some(reference) => reference,
+            None => {
+                scope.record(query, Stage::From(0), 1, start);
+                return Ok((vec![], Box::new(iter::once(Ok(Cow::Owned(vec![]))))));
+            }
+        };
+        if let Some((derived_query, derived_scope)) = self.streamed_query(reference, scope) {
+            let columns = self.relation_columns(reference, scope)?;
+            let rows = self.stream(derived_query, derived_scope, (query.id, 0))?;
+            return Ok((columns, Box::new(rows)));
+        }
+
+        let relation = self.scan(query, reference, 0, scope)?;
+        let rows: Rows = match relation.rows {
+            Cow::Borrowed(rows) => Box::new(rows.iter().map(|row| Ok(Cow::Borrowed(row.as_slice())))),
+            Cow::Owned(rows) => Box::new(rows.into_iter().map(|row| Ok(Cow::Owned(row)))),
+        };
+        Ok((relation.columns, rows))
+    }
+
+    // the query of the view or subquery `reference` and the scope it runs in, if its rows can be
+    // made one at a time.
+    fn streamed_query<'a>(
+        &'a self,
+        reference: &TableReference,
+        outer: &RowContext<'a>,
+    ) -> Option<(Cow<'a, SelectStatement>, RowContext<'a>)> {
+        match reference {
+            TableReference::Table { name, .. } if outer.find_common_table(&name.value).is_none() => {
+                let view = self.views.get(&name.value)?;
+                is_streamable(&view.query).then(|| (Cow::Borrowed(&view.query), outer.view_scope()))
+            }
+            TableReference::Subquery { query, .. } => {
+                is_streamable(query).then(|| (Cow::Owned(query.as_ref().clone()), *outer))
+            }
+            _ => None,
+        }
+    }
+
+    // the rows of `query`, which has to be streamable, as they are read.
+    fn stream<'a>(
+        &'a self,
+        query: Cow<'a, SelectStatement>,
+        scope: RowContext<'a>,
+        scanned_as: (QueryId, usize),
+    ) -> Result<DerivedRows<'a>> {
+        let start = Instant::now();
+        let (columns, source) = self.scan_source(&query, &scope, start)?;
+        Ok(DerivedRows {
+            query,
+            scope,
+            columns,
+            source,
+            scanned_as,
+            rows: 0,
+            start,
+        })
+    }
+
+    fn scan_reference<'a>(
+        &'a self,
+        query: &SelectStatement,
+        reference: &TableReference,
+        index: usize,
+        outer: &RowContext<'a>,
+    ) -> Result<Relation<'a>> {
+        match reference {
+            TableReference::Table { name, alias } => {
+                let qualifier = &alias.as_ref().unwrap_or(name).value;
+                if let Some(common_table) = outer.find_common_table(&name.value) {
+                    return Ok(Relation {
+                        columns: qualify_columns(&common_table.columns, qualifier),
+                        rows: Cow::Borrowed(&common_table.rows),
+                    });
+                }
+                if let Some(view) = self.views.get(&name.value) {
+                    let result = self.query(&view.query, &outer.view_scope())?;
+                    let columns = rename_columns(result.columns.clone(), &name.value, &view.columns)?;
+                    return Ok(Relation {
+                        columns: qualify_columns(&columns, qualifier),
+                        rows: Cow::Owned(result_values(result)),
+                    });
+                }
+                let table = self.get_table(&name.value)?;
+                Ok(Relation {
+                    columns: qualify_columns(&table.columns, qualifier),
+                    rows: Cow::Borrowed(&table.rows),
+                })
+            }
+            TableReference::Subquery { query, alias } => {
+                let result = self.query(query, outer)?;
+                Ok(Relation {
+                    columns: qualify_columns(&result.columns, &alias.value),
+                    rows: Cow::Owned(result_values(result)),
+                })
+            }
+            TableReference::Values { rows, .. } => {
+                let mut values = vec![];
+                for row in rows {
+                    values.push(row.iter().map(|value| outer.evaluate(value)).collect::<Result<Vec<_>>>()?);
+                }
+                let columns = self.relation_columns(reference, outer)?;
+                widen_values(&mut values, &columns);
+                Ok(Relation {
+                    columns,
+                    rows: Cow::Owned(values),
+                })
+            }
+            TableReference::Join {
+                left,
+                right,
+                kind,
+                constraint,
+            } => {
+                let left_relation = self.scan(query, left, index + 1, outer)?;
+                let right_relation = self.scan(query, right, index + 1 + left.node_count(), outer)?;
+                join(left_relation, right_relation, *kind, constraint, outer)
+            }
+        }
+    }
+
+    // the columns the from clause of `query` reads, none without a from clause.
+    fn source_columns(&self, query: &SelectStatement, outer: &RowContext) -> Result<Vec<Column>> {
+        match &query.from {
+            Some(from) => self.relation_columns(from, outer),
+            None => Ok(vec![]),
+        }
+    }
+
+    // the columns `scan` would return.
+    fn relation_columns(&self, reference: &TableReference, outer: &RowContext) -> Result<Vec<Column>> {
+        match reference {
+            TableReference::Table { name, alias } => {
+                let qualifier = &alias.as_ref().unwrap_or(name).value;
+                if let Some(common_table) = outer.find_common_table(&name.value) {
+                    return Ok(qualify_columns(&common_table.columns, qualifier));
+                }
+                if let Some(view) = self.views.get(&name.value) {
+                    let columns = self.describe(&view.query, &outer.view_scope())?;
+                    return Ok(qualify_columns(&rename_columns(columns, &name.value, &view.columns)?, qualifier));
+                }
+                let table = self.get_table(&name.value)?;
+                Ok(qualify_columns(&table.columns, qualifier))
+            }
+            TableReference::Subquery { query, alias } => {
+                Ok(qualify_columns(&self.describe(query, outer)?, &alias.value))
+            }
+            TableReference::Values { rows, alias, columns } => {
+                let qualifier = alias.as_ref().map_or("*values*", |alias| &alias.value);
+                let mut values_columns = vec![];
+                for index in 0..rows[0].len() {
+                    let values: Vec<Expression> = rows.iter().map(|row| row[index].clone()).collect();
+                    values_columns.push(Column {
+                        name: format!("column{}", index + 1),
+                        column_type: common_type(&values, "values", outer)?,
+                        ..Default::default()
+                    });
+                }
+                Ok(qualify_columns(&rename_columns(values_columns, qualifier, columns)?, qualifier))
+            }
+            TableReference::Join {
+                left,
+                right,
+                constraint,
+                ..
+            } => {
+                let left = self.relation_columns(left, outer)?;
+                let right = self.relation_columns(right, outer)?;
+                let columns = join_columns(&left, &right, constraint)?.0;
+                if let JoinConstraint::On(condition) = constraint {
+                    expression_type(condition, &outer.nested(&columns))?;
+                }
+                Ok(columns)
+            }
+        }
+    }
+}
+
+impl Memory {
+    // the operators `query` runs, `common_tables` are the names of the with clause queries it can read.
+    fn plan(&self, query: &SelectStatement, common_tables: &[String], profile: Option<&Profile>) -> PlanNode {
+        let mut names = common_tables.to_vec();
+        let mut common_table_plans = vec![];
+        for (index, expression) in query.with.iter().enumerate() {
+            let (base, recursive_term) = expression.recursive_parts();
+            let mut node = match recursive_term {
+                Some(recursive_term) => {
+                    // the recursive term can read the common table itself.
+                    let base = self.plan(&base, &names, profile);
+                    let mut recursive_names = names.clone();
+                    recursive_names.push(expression.name.value.clone());
+                    let mut node = PlanNode::new("Recursive Union");
+                    node.actual = profile.and_then(|profile| profile.stats(query.id, Stage::CommonTable(index)));
+                    node.children = vec![base, self.plan(&recursive_term.query, &recursive_names, profile)];
+                    node
+                }
+                None => self.plan(&base, &names, profile),
+            };
+            node.relationship = Some(format!("CTE {}", expression.name.value));
+            common_table_plans.push(node);
+            names.push(expression.name.value.clone());
+        }
+
+        let mut root = if query.set_operations.is_empty() {
+            self.plan_core(query, &names, true, profile)
+        } else {
+            let mut node = self.plan_core(query, &names, false, profile);
+            for (index, set_operation) in query.set_operations.iter().enumerate() {
+                let operator = match (set_operation.operator, set_operation.is_all) {
+                    (SetOperator::Union, false) => "Union",
+                    (SetOperator::Union, true) => "Union All",
+                    (SetOperator::Intersect, false) => "Intersect",
+                    (SetOperator::Intersect, true) => "Intersect All",
+                    (SetOperator::Except, false) => "Except",
+                    (SetOperator::Except, true) => "Except All",
+                };
+                let mut combined = PlanNode::new(operator);
+                combined.actual = profile.and_then(|profile| profile.stats(query.id, Stage::SetOperation(index)));
+                combined.children = vec![node, self.plan(&set_operation.query, &names, profile)];
+                node = combined;
+            }
+            let mut project = PlanNode::new("Project");
+            project.actual = profile.and_then(|profile| profile.stats(query.id, Stage::Combine));
+            project.children.push(node);
+            plan_order(project, query, None, profile)
+        };
+        root.children.extend(common_table_plans);
+        root
+    }
+
+    // the operators of a single select, without its set operations. `is_sorted` is false when the
+    // order by, limit and offset apply to the rows of the set operations instead.
+    fn plan_core(
+        &self,
+        query: &SelectStatement,
+        common_tables: &[String],
+        is_sorted: bool,
+        profile: Option<&Profile>,
+    ) -> PlanNode {
+        let stats = |stage| profile.and_then(|profile| profile.stats(query.id, stage));
+        let mut input = match &query.from {
+            Some(from) => self.plan_from(query, from, 0, common_tables, profile),
+            None => {
+                let mut result = PlanNode::new("Result");
+                result.actual = stats(Stage::From(0));
+                result
+            }
+        };
+
+        let mut filter = query.where_clause.as_ref();
+        if AggregatePlan::is_needed(query) {
+            let mut aggregate = PlanNode::new("Aggregate");
+            if !query.group_by.is_empty() {
+                aggregate.details.push(("Group Key", expression_list(&query.group_by)));
+            }
+            if let Some(filter) = filter {
+                aggregate.details.push(("Filter", filter.to_string()));
+            }
+            aggregate.actual = stats(Stage::Aggregate);
+            aggregate.children.push(input);
+            input = aggregate;
+            filter = query.having.as_ref();
+        }
+
+        let has_windows = query.items.iter().any(|item| find_window(&item.expression).is_some())
+            || query.order_by.iter().any(|item| find_window(&item.expression).is_some());
+        if has_windows {
+            let mut window = PlanNode::new("WindowAgg");
+            if let Some(filter) = filter.take() {
+                window.details.push(("Filter", filter.to_string()));
+            }
+            window.actual = stats(Stage::Window);
+            window.children.push(input);
+            input = window;
+        }
+
+        let mut project = PlanNode::new("Project");
+        project.details.push(("Output", item_list(&query.items)));
+        if let Some(filter) = filter {
+            project.details.push(("Filter", filter.to_string()));
+        }
+        project.actual = stats(Stage::Project);
+        project.children.push(input);
+
+        // the subqueries of the expressions, the ones of the from clause are inputs already.
+        let mut subqueries = vec![];
+        let mut expressions = query_expressions(query);
+        add_from_expressions(query.from.as_ref(), &mut expressions);
+        for expression in expressions {
+            add_subqueries(expression, &mut subqueries);
+        }
+        for subquery in subqueries {
+            let mut node = self.plan(subquery, common_tables, profile);
+            node.relationship = Some(String::from("SubPlan"));
+            project.children.push(node);
+        }
+        if is_sorted {
+            return plan_order(project, query, query.distinct.as_ref(), profile);
+        }
+        match &query.distinct {
+            Some(distinct) => plan_unique(project, query, distinct, profile),
+            None => project,
+        }
+    }
+
+    // the plan of the node at `index` of the from clause of `query`, counted like `scan` does.
+    fn plan_from(
+        &self,
+        query: &SelectStatement,
+        reference: &TableReference,
+        index: usize,
+        common_tables: &[String],
+        profile: Option<&Profile>,
+    ) -> PlanNode {
+        let mut node = match reference {
+            TableReference::Table { name, alias } => {
+                let mut node = if common_tables.contains(&name.value) {
+                    PlanNode::new("CTE Scan")
+                } else if let Some(view) = self.views.get(&name.value) {
+                    let mut node = PlanNode::new("View Scan");
+                    node.children.push(self.plan(&view.query, &[], profile));
+                    node
+                } else {
+                    PlanNode::new("Seq Scan")
+                };
+                node.relation = Some(match alias {
+                    Some(alias) => format!("{} {}", name.value, alias.value),
+                    None => name.value.clone(),
+                });
+                node
+            }
+            TableReference::Subquery { query, alias } => {
+                let mut node = PlanNode::new("Subquery Scan");
+                node.relation = Some(alias.value.clone());
+                node.children.push(self.plan(query, common_tables, profile));
+                node
+            }
+            TableReference::Values { rows, alias, .. } => {
+                let mut node = PlanNode::new("Values Scan");
+                node.relation = Some(alias.as_ref().map_or("*values*", |alias| &alias.value).to_string());
+                node.details.push(("Rows", rows.len().to_string()));
+                node
+            }
+            TableReference::Join {
+                left,
+                right,
+                kind,
+                constraint,
+            } => {
+                let mut node = PlanNode::new("Nested Loop");
+                let kind = match kind {
+                    JoinKind::Inner => "Inner",
+                    JoinKind::Left => "Left",
+                    JoinKind::Right => "Right",
+                    JoinKind::Full => "Full",
+                    JoinKind::Cross => "Cross",
+                };
+                node.details.push(("Join Type", String::from(kind)));
+                match constraint {
+                    JoinConstraint::On(condition) => node.details.push(("Join Filter", condition.to_string())),
+                    JoinConstraint::Using(names) => {
+                        let names: Vec<&str> = names.iter().map(|name| name.value.as_str()).collect();
+                        node.details.push(("Using", names.join(", ")));
+                    }
+                    JoinConstraint::None => {}
+                }
+                let right_index = index + 1 + left.node_count();
+                node.children = vec![
+                    self.plan_from(query, left, index + 1, common_tables, profile),
+                    self.plan_from(query, right, right_index, common_tables, profile),
+                ];
+                node
+            }
+        };
+        node.actual = profile.and_then(|profile| profile.stats(query.id, Stage::From(index)));
+        node
+    }
+}
+
+impl Memory {
+    // the operators of an insert, update or delete, which are checked like `describe` checks a select.
+    fn plan_write(&self, statement: &Statement, profile: Option<&Profile>) -> Result<PlanNode> {
+        let (operator, table_name, returning) = match statement {
+            Statement::Insert(insert_statement) => {
+                ("Insert", &insert_statement.table, insert_statement.returning.as_ref())
+            }
+            Statement::Update(update_statement) => {
+                ("Update", &update_statement.table, update_statement.returning.as_ref())
+            }
+            Statement::Delete(delete_statement) => {
+                ("Delete", &delete_statement.table, delete_statement.returning.as_ref())
+            }
+            _ => return Err(MemoryError::QueryNotValid),
+        };
+        let stats = |stage| profile.and_then(|profile| profile.write_stats(stage));
+        let table = self.get_table(&table_name.value)?;
+        let outer = RowContext {
+            memory: Some(self),
+            ..Default::default()
+        };
+        let columns = qualify_columns(&table.columns, &table.name);
+        let input = outer.nested(&columns);
+
+        let mut node = PlanNode::new(operator);
+        node.relation = Some(table_name.value.clone());
+        // the expressions evaluated on the rows, their subqueries are shown as subplans.
+        let mut expressions = Vec::<&Expression>::new();
+        let source = match statement {
+            Statement::Insert(insert_statement) => {
+                if let Some(on_conflict) = &insert_statement.on_conflict {
+                    table.conflict_keys(&on_conflict.columns)?;
+                    if !on_conflict.columns.is_empty() {
+                        let names: Vec<&str> = on_conflict.columns.iter().map(|name| name.value.as_str()).collect();
+                        node.details.push(("Conflict Target", names.join(", ")));
+                    }
+                    match &on_conflict.action {
+                        ConflictAction::Nothing => node.details.push(("Conflict Action", String::from("nothing"))),
+                        ConflictAction::Update {
+                            assignments,
+                            where_clause,
+                        } => {
+                            node.details.push(("Conflict Action", String::from("update")));
+                            if let Some(where_clause) = where_clause {
+                                node.details.push(("Conflict Filter", where_clause.to_string()));
+                            }
+                            expressions.extend(assignments.iter().map(|assignment| &assignment.value));
+                            expressions.extend(where_clause);
+                        }
+                    }
+                }
+                match &insert_statement.source {
+                    InsertSource::Values(values) => {
+                        expressions.extend(values);
+                        let mut result = PlanNode::new("Result");
+                        result.actual = stats(Stage::From(0));
+                        result
+                    }
+                    InsertSource::Query(query) => {
+                        self.describe(query, &outer)?;
+                        self.plan(query, &[], profile)
+                    }
+                }
+            }
+            Statement::Update(update_statement) => {
+                table.assigned_columns(&update_statement.assignments)?;
+                for assignment in &update_statement.assignments {
+                    expression_type(&assignment.value, &input)?;
+                    expressions.push(&assignment.value);
+                }
+                plan_target_scan(table_name, update_statement.where_clause.as_ref(), &input, profile)?
+            }
+            Statement::Delete(delete_statement) => {
+                plan_target_scan(table_name, delete_statement.where_clause.as_ref(), &input, profile)?
+            }
+            _ => return Err(MemoryError::QueryNotValid),
+        };
+        if let Some(items) = self.returning_items(table, returning)? {
+            node.details.push(("Returning", item_list(&items)));
+        }
+        if let Some(Returning::Items(items)) = returning {
+            expressions.extend(items.iter().map(|item| &item.expression));
+        }
+        node.actual = stats(Stage::Modify);
+        node.children.push(source);
+
+        let mut subqueries = vec![];
+        for expression in expressions {
+            add_subqueries(expression, &mut subqueries);
+        }
+        for subquery in subqueries {
+            let mut subplan = self.plan(subquery, &[], profile);
+            subplan.relationship = Some(String::from("SubPlan"));
+            node.children.push(subplan);
+        }
+        Ok(node)
+    }
+}
+
+// the scan of the table an update or delete changes, for the rows that pass `where_clause`.
+fn plan_target_scan(
+    table_name: &Token,
+    where_clause: Option<&Expression>,
+    input: &RowContext,
+    profile: Option<&Profile>,
+) -> Result<PlanNode> {
+    let mut scan = PlanNode::new("Seq Scan");
+    scan.relation = Some(table_name.value.clone());
+    if let Some(where_clause) = where_clause {
+        expression_type(where_clause, input)?;
+        scan.details.push(("Filter", where_clause.to_string()));
+    }
+    scan.actual = profile.and_then(|profile| profile.write_stats(Stage::From(0)));
+    Ok(scan)
+}
+
+// the select items as the plans show them.
+fn item_list(items: &[SelectItem]) -> String {
+    let items: Vec<String> = items
+        .iter()
+        .map(|item| match &item.as_name {
+            Some(name) => format!("{} as {}", item.expression, name.value),
+            None => item.expression.to_string(),
+        })
+        .collect();
+    items.join(", ")
+}
+
+// the sort, distinct and limit of `query` over the projected rows in `node`, in the order they run.
+fn plan_order(
+    mut node: PlanNode,
+    query: &SelectStatement,
+    distinct: Option<&Distinct>,
+    profile: Option<&Profile>,
+) -> PlanNode {
+    if !query.order_by.is_empty() {
+        let items: Vec<String> = query.order_by.iter().map(|item| item.to_string()).collect();
+        let mut sort = PlanNode::new("Sort");
+        sort.details.push(("Sort Key", items.join(", ")));
+        sort.actual = profile.and_then(|profile| profile.stats(query.id, Stage::Sort));
+        sort.children.push(node);
+        node = sort;
+    }
+    if let Some(distinct) = distinct {
+        node = plan_unique(node, query, distinct, profile);
+    }
+    if query.limit.is_some() || query.offset.is_some() {
+        let mut limit = PlanNode::new("Limit");
+        if let Some(count) = &query.limit {
+            limit.details.push(("Count", count.to_string()));
+        }
+        if let Some(offset) = &query.offset {
+            limit.details.push(("Offset", offset.to_string()));
+        }
+        limit.actual = profile.and_then(|profile| profile.stats(query.id, Stage::Limit));
+        limit.children.push(node);
+        node = limit;
+    }
+    node
+}
+
+fn plan_unique(node: PlanNode, query: &SelectStatement, distinct: &Distinct, profile: Option<&Profile>) -> PlanNode {
+    let mut unique = PlanNode::new("Unique");
+    if let Distinct::On(expressions) = distinct {
+        unique.details.push(("Distinct On", expression_list(expressions)));
+    }
+    unique.actual = profile.and_then(|profile| profile.stats(query.id, Stage::Unique));
+    unique.children.push(node);
+    unique
+}
+
+fn expression_list(expressions: &[Expression]) -> String {
+    let expressions: Vec<String> = expressions.iter().map(|expression| expression.to_string()).collect();
+    expressions.join(", ")
+}
+
+impl TableReference {
+    // the count of tables, subqueries and joins in the reference.
+    fn node_count(&self) -> usize {
+        match self {
+            TableReference::Join { left, right, .. } => 1 + left.node_count() + right.node_count(),
+            _ => 1,
+        }
+    }
+}
+
+impl CommonTableExpression {
+    // a recursive query is split at its last union, the query after it is run until it adds no rows.
+    fn recursive_parts(&self) -> (Cow<'_, SelectStatement>, Option<&SetOperation>) {
+        match self.query.set_operations.last() {
+            Some(last) if self.is_recursive && last.operator == SetOperator::Union => {
+                let mut query = self.query.clone();
+                query.set_operations.pop();
+                (Cow::Owned(query), Some(last))
+            }
+            _ => (Cow::Borrowed(&self.query), None),
+        }
+    }
+}
+
+impl SetOperator {
+    fn name(self) -> &'static str {
+        match self {
+            SetOperator::Union => "union",
+            SetOperator::Intersect => "intersect",
+            SetOperator::Except => "except",
+        }
+    }
+}
+
+impl SetOperation {
+    // rows are compared like group by values, so nulls are equal to each other.
+    fn combine(&self, left: Vec<Vec<CellValue>>, right: Vec<Vec<CellValue>>) -> Vec<Vec<CellValue>> {
+        let rows = match self.operator {
+            SetOperator::Union => {
+                let mut rows = left;
+                rows.extend(right);
+                rows
+            }
+            SetOperator::Intersect | SetOperator::Except => {
+                // with all, every row on the right cancels out a single row on the left.
+                let mut right_counts = HashMap::<Vec<CellValue>, usize>::new();
+                for row in right {
+                    *right_counts.entry(row).or_insert(0) += 1;
+                }
+                let is_intersect = self.operator == SetOperator::Intersect;
+                left.into_iter()
+                    .filter(|row| match right_counts.get_mut(row) {
+                        Some(count) if *count > 0 => {
+                            if self.is_all {
+                                *count -= 1;
+                            }
+                            is_intersect
+                        }
+                        _ => !is_intersect,
+                    })
+                    .collect()
+            }
+        };
+
+        if self.is_all {
+            rows
+        } else {
+            distinct_rows(rows)
+        }
+    }
+}
+
+// keeps the first of every group of equal rows.
+fn distinct_rows(mut rows: Vec<Vec<CellValue>>) -> Vec<Vec<CellValue>> {
+    let mut seen = HashSet::<Vec<CellValue>>::new();
+    rows.retain(|row| seen.insert(row.clone()));
+    rows
+}
+
+// the columns of a set operation, named after the left side. both sides need the same
+// number of columns and matching types, ints and floats combine into floats.
+fn combine_columns(mut left: Vec<Column>, right: &[Column], operator: SetOperator) -> Result<Vec<Column>> {
+    if left.len() != right.len() {
+        return Err(MemoryError::SetOperationColumns(operator.name().to_string()));
+    }
+    for (left_column, right_column) in left.iter_mut().zip(right) {
+        left_column.column_type = unify_types(&left_column.column_type, &right_column.column_type)
+            .ok_or_else(|| {
+                MemoryError::IncompatibleTypes(
+                    operator.name().to_string(),
+                    left_column.column_type.clone(),
+                    right_column.column_type.clone(),
+                )
+            })?;
+    }
+    Ok(left)
+}
+
+// turns the ints in float columns into floats, after combining int and float columns.
+fn widen_values(rows: &mut [Vec<CellValue>], columns: &[Column]) {
+    for row in rows {
+        for (value, column) in row.iter_mut().zip(columns) {
+            if let (CellValue::Int(int), CellType::Float) = (&value, &column.column_type) {
+                *value = CellValue::Float(*int as f64);
+            }
+        }
+    }
+}
+
+// the order by, limit and offset of a select with set operations, which can only use the output columns.
+fn project_combined_rows(
+    select_statement: &SelectStatement,
+    columns: &[Column],
+    rows: &[Vec<CellValue>],
+    scope: &RowContext,
+    start: Instant,
+) -> Result<QueryResult> {
+    // the rows are read through made up names, so output names that appear twice don't clash.
+    let mut items = vec![];
+    let mut input_columns = vec![];
+    for (index, column) in columns.iter().enumerate() {
+        let name = format!("#column{}", index);
+        items.push(SelectItem {
+            expression: synthetic_column(&name),
+            as_name: Some(Token {
+                value: column.name.clone(),
+                kind: TokenKind::Identifier,
+            }),
+        });
+        input_columns.push(Column {
+            name,
+            column_type: column.column_type.clone(),
+            ..Default::default()
+        });
+    }
+
+    let projection = Projection::new(
+        &items,
+        None,
+        &select_statement.order_by,
+        select_statement.limit.as_ref(),
+        select_statement.offset.as_ref(),
+        scope.parameters,
+    )?;
+    let profiled = Profiled {
+        query: select_statement,
+        stage: Stage::Combine,
+        start,
+    };
+    let rows = rows.iter().map(Vec::as_slice).map(Ok);
+    projection.run(&scope.nested(&input_columns), rows, None, Some(profiled))
+}
+
+fn result_values(result: QueryResult) -> Vec<Vec<CellValue>> {
+    result
+        .rows
+        .into_iter()
+        .map(|row| row.into_iter().map(|cell| cell.value).collect())
+        .collect()
+}
+
+// a result with a single text column and a row for each line.
+fn text_result(column_name: &str, lines: Vec<String>) -> QueryResult {
+    QueryResult {
+        columns: vec![Column {
+            name: column_name.to_string(),
+            column_type: CellType::Text,
+            ..Default::default()
+        }],
+        rows: lines
+            .into_iter()
+            .map(|line| {
+                vec![Cell {
+                    value: CellValue::Text(line),
+                    column_name: column_name.to_string(),
+                    cell_type: CellType::Text,
+                }]
+            })
+            .collect(),
+    }
+}
+
+// the tables and views `query` and its subqueries read, the queries of its with clauses aren't included.
+fn query_relations(query: &SelectStatement) -> Vec<String> {
+    let mut relations = vec![];
+    let mut common_tables = vec![];
+    add_query_relations(query, &mut relations, &mut common_tables);
+    relations.retain(|relation| !common_tables.contains(relation));
+    relations
+}
+
+fn add_query_relations(query: &SelectStatement, relations: &mut Vec<String>, common_tables: &mut Vec<String>) {
+    for expression in &query.with {
+        common_tables.push(expression.name.value.clone());
+        add_query_relations(&expression.query, relations, common_tables);
+    }
+    if let Some(from) = &query.from {
+        add_reference_relations(from, relations, common_tables);
+    }
+    for expression in query_expressions(query) {
+        add_expression_relations(expression, relations, common_tables);
+    }
+
+    for set_operation in &query.set_operations {
+        add_query_relations(&set_operation.query, relations, common_tables);
+    }
+}
+
+// the expressions of a select outside of its from clause, with clause and set operations.
+fn query_expressions(query: &SelectStatement) -> Vec<&Expression> {
+    let mut expressions: Vec<&Expression> = query.items.iter().map(|item| &item.expression).collect();
+    expressions.extend(&query.where_clause);
+    expressions.extend(&query.group_by);
+    expressions.extend(&query.having);
+    expressions.extend(query.order_by.iter().map(|item| &item.expression));
+    expressions.extend(&query.limit);
+    expressions.extend(&query.offset);
+    if let Some(Distinct::On(distinct_on)) = &query.distinct {
+        expressions.extend(distinct_on);
+    }
+    expressions
+}
+
+// the join conditions and the values of a from clause.
+fn add_from_expressions<'a>(reference: Option<&'a TableReference>, expressions: &mut Vec<&'a Expression>) {
+    match reference {
+        Some(TableReference::Join {
+            left,
+            right,
+            constraint,
+            ..
+        }) => {
+            add_from_expressions(Some(left), expressions);
+            add_from_expressions(Some(right), expressions);
+            if let JoinConstraint::On(condition) = constraint {
+                expressions.push(condition);
+            }
+        }
+        Some(TableReference::Values { rows, .. }) => expressions.extend(rows.iter().flatten()),
+        _ => {}
+    }
+}
+
+// calls `visit` with the subqueries of the from clause, the ones of joins included.
+fn add_subquery_sources(
+    reference: &TableReference,
+    visit: &mut dyn FnMut(&SelectStatement) -> Result<()>,
+) -> Result<()> {
+    match reference {
+        TableReference::Table { .. } | TableReference::Values { .. } => Ok(()),
+        TableReference::Subquery { query, .. } => visit(query),
+        TableReference::Join { left, right, .. } => {
+            add_subquery_sources(left, visit)?;
+            add_subquery_sources(right, visit)
+        }
+    }
+}
+
+// the subqueries of `expression`, without the ones nested in them.
+fn add_subqueries<'a>(expression: &'a Expression, subqueries: &mut Vec<&'a SelectStatement>) {
+    match expression {
+        Expression::Subquery(subquery) | Expression::Exists(subquery) | Expression::InSubquery { subquery, .. } => {
+            subqueries.push(subquery)
+        }
+        _ => {}
+    }
+    for child in expression.children() {
+        add_subqueries(child, subqueries);
+    }
+}
+
+fn add_reference_relations(reference: &TableReference, relations: &mut Vec<String>, common_tables: &mut Vec<String>) {
+    match reference {
+        TableReference::Table { name, .. } => {
+            if !relations.contains(&name.value) {
+                relations.push(name.value.clone());
+            }
+        }
+        TableReference::Subquery { query, .. } => add_query_relations(query, relations, common_tables),
+        TableReference::Values { rows, .. } => {
+            for value in rows.iter().flatten() {
+                add_expression_relations(value, relations, common_tables);
+            }
+        }
+        TableReference::Join {
+            left,
+            right,
+            constraint,
+            ..
+        } => {
+            add_reference_relations(left, relations, common_tables);
+            add_reference_relations(right, relations, common_tables);
+            if let JoinConstraint::On(condition) = constraint {
+                add_expression_relations(condition, relations, common_tables);
+            }
+        }
+    }
+}
+
+fn add_expression_relations(expression: &Expression, relations: &mut Vec<String>, common_tables: &mut Vec<String>) {
+    match expression {
+        Expression::Subquery(subquery) | Expression::Exists(subquery) | Expression::InSubquery { subquery, .. } => {
+            add_query_relations(subquery, relations, common_tables)
+        }
+        _ => {}
+    }
+    for child in expression.children() {
+        add_expression_relations(child, relations, common_tables);
+    }
+}
+
+// gives the columns of a with clause query or a view the names listed after its name, if any.
+fn rename_columns(mut columns: Vec<Column>, name: &str, names: &[Token]) -> Result<Vec<Column>> {
+    if names.len() > columns.len() {
+        return Err(MemoryError::TooManyColumnNames(name.to_string(), columns.len(), names.len()));
+    }
+    for (column, name) in columns.iter_mut().zip(names) {
+        column.name = name.value.clone();
+    }
+    Ok(columns)
+}
+
+// the columns of a table or subquery as a from clause sees them, read through `table`.
+fn qualify_columns(columns: &[Column], table: &str) -> Vec<Column> {
+    columns
+        .iter()
+        .map(|column| Column {
+            name: column.name.clone(),
+            column_type: column.column_type.clone(),
+            table: Some(table.to_string()),
+            ..Default::default()
+        })
+        .collect()
+}
+
+// the left and right index of each pair of columns a join's `using` compares.
+type UsingColumns = Vec<(usize, usize)>;
+
+// the columns of a join and the pairs of columns `using` compares. the columns named
+// in `using` are merged into a single column in front of the others and hide the originals.
+fn join_columns(
+    left: &[Column],
+    right: &[Column],
+    constraint: &JoinConstraint,
+) -> Result<(Vec<Column>, UsingColumns)> {
+    let mut columns = left.to_vec();
+    columns.extend_from_slice(right);
+
+    let mut using_columns = UsingColumns::new();
+    let mut merged_columns = Vec::<Column>::new();
+    if let JoinConstraint::Using(names) = constraint {
+        for name in names {
+            let left_index = resolve_column(left, None, name)?;
+            let right_index = resolve_column(right, None, name)?;
+            columns[left_index].is_hidden = true;
+            columns[left.len() + right_index].is_hidden = true;
+            using_columns.push((left_index, right_index));
+            merged_columns.push(Column {
+                name: name.value.clone(),
+                column_type: left[left_index].column_type.clone(),
+                ..Default::default()
+            });
+        }
+    }
+
+    merged_columns.append(&mut columns);
+    Ok((merged_columns, using_columns))
+}
+
+// a nested loop join, the rows of an outer join that match nothing are padded with nulls.
+fn join<'a>(
+    left: Relation,
+    right: Relation,
+    kind: JoinKind,
+    constraint: &JoinConstraint,
+    outer: &RowContext,
+) -> Result<Relation<'a>> {
+    let (columns, using_columns) = join_columns(&left.columns, &right.columns, constraint)?;
+    let context = outer.nested(&columns);
+
+    let mut rows = Vec::<Vec<CellValue>>::new();
+    let mut is_right_matched = vec![false; right.rows.len()];
+    for left_row in left.rows.iter() {
+        let mut is_left_matched = false;
+        for (right_index, right_row) in right.rows.iter().enumerate() {
+            let mut row = left_row.clone();
+            row.extend(right_row.iter().cloned());
+
+            let is_match = match constraint {
+                JoinConstraint::On(condition) => context.with_row(&row).matches(Some(condition), "on")?,
+                JoinConstraint::Using(_) => using_columns
+                    .iter()
+                    .all(|&(left_index, right_index)| left_row[left_index].is_same_as(&right_row[right_index])),
+                JoinConstraint::None => true,
+            };
+            if is_match {
+                is_left_matched = true;
+                is_right_matched[right_index] = true;
+                rows.push(row);
+            }
+        }
+
+        if !is_left_matched && (kind == JoinKind::Left || kind == JoinKind::Full) {
+            let mut row = left_row.clone();
+            row.extend(vec![CellValue::Null; right.columns.len()]);
+            rows.push(row);
+        }
+    }
+    if kind == JoinKind::Right || kind == JoinKind::Full {
+        for (right_row, is_matched) in right.rows.iter().zip(is_right_matched) {
+            if !is_matched {
+                let mut row = vec![CellValue::Null; left.columns.len()];
+                row.extend(right_row.iter().cloned());
+                rows.push(row);
+            }
+        }
+    }
+
+    if !using_columns.is_empty() {
+        for row in &mut rows {
+            let mut merged_row = Vec::<CellValue>::new();
+            for &(left_index, right_index) in &using_columns {
+                merged_row.push(match &row[left_index] {
+                    CellValue::Null => row[left.columns.len() + right_index].clone(),
+                    value => value.clone(),
+                });
+            }
+            merged_row.append(row);
+            *row = merged_row;
+        }
+    }
+
+    Ok(Relation {
+        columns,
+        rows: Cow::Owned(rows),
+    })
+}
+
+impl SelectItem {
+    // the column name in the query result
+    fn output_name(&self) -> String {
+        match (&self.as_name, &self.expression) {
+            (Some(as_name), _) => as_name.value.clone(),
+            (None, Expression::Column { name, .. }) => name.value.clone(),
+            // like postgres, a function call is named after the function, e.g. `count`.
+            (None, Expression::Function(call)) | (None, Expression::Window { function: call, .. }) => {
+                call.name.value.clone()
+            }
+            (None, _) => "?column?".to_string(),
+        }
+    }
+}
+
+// the type of the values `expression` evaluates to on rows of `context`.
+fn expression_type(expression: &Expression, context: &RowContext) -> Result<CellType> {
+    match expression {
+        Expression::Literal(token) => Ok(match CellValue::from_literal(token)? {
+            CellValue::Int(_) => CellType::Int,
+            CellValue::Float(_) => CellType::Float,
+            CellValue::Bool(_) => CellType::Bool,
+            CellValue::Text(_) | CellValue::Null => CellType::Text,
+        }),
+        // only a statement that is being prepared has parameters, they have no value yet, like a null.
+        Expression::Parameter(_) => Ok(CellType::Text),
+        Expression::Column { table, name } => {
+            match (resolve_column(context.columns, table.as_ref(), name), context.outer) {
+                (Ok(col_index), _) => Ok(context.columns[col_index].column_type.clone()),
+                (Err(MemoryError::ColumnNotFound(_)), Some(outer)) => expression_type(expression, outer),
+                (Err(err), _) => Err(err),
+            }
+        }
+        Expression::Function(call) if is_aggregate(call) => aggregate_type(call, context),
+        Expression::Window { function, window } => window_type(function, window, context),
+        Expression::Function(call) => {
+            check_scalar_function(call)?;
+            match call.name.value.as_str() {
+                "nullif" => expression_type(&call.args[0], context),
+                "upper" | "lower" => match expression_type(&call.args[0], context)? {
+                    CellType::Text => Ok(CellType::Text),
+                    _ => Err(MemoryError::InvalidArguments(call.name.value.clone())),
+                },
+                name => common_type(&call.args, name, context),
+            }
+        }
+        Expression::Unary { operator, operand } => match operator {
+            UnaryOperator::Not => Ok(CellType::Bool),
+            UnaryOperator::Minus => expression_type(operand, context),
+        },
+        Expression::Binary {
+            left,
+            operator,
+            right,
+        } => {
+            let left = expression_type(left, context)?;
+            let right = expression_type(right, context)?;
+            Ok(match operator {
+                BinaryOperator::Plus
+                | BinaryOperator::Minus
+                | BinaryOperator::Multiply
+                | BinaryOperator::Divide => {
+                    if left == CellType::Int && right == CellType::Int {
+                        CellType::Int
+                    } else {
+                        CellType::Float
+                    }
+                }
+                _ => CellType::Bool,
+            })
+        }
+        Expression::Subquery(query) => context.subquery_type(query),
+        Expression::Exists(_) => Ok(CellType::Bool),
+        Expression::InSubquery {
+            expression,
+            subquery,
+            ..
+        } => {
+            expression_type(expression, context)?;
+            context.subquery_type(subquery)?;
+            Ok(CellType::Bool)
+        }
+        Expression::Case {
+            branches,
+            else_result,
+            ..
+        } => {
+            let mut results: Vec<Expression> = branches.iter().map(|branch| branch.result.clone()).collect();
+            results.extend(else_result.iter().map(|else_result| (**else_result).clone()));
+            common_type(&results, "case", context)
+        }
+        Expression::Like { .. }
+        | Expression::InList { .. }
+        | Expression::Between { .. }
+        | Expression::IsNull { .. }
+        | Expression::IsDistinctFrom { .. } => {
+            for child in expression.children() {
+                expression_type(child, context)?;
+            }
+            Ok(CellType::Bool)
+        }
+        Expression::Cast { expression, data_type } => {
+            expression_type(expression, context)?;
+            CellType::parse_token(data_type)
+        }
+    }
+}
+
+// the type the values of `expressions` share, null literals fit any type.
+fn common_type(expressions: &[Expression], construct: &str, context: &RowContext) -> Result<CellType> {
+    let mut result = None;
+    for expression in expressions {
+        if let Expression::Literal(Token {
+            kind: TokenKind::Null,
+            ..
+        }) = expression
+        {
+            continue;
+        }
+        let expression_type = expression_type(expression, context)?;
+        result = Some(match result {
+            None => expression_type,
+            Some(result) => unify_types(&result, &expression_type)
+                .ok_or_else(|| MemoryError::IncompatibleTypes(construct.to_string(), result, expression_type))?,
+        });
+    }
+    Ok(result.unwrap_or_default())
+}
+
+// the type values of both types can be turned into, ints and floats become floats.
+fn unify_types(left: &CellType, right: &CellType) -> Option<CellType> {
+    match (left, right) {
+        (left, right) if left == right => Some(left.clone()),
+        (CellType::Int, CellType::Float) | (CellType::Float, CellType::Int) => Some(CellType::Float),
+        _ => None,
+    }
+}
+
+// the minimum and maximum number of arguments of the scalar functions, no maximum for None.
+fn scalar_function_arity(name: &str) -> Option<(usize, Option<usize>)> {
+    match name {
+        "coalesce" | "greatest" | "least" => Some((1, None)),
+        "nullif" => Some((2, Some(2))),
+        "upper" | "lower" => Some((1, Some(1))),
+        _ => None,
+    }
+}
+
+fn check_scalar_function(call: &FunctionCall) -> Result<()> {
+    let name = &call.name.value;
+    let (min_args, max_args) =
+        scalar_function_arity(name).ok_or_else(|| MemoryError::FunctionNotFound(name.clone()))?;
+    if call.is_star
+        || call.is_distinct
+        || call.args.len() < min_args
+        || max_args.is_some_and(|max_args| call.args.len() > max_args)
+    {
+        return Err(MemoryError::InvalidArguments(name.clone()));
+    }
+    Ok(())
+}
+
+// evaluates a limit or offset, null means there is no limit. it can only be a constant or a parameter.
+fn evaluate_row_count(
+    expression: Option<&Expression>,
+    clause: &str,
+    parameters: &[CellValue],
+) -> Result<Option<usize>> {
+    let expression = match expression {
+        Some(expression) => expression,
+        None => return Ok(None),
+    };
+    let context = RowContext {
+        parameters,
+        ..Default::default()
+    };
+    match context.evaluate(expression)? {
+        CellValue::Null => Ok(None),
+        CellValue::Int(count) if count >= 0 => Ok(Some(count as usize)),
+        value => Err(MemoryError::InvalidRowCount(clause.to_string(), value.to_string())),
+    }
+}
+
+// where the value an order by item sorts on comes from.
+enum SortKey {
+    // the index of a select item, for ordinals and output names
+    Output(usize),
+    // an expression over the input row
+    Expression(Expression),
+}
+
+impl SortKey {
+    fn resolve(order_by: &[OrderByItem], items: &[SelectItem]) -> Result<Vec<Self>> {
+        let mut sort_keys = vec![];
+        for order_by_item in order_by {
+            sort_keys.push(match &order_by_item.expression {
+                Expression::Literal(token) if token.kind == TokenKind::Numeric => {
+                    let position = token.value.parse::<usize>().unwrap_or(0);
+                    if position == 0 || position > items.len() {
+                        return Err(MemoryError::InvalidOrderByPosition(token.value.clone()));
+                    }
+                    SortKey::Output(position - 1)
+                }
+                Expression::Column { table: None, name } => {
+                    match items.iter().position(|item| item.output_name() == name.value) {
+                        Some(index) => SortKey::Output(index),
+                        None => SortKey::Expression(order_by_item.expression.clone()),
+                    }
+                }
+                expression => SortKey::Expression(expression.clone()),
+            });
+        }
+        Ok(sort_keys)
+    }
+}
+
+// a distinct can only sort on what it keeps: select distinct sorts on select items, and
+// distinct on has to sort on its expressions first, in any order, so the row it keeps
+// for a set of values is the first of them in the order by.
+fn check_distinct_order(distinct: &Distinct, sort_keys: &[SortKey], items: &[SelectItem]) -> Result<()> {
+    let sort_expressions = sort_keys.iter().map(|sort_key| match sort_key {
+        SortKey::Output(index) => &items[*index].expression,
+        SortKey::Expression(expression) => expression,
+    });
+    match distinct {
+        Distinct::Row => {
+            for expression in sort_expressions {
+                if !items.iter().any(|item| item.expression == *expression) {
+                    return Err(MemoryError::OrderByNotInDistinct);
+                }
+            }
+        }
+        Distinct::On(distinct_on) => {
+            let mut unsorted: Vec<&Expression> = distinct_on.iter().collect();
+            for expression in sort_expressions {
+                if unsorted.is_empty() {
+                    break;
+                }
+                if !distinct_on.contains(expression) {
+                    return Err(MemoryError::DistinctOnNotInOrderBy);
+                }
+                unsorted.retain(|distinct_expression| *distinct_expression != expression);
+            }
+        }
+    }
+    Ok(())
+}
+
+// what the operators of a projection are recorded as by `explain analyze`.
+#[derive(Clone, Copy)]
+struct Profiled<'q> {
+    query: &'q SelectStatement,
+    // the stage of the projection itself
+    stage: Stage,
+    // when the select started running, the times of its operators include the ones of their inputs
+    start: Instant,
+}
+
+/// The select list, distinct, order by, limit and offset of a select, run over
+/// the rows coming out of the earlier stages.
+struct Projection<'a> {
+    items: Vec<Expression>,
+    names: Vec<String>,
+    distinct: Option<Distinct>,
+    sort_keys: Vec<SortKey>,
+    order_by: &'a [OrderByItem],
+    limit: Option<usize>,
+    offset: usize,
+}
+
+impl<'a> Projection<'a> {
+    fn new(
+        items: &[SelectItem],
+        distinct: Option<&Distinct>,
+        order_by: &'a [OrderByItem],
+        limit: Option<&Expression>,
+        offset: Option<&Expression>,
+        parameters: &[CellValue],
+    ) -> Result<Self> {
+        let sort_keys = SortKey::resolve(order_by, items)?;
+        if let Some(distinct) = distinct {
+            check_distinct_order(distinct, &sort_keys, items)?;
+        }
+        Ok(Projection {
+            items: items.iter().map(|item| item.expression.clone()).collect(),
+            names: items.iter().map(SelectItem::output_name).collect(),
+            distinct: distinct.cloned(),
+            sort_keys,
+            order_by,
+            limit: evaluate_row_count(limit, "limit", parameters)?,
+            offset: evaluate_row_count(offset, "offset", parameters)?.unwrap_or(0),
+        })
+    }
+
+    // replaces every expression the projection evaluates.
+    fn rewrite(&mut self, mut rewrite: impl FnMut(&Expression) -> Result<Expression>) -> Result<()> {
+        for item in &mut self.items {
+            *item = rewrite(item)?;
+        }
+        if let Some(Distinct::On(expressions)) = &mut self.distinct {
+            for expression in expressions {
+                *expression = rewrite(expression)?;
+            }
+        }
+        for sort_key in &mut self.sort_keys {
+            if let SortKey::Expression(expression) = sort_key {
+                *expression = rewrite(expression)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn has_windows(&self) -> bool {
+        let distinct_on = match &self.distinct {
+            Some(Distinct::On(expressions)) => expressions.as_slice(),
+            _ => &[],
+        };
+        let sort_keys = self.sort_keys.iter().filter_map(|sort_key| match sort_key {
+            SortKey::Expression(expression) => Some(expression),
+            SortKey::Output(_) => None,
+        });
+        self.items
+            .iter()
+            .chain(distinct_on)
+            .chain(sort_keys)
+            .any(|expression| find_window(expression).is_some())
+    }
+
+    // like `run`, with the window functions computed over the rows that pass the filter first.
+    // `select_statement` and `start` are what the operators are profiled as.
+    fn run_with_windows<R: AsRef<[CellValue]>>(
+        mut self,
+        select_statement: &SelectStatement,
+        input: &RowContext,
+        input_rows: impl Iterator<Item = Result<R>>,
+        filter: Option<(&Expression, &str)>,
+        start: Instant,
+    ) -> Result<QueryResult> {
+        let profiled = Profiled {
+            query: select_statement,
+            stage: Stage::Project,
+            start,
+        };
+        if !self.has_windows() {
+            return self.run(input, input_rows, filter, Some(profiled));
+        }
+
+        let plan = WindowPlan::apply(&mut self, input)?;
+        let mut rows = Vec::<R>::new();
+        for row in input_rows {
+            let row = row?;
+            if filter_matches(&input.with_row(row.as_ref()), filter)? {
+                rows.push(row);
+            }
+        }
+        let rows: Vec<&[CellValue]> = rows.iter().map(AsRef::as_ref).collect();
+        let window_rows = plan.window_rows(input, &rows)?;
+        input.record(select_statement, Stage::Window, window_rows.len(), start);
+        let window_input = RowContext {
+            columns: &plan.columns,
+            ..*input
+        };
+        self.run(&window_input, window_rows.iter().map(Vec::as_slice).map(Ok), None, Some(profiled))
+    }
+
+    fn result_columns(&self, input: &RowContext) -> Result<Vec<Column>> {
+        let mut result_columns = Vec::<Column>::new();
+        for (item, name) in self.items.iter().zip(&self.names) {
+            result_columns.push(Column {
+                name: name.clone(),
+                column_type: expression_type(item, input)?,
+                ..Default::default()
+            });
+        }
+        Ok(result_columns)
+    }
+
+    // `input` has the columns of the input rows. with `profiled` the projection and the sort,
+    // distinct and limit after it are recorded as the operators of a select.
+    fn run<R: AsRef<[CellValue]>>(
+        &self,
+        input: &RowContext,
+        mut input_rows: impl Iterator<Item = Result<R>>,
+        filter: Option<(&Expression, &str)>,
+        profiled: Option<Profiled>,
+    ) -> Result<QueryResult> {
+        let result_columns = self.result_columns(input)?;
+        // `None` is the stage of the projection itself.
+        let record = |stage: Option<Stage>, rows: usize| {
+            if let Some(profiled) = profiled {
+                input.record(profiled.query, stage.unwrap_or(profiled.stage), rows, profiled.start);
+            }
+        };
+
+        // without an order by the rows come out in input order, so the scan can
+        // skip the offset and stop as soon as the limit is reached, without reading
+        // another row. duplicates are then dropped as they come, otherwise only once
+        // the rows are sorted.
+        let is_sorted = !self.order_by.is_empty();
+        let is_distinct = self.distinct.is_some();
+        let is_limited = self.limit.is_some() || self.offset > 0;
+        let mut skipped_rows = 0;
+        let mut projected_rows = 0;
+        let mut seen = HashSet::<Vec<CellValue>>::new();
+
+        let mut rows = Vec::<ResultRow>::new();
+        while is_sorted || Some(rows.len()) != self.limit {
+            let row = match input_rows.next() {
+                Some(row) => row?,
+                None => break,
+            };
+
+            let context = input.with_row(row.as_ref());
+            if !filter_matches(&context, filter)? {
+                continue;
+            }
+            projected_rows += 1;
+            if !is_sorted && !is_distinct && skipped_rows < self.offset {
+                skipped_rows += 1;
+                continue;
+            }
+
+            let mut values = Vec::<CellValue>::new();
+            for item in &self.items {
+                values.push(context.evaluate(item)?);
+            }
+            let distinct_key = match &self.distinct {
+                None => vec![],
+                Some(Distinct::Row) => values.clone(),
+                Some(Distinct::On(expressions)) => {
+                    let mut key = Vec::<CellValue>::new();
+                    for expression in expressions {
+                        key.push(context.evaluate(expression)?);
+                    }
+                    key
+                }
+            };
+            if !is_sorted && is_distinct {
+                if !seen.insert(distinct_key.clone()) {
+                    continue;
+                }
+                if skipped_rows < self.offset {
+                    skipped_rows += 1;
+                    continue;
+                }
+            }
+
+            let mut sort_values = Vec::<CellValue>::new();
+            for sort_key in &self.sort_keys {
+                sort_values.push(match sort_key {
+                    SortKey::Output(index) => values[*index].clone(),
+                    SortKey::Expression(expression) => context.evaluate(expression)?,
+                });
+            }
+            rows.push(ResultRow {
+                values,
+                sort_values,
+                distinct_key,
+            });
+        }
+
+        record(None, projected_rows);
+        if is_sorted {
+            sort_rows(&mut rows, self.order_by, |row| &row.sort_values)?;
+            record(Some(Stage::Sort), rows.len());
+            // the sort is stable, so `distinct on` keeps the first row in the order by order.
+            if is_distinct {
+                rows.retain(|row| seen.insert(row.distinct_key.clone()));
+                record(Some(Stage::Unique), rows.len());
+            }
+            rows.drain(..self.offset.min(rows.len()));
+            if let Some(limit) = self.limit {
+                rows.truncate(limit);
+            }
+        } else if is_distinct {
+            record(Some(Stage::Unique), seen.len());
+        }
+        if is_limited {
+            record(Some(Stage::Limit), rows.len());
+        }
+
+        let rows = rows
+            .into_iter()
+            .map(|row| {
+                row.values
+                    .into_iter()
+                    .zip(&result_columns)
+                    .map(|(value, column)| Cell {
+                        value,
+                        column_name: column.name.clone(),
+                        cell_type: column.column_type.clone(),
+                    })
+                    .collect()
+            })
+            .collect();
+
+        Ok(QueryResult {
+            rows,
+            columns: result_columns,
+        })
+    }
+}
+
+const AGGREGATE_FUNCTIONS: [&str; 5] = ["count", "sum", "avg", "min", "max"];
+
+fn is_aggregate(call: &FunctionCall) -> bool {
+    AGGREGATE_FUNCTIONS.contains(&call.name.value.as_str())
+}
+
+// the first aggregate call in `expression`, if any.
+fn find_aggregate(expression: &Expression) -> Option<&FunctionCall> {
+    match expression {
+        Expression::Function(call) if is_aggregate(call) => Some(call),
+        _ => expression.children().into_iter().find_map(find_aggregate),
+    }
+}
+
+impl Expression {
+    // the expressions inside this one that are evaluated on the same row, subqueries have rows of their own.
+    fn children(&self) -> Vec<&Expression> {
+        match self {
+            Expression::Literal(_) | Expression::Parameter(_) | Expression::Column { .. } => vec![],
+            Expression::Subquery(_) | Expression::Exists(_) => vec![],
+            Expression::Function(call) => call.args.iter().collect(),
+            // the frame offsets are constants, they are not evaluated on the row.
+            Expression::Window { function, window } => {
+                let mut children: Vec<&Expression> = function.args.iter().collect();
+                children.extend(&window.partition_by);
+                children.extend(window.order_by.iter().map(|item| &item.expression));
+                children
+            }
+            Expression::Unary { operand, .. } => vec![operand],
+            Expression::Binary { left, right, .. } => vec![left, right],
+            Expression::InSubquery { expression, .. } => vec![expression],
+            Expression::InList { expression, list, .. } => {
+                let mut children: Vec<&Expression> = vec![expression];
+                children.extend(list);
+                children
+            }
+            Expression::Between {
+                expression,
+                low,
+                high,
+                ..
+            } => vec![expression, low, high],
+            Expression::IsNull { expression, .. } => vec![expression],
+            Expression::IsDistinctFrom { left, right, .. } => vec![left, right],
+            Expression::Like {
+                expression,
+                pattern,
+                escape,
+                ..
+            } => {
+                let mut children: Vec<&Expression> = vec![expression, pattern];
+                children.extend(escape.as_deref());
+                children
+            }
+            Expression::Cast { expression, .. } => vec![expression],
+            Expression::Case {
+                operand,
+                branches,
+                else_result,
+            } => {
+                let mut children: Vec<&Expression> = operand.iter().map(|operand| &**operand).collect();
+                for branch in branches {
+                    children.push(&branch.condition);
+                    children.push(&branch.result);
+                }
+                children.extend(else_result.iter().map(|else_result| &**else_result));
+                children
+            }
+        }
+    }
+
+    // a copy of this expression with `rewrite` applied to each of the children.
+    fn map_children(&self, mut rewrite: impl FnMut(&Expression) -> Result<Expression>) -> Result<Expression> {
+        let mut rewrite_box = |expression: &Expression| rewrite(expression).map(Box::new);
+        Ok(match self {
+            Expression::Literal(_) | Expression::Parameter(_) | Expression::Column { .. } => self.clone(),
+            Expression::Subquery(_) | Expression::Exists(_) => self.clone(),
+            Expression::Function(call) => {
+                let mut args = vec![];
+                for arg in &call.args {
+                    args.push(*rewrite_box(arg)?);
+                }
+                Expression::Function(FunctionCall {
+                    args,
+                    ..call.clone()
+                })
+            }
+            Expression::Window { function, window } => {
+                let mut args = vec![];
+                for arg in &function.args {
+                    args.push(*rewrite_box(arg)?);
+                }
+                let mut partition_by = vec![];
+                for expression in &window.partition_by {
+                    partition_by.push(*rewrite_box(expression)?);
+                }
+                let mut order_by = vec![];
+                for item in &window.order_by {
+                    order_by.push(OrderByItem {
+                        expression: *rewrite_box(&item.expression)?,
+                        ..item.clone()
+                    });
+                }
+                Expression::Window {
+                    function: FunctionCall {
+                        args,
+                        ..function.clone()
+                    },
+                    window: WindowSpec {
+                        partition_by,
+                        order_by,
+                        frame: window.frame.clone(),
+                    },
+                }
+            }
+            Expression::Unary { operator, operand } => Expression::Unary {
+                operator: *operator,
+                operand: rewrite_box(operand)?,
+            },
+            Expression::Binary {
+                left,
+                operator,
+                right,
+            } => Expression::Binary {
+                left: rewrite_box(left)?,
+                operator: *operator,
+                right: rewrite_box(right)?,
+            },
+            Expression::InSubquery {
+                expression,
+                subquery,
+                is_negated,
+            } => Expression::InSubquery {
+                expression: rewrite_box(expression)?,
+                subquery: subquery.clone(),
+                is_negated: *is_negated,
+            },
+            Expression::InList {
+                expression,
+                list,
+                is_negated,
+            } => {
+                let mut new_list = vec![];
+                for item in list {
+                    new_list.push(*rewrite_box(item)?);
+                }
+                Expression::InList {
+                    expression: rewrite_box(expression)?,
+                    list: new_list,
+                    is_negated: *is_negated,
+                }
+            }
+            Expression::Between {
+                expression,
+                low,
+                high,
+                is_negated,
+            } => Expression::Between {
+                expression: rewrite_box(expression)?,
+                low: rewrite_box(low)?,
+                high: rewrite_box(high)?,
+                is_negated: *is_negated,
+            },
+            Expression::IsNull {
+                expression,
+                is_negated,
+            } => Expression::IsNull {
+                expression: rewrite_box(expression)?,
+                is_negated: *is_negated,
+            },
+            Expression::IsDistinctFrom {
+                left,
+                right,
+                is_negated,
+            } => Expression::IsDistinctFrom {
+                left: rewrite_box(left)?,
+                right: rewrite_box(right)?,
+                is_negated: *is_negated,
+            },
+            Expression::Case {
+                operand,
+                branches,
+                else_result,
+            } => {
+                let mut new_branches = vec![];
+                for branch in branches {
+                    new_branches.push(WhenClause {
+                        condition: *rewrite_box(&branch.condition)?,
+                        result: *rewrite_box(&branch.result)?,
+                    });
+                }
+                Expression::Case {
+                    operand: operand.as_deref().map(&mut rewrite_box).transpose()?,
+                    branches: new_branches,
+                    else_result: else_result.as_deref().map(&mut rewrite_box).transpose()?,
+                }
+            }
+            Expression::Like {
+                expression,
+                operator,
+                pattern,
+                escape,
+                is_negated,
+            } => Expression::Like {
+                expression: rewrite_box(expression)?,
+                operator: *operator,
+                pattern: rewrite_box(pattern)?,
+                escape: escape.as_deref().map(&mut rewrite_box).transpose()?,
+                is_negated: *is_negated,
+            },
+            Expression::Cast { expression, data_type } => Expression::Cast {
+                expression: rewrite_box(expression)?,
+                data_type: data_type.clone(),
+            },
+        })
+    }
+}
+
+/// Groups rows by the group by expressions and computes the aggregates of each group.
+///
+/// A group row holds the group by values followed by the aggregate results, and the
+/// expressions evaluated after grouping are rewritten to read those instead of table columns.
+struct AggregatePlan {
+    group_by: Vec<Expression>,
+    aggregates: Vec<FunctionCall>,
+    // the columns of a group row
+    columns: Vec<Column>,
+}
+
+impl AggregatePlan {
+    fn is_needed(select_statement: &SelectStatement) -> bool {
+        !select_statement.group_by.is_empty()
+            || select_statement.having.is_some()
+            || select_statement
+                .items
+                .iter()
+                .any(|item| find_aggregate(&item.expression).is_some())
+            || select_statement
+                .order_by
+                .iter()
+                .any(|item| find_aggregate(&item.expression).is_some())
+    }
+
+    // plans the grouping of a select and makes its projection and having read the group rows.
+    fn apply(
+        select_statement: &SelectStatement,
+        projection: &mut Projection,
+        input: &RowContext,
+    ) -> Result<(Self, Option<Expression>)> {
+        let mut plan = AggregatePlan::new(select_statement, input)?;
+        projection.rewrite(|expression| plan.rewrite(expression, input))?;
+        let having = match &select_statement.having {
+            Some(having) => Some(plan.rewrite(having, input)?),
+            None => None,
+        };
+        Ok((plan, having))
+    }
+
+    fn new(select_statement: &SelectStatement, input: &RowContext) -> Result<Self> {
+        let mut columns = vec![];
+        for (index, expression) in select_statement.group_by.iter().enumerate() {
+            if let Some(aggregate) = find_aggregate(expression) {
+                return Err(MemoryError::AggregateNotAllowed(aggregate.name.value.clone()));
+            }
+            columns.push(Column {
+                name: format!("#group{}", index),
+                column_type: expression_type(expression, input)?,
+                ..Default::default()
+            });
+        }
+        Ok(AggregatePlan {
+            group_by: select_statement.group_by.clone(),
+            aggregates: vec![],
+            columns,
+        })
+    }
+
+    // makes `expression` read the group row, every column it uses has to be grouped or aggregated.
+    fn rewrite(&mut self, expression: &Expression, input: &RowContext) -> Result<Expression> {
+        if let Some(index) = self.group_by.iter().position(|group_by| group_by == expression) {
+            return Ok(synthetic_column(&self.columns[index].name));
+        }
+
+        match expression {
+            Expression::Column { table, name } => match resolve_column(input.columns, table.as_ref(), name) {
+                // columns of an enclosing query are the same for the whole group.
+                Err(MemoryError::ColumnNotFound(_)) => Ok(expression.clone()),
+                _ => Err(MemoryError::ColumnNotGrouped(name.value.clone())),
+            },
+            Expression::Function(call) if is_aggregate(call) => {
+                let index = match self.aggregates.iter().position(|aggregate| aggregate == call) {
+                    Some(index) => index,
+                    None => {
+                        self.columns.push(Column {
+                            name: format!("#aggregate{}", self.aggregates.len()),
+                            column_type: aggregate_type(call, input)?,
+                            ..Default::default()
+                        });
+                        self.aggregates.push(call.clone());
+                        self.aggregates.len() - 1
+                    }
+                };
+                Ok(synthetic_column(&self.columns[self.group_by.len() + index].name))
+            }
+            _ => expression.map_children(|child| self.rewrite(child, input)),
+        }
+    }
+
+    // hash-based grouping, groups come out in the order they are first seen.
+    fn group_rows<'r>(
+        &self,
+        input: &RowContext,
+        rows: impl Iterator<Item = &'r [CellValue]>,
+        where_clause: Option<&Expression>,
+    ) -> Result<Vec<Vec<CellValue>>> {
+        let mut group_indexes = HashMap::<Vec<CellValue>, usize>::new();
+        let mut groups = Vec::<(Vec<CellValue>, Vec<Accumulator>)>::new();
+
+        // without a group by there is exactly one group, even for an empty table.
+        if self.group_by.is_empty() {
+            group_indexes.insert(vec![], 0);
+            groups.push((vec![], self.new_accumulators()));
+        }
+
+        for row in rows {
+            let context = input.with_row(row);
+            if !context.matches(where_clause, "where")? {
+                continue;
+            }
+
+            let mut key = Vec::<CellValue>::new();
+            for expression in &self.group_by {
+                key.push(context.evaluate(expression)?);
+            }
+            let group_index = match group_indexes.get(&key) {
+                Some(group_index) => *group_index,
+                None => {
+                    group_indexes.insert(key.clone(), groups.len());
+                    groups.push((key, self.new_accumulators()));
+                    groups.len() - 1
+                }
+            };
+
+            let accumulators = &mut groups[group_index].1;
+            for (aggregate, accumulator) in self.aggregates.iter().zip(accumulators) {
+                if aggregate.is_star {
+                    accumulator.count += 1;
+                } else {
+                    accumulator.add(&aggregate.name.value, context.evaluate(&aggregate.args[0])?)?;
+                }
+            }
+        }
+
+        Ok(groups
+            .into_iter()
+            .map(|(mut key, accumulators)| {
+                for (aggregate, accumulator) in self.aggregates.iter().zip(accumulators) {
+                    key.push(accumulator.finish(&aggregate.name.value));
+                }
+                key
+            })
+            .collect())
+    }
+
+    fn new_accumulators(&self) -> Vec<Accumulator> {
+        self.aggregates.iter().map(Accumulator::new).collect()
+    }
+}
+
+// a reference to a column the executor made up, like the ones of group rows.
+fn synthetic_column(name: &str) -> Expression {
+    Expression::Column {
+        table: None,
+        name: Token {
+            value: name.to_string(),
+            kind: TokenKind::Identifier,
+        },
+    }
+}
+
+// checks the arguments of an aggregate and returns the type of its result.
+fn aggregate_type(call: &FunctionCall, input: &RowContext) -> Result<CellType> {
+    let name = call.name.value.as_str();
+    if call.is_star {
+        return if name == "count" {
+            Ok(CellType::Int)
+        } else {
+            Err(MemoryError::InvalidArguments(name.to_string()))
+        };
+    }
+    if call.args.len() != 1 {
+        return Err(MemoryError::InvalidArguments(name.to_string()));
+    }
+    if let Some(aggregate) = find_aggregate(&call.args[0]) {
+        return Err(MemoryError::AggregateNotAllowed(aggregate.name.value.clone()));
+    }
+
+    let arg_type = expression_type(&call.args[0], input)?;
+    Ok(match name {
+        "count" => CellType::Int,
+        "avg" => CellType::Float,
+        "sum" if arg_type == CellType::Int => CellType::Int,
+        "sum" => CellType::Float,
+        _ => arg_type,
+    })
+}
+
+// the running state of one aggregate in one group.
+#[derive(Clone)]
+struct Accumulator {
+    // the values added so far, only kept for distinct aggregates
+    seen: Option<HashSet<CellValue>>,
+    // the number of values added
+    count: i64,
+    // the running sum, min or max
+    value: CellValue,
+}
+
+impl Accumulator {
+    fn new(aggregate: &FunctionCall) -> Self {
+        Accumulator {
+            seen: if aggregate.is_distinct {
+                Some(HashSet::new())
+            } else {
+                None
+            },
+            count: 0,
+            value: CellValue::Null,
+        }
+    }
+
+    // aggregates skip nulls, and distinct aggregates skip values they have already seen.
+    fn add(&mut self, function_name: &str, value: CellValue) -> Result<()> {
+        if let CellValue::Null = value {
+            return Ok(());
+        }
+        if let Some(seen) = &mut self.seen {
+            if !seen.insert(value.clone()) {
+                return Ok(());
+            }
+        }
+        self.count += 1;
+
+        let is_first = matches!(self.value, CellValue::Null);
+        match function_name {
+            "sum" if is_first => self.value = value,
+            "sum" => self.value = self.value.arithmetic(BinaryOperator::Plus, &value)?,
+            // the average of ints fits even when their sum doesn't, and it is a float anyway.
+            "avg" if is_first => self.value = CellValue::Float(value.as_float()),
+            "avg" => self.value = CellValue::Float(self.value.as_float() + value.as_float()),
+            "min" if is_first || value.compare(&self.value)? == Some(Ordering::Less) => self.value = value,
+            "max" if is_first || value.compare(&self.value)? == Some(Ordering::Greater) => self.value = value,
+            _ => {}
+        }
+        Ok(())
+    }
+
+    fn finish(self, function_name: &str) -> CellValue {
+        match function_name {
+            "count" => CellValue::Int(self.count),
+            "avg" if self.count == 0 => CellValue::Null,
+            "avg" => CellValue::Float(self.value.as_float() / self.count as f64),
+            _ => self.value,
+        }
+    }
+}
+
+const RANKING_FUNCTIONS: [&str; 3] = ["row_number", "rank", "dense_rank"];
+
+// whether the row passes `filter`, the condition and the name of its clause.
+fn filter_matches(context: &RowContext, filter: Option<(&Expression, &str)>) -> Result<bool> {
+    match filter {
+        Some((condition, clause)) => context.matches(Some(condition), clause),
+        None => Ok(true),
+    }
+}
+
+// the first window function call in `expression`, if any.
+fn find_window(expression: &Expression) -> Option<&FunctionCall> {
+    match expression {
+        Expression::Window { function, .. } => Some(function),
+        _ => expression.children().into_iter().find_map(find_window),
+    }
+}
+
+// checks the arguments and window of a window function and returns the type of its result.
+fn window_type(function: &FunctionCall, window: &WindowSpec, input: &RowContext) -> Result<CellType> {
+    for expression in &window.partition_by {
+        expression_type(expression, input)?;
+    }
+    for item in &window.order_by {
+        expression_type(&item.expression, input)?;
+    }
+    if let Some(nested) = function.args.iter().find_map(find_window) {
+        return Err(MemoryError::WindowNotAllowed(nested.name.value.clone()));
+    }
+
+    let name = function.name.value.as_str();
+    if is_aggregate(function) {
+        // a distinct aggregate would need the frame's values again for every row.
+        if function.is_distinct {
+            return Err(MemoryError::InvalidArguments(name.to_string()));
+        }
+        return aggregate_type(function, input);
+    }
+    let (min_args, max_args) = match name {
+        name if RANKING_FUNCTIONS.contains(&name) => (0, 0),
+        "lag" | "lead" => (1, 3),
+        "first_value" => (1, 1),
+        _ => return Err(MemoryError::NotAWindowFunction(name.to_string())),
+    };
+    let args = &function.args;
+    if function.is_star || function.is_distinct || args.len() < min_args || args.len() > max_args {
+        return Err(MemoryError::InvalidArguments(name.to_string()));
+    }
+
+    match name {
+        "lag" | "lead" => {
+            if let Some(offset) = args.get(1) {
+                if expression_type(offset, input)? != CellType::Int {
+                    return Err(MemoryError::InvalidArguments(name.to_string()));
+                }
+            }
+            // the default is returned when the offset row is outside the partition.
+            let mut values = vec![args[0].clone()];
+            values.extend(args.get(2).cloned());
+            common_type(&values, name, input)
+        }
+        "first_value" => expression_type(&args[0], input),
+        _ => Ok(CellType::Int),
+    }
+}
+
+/// Computes the window functions of a select, after the where clause or after grouping and having.
+///
+/// Each function's value is appended to the rows it is computed over, and the expressions of the
+/// projection are rewritten to read those values instead.
+struct WindowPlan {
+    windows: Vec<(FunctionCall, WindowSpec)>,
+    // the input columns followed by one column for each window function
+    columns: Vec<Column>,
+    input_width: usize,
+}
+
+impl WindowPlan {
+    // plans the window functions of a projection and makes it read their columns.
+    fn apply(projection: &mut Projection, input: &RowContext) -> Result<Self> {
+        let mut plan = WindowPlan {
+            windows: vec![],
+            columns: input.columns.to_vec(),
+            input_width: input.columns.len(),
+        };
+        projection.rewrite(|expression| plan.rewrite(expression, input))?;
+        Ok(plan)
+    }
+
+    fn rewrite(&mut self, expression: &Expression, input: &RowContext) -> Result<Expression> {
+        match expression {
+            Expression::Window { function, window } => {
+                let position = self.windows.iter().position(|(f, w)| f == function && w == window);
+                let index = match position {
+                    Some(index) => index,
+                    None => {
+                        self.columns.push(Column {
+                            name: format!("#window{}", self.windows.len()),
+                            column_type: window_type(function, window, input)?,
+                            ..Default::default()
+                        });
+                        self.windows.push((function.clone(), window.clone()));
+                        self.windows.len() - 1
+                    }
+                };
+                Ok(synthetic_column(&self.columns[self.input_width + index].name))
+            }
+            _ => expression.map_children(|child| self.rewrite(child, input)),
+        }
+    }
+
+    // the rows with the value of every window function appended.
+    fn window_rows(&self, input: &RowContext, rows: &[&[CellValue]]) -> Result<Vec<Vec<CellValue>>> {
+        let mut window_rows: Vec<Vec<CellValue>> = rows.iter().map(|row| row.to_vec()).collect();
+        for (function, window) in &self.windows {
+            let values = window_values(function, window, input, rows)?;
+            for (row, value) in window_rows.iter_mut().zip(values) {
+                row.push(value);
+            }
+        }
+        Ok(window_rows)
+    }
+}
+
+// a row of a window partition, with its index in the window's input and the values it is sorted on.
+struct PartitionRow {
+    index: usize,
+    sort_values: Vec<CellValue>,
+}
+
+// the value of a window function for each row, in the order of `rows`.
+fn window_values(
+    function: &FunctionCall,
+    window: &WindowSpec,
+    input: &RowContext,
+    rows: &[&[CellValue]],
+) -> Result<Vec<CellValue>> {
+    // hash-based partitioning, then each partition is sorted on its own.
+    let mut partition_indexes = HashMap::<Vec<CellValue>, usize>::new();
+    let mut partitions = Vec::<Vec<PartitionRow>>::new();
+    for (index, row) in rows.iter().enumerate() {
+        let context = input.with_row(row);
+        let mut key = Vec::<CellValue>::new();
+        for expression in &window.partition_by {
+            key.push(context.evaluate(expression)?);
+        }
+        let mut sort_values = Vec::<CellValue>::new();
+        for item in &window.order_by {
+            sort_values.push(context.evaluate(&item.expression)?);
+        }
+        let partition_index = match partition_indexes.get(&key) {
+            Some(partition_index) => *partition_index,
+            None => {
+                partition_indexes.insert(key, partitions.len());
+                partitions.push(vec![]);
+                partitions.len() - 1
+            }
+        };
+        partitions[partition_index].push(PartitionRow { index, sort_values });
+    }
+
+    let mut values = vec![CellValue::Null; rows.len()];
+    for mut partition in partitions {
+        sort_rows(&mut partition, &window.order_by, |row| &row.sort_values)?;
+        let partition = Partition::new(partition, window, input, rows)?;
+        for (position, value) in partition.values(function, window)?.into_iter().enumerate() {
+            values[partition.rows[position].index] = value;
+        }
+    }
+    Ok(values)
+}
+
+// a sorted window partition.
+struct Partition<'a> {
+    rows: Vec<PartitionRow>,
+    input: &'a RowContext<'a>,
+    input_rows: &'a [&'a [CellValue]],
+    // for each row, the range of the rows that sort like it, its peers
+    peers: Vec<(usize, usize)>,
+}
+
+impl<'a> Partition<'a> {
+    fn new(
+        rows: Vec<PartitionRow>,
+        window: &WindowSpec,
+        input: &'a RowContext<'a>,
+        input_rows: &'a [&'a [CellValue]],
+    ) -> Result<Self> {
+        let mut peers = Vec::with_capacity(rows.len());
+        let mut start = 0;
+        for end in 1..=rows.len() {
+            let is_peer = end < rows.len() && is_same_sort(&rows[end - 1], &rows[end], &window.order_by)?;
+            if !is_peer {
+                peers.extend((start..end).map(|_| (start, end)));
+                start = end;
+            }
+        }
+        Ok(Partition {
+            rows,
+            input,
+            input_rows,
+            peers,
+        })
+    }
+
+    fn evaluate_at(&self, position: usize, expression: &Expression) -> Result<CellValue> {
+        self.input
+            .with_row(self.input_rows[self.rows[position].index])
+            .evaluate(expression)
+    }
+
+    fn values(&self, function: &FunctionCall, window: &WindowSpec) -> Result<Vec<CellValue>> {
+        let count = self.rows.len();
+        let mut values = Vec::with_capacity(count);
+        match function.name.value.as_str() {
+            "row_number" => values.extend((1..=count).map(|number| CellValue::Int(number as i64))),
+            "rank" => values.extend(self.peers.iter().map(|(start, _)| CellValue::Int(*start as i64 + 1))),
+            "dense_rank" => {
+                let mut rank = 0;
+                for (position, (start, _)) in self.peers.iter().enumerate() {
+                    if *start == position {
+                        rank += 1;
+                    }
+                    values.push(CellValue::Int(rank));
+                }
+            }
+            name @ ("lag" | "lead") => {
+                for position in 0..count {
+                    values.push(self.offset_value(name, &function.args, position)?);
+                }
+            }
+            "first_value" => {
+                for position in 0..count {
+                    let (start, end) = self.frame(window, position)?;
+                    values.push(if start < end {
+                        self.evaluate_at(start, &function.args[0])?
+                    } else {
+                        CellValue::Null
+                    });
+                }
+            }
+            _ => {
+                // the accumulator holds the rows from `start` to `end`, and is only started over when
+                // the frame start moves, frames that start with the partition are accumulated once.
+                let name = function.name.value.as_str();
+                let mut accumulator = Accumulator::new(function);
+                let (mut start, mut end) = (0, 0);
+                for position in 0..count {
+                    let (frame_start, frame_end) = self.frame(window, position)?;
+                    if frame_start != start || frame_end < end {
+                        accumulator = Accumulator::new(function);
+                        start = frame_start;
+                        end = frame_start;
+                    }
+                    while end < frame_end {
+                        if function.is_star {
+                            accumulator.count += 1;
+                        } else {
+                            accumulator.add(name, self.evaluate_at(end, &function.args[0])?)?;
+                        }
+                        end += 1;
+                    }
+                    values.push(accumulator.clone().finish(name));
+                }
+            }
+        }
+        Ok(values)
+    }
+
+    // `lag(value, offset, default)` or `lead(...)`, the default is used past the ends of the partition.
+    fn offset_value(&self, name: &str, args: &[Expression], position: usize) -> Result<CellValue> {
+        let offset = match args.get(1) {
+            Some(offset) => self.evaluate_at(position, offset)?,
+            None => CellValue::Int(1),
+        };
+        let offset = match offset {
+            CellValue::Int(offset) => offset,
+            _ => return Ok(CellValue::Null),
+        };
+        let target = if name == "lag" {
+            (position as i64).checked_sub(offset)
+        } else {
+            (position as i64).checked_add(offset)
+        };
+        match target {
+            Some(target) if target >= 0 && (target as usize) < self.rows.len() => {
+                self.evaluate_at(target as usize, &args[0])
+            }
+            _ => match args.get(2) {
+                Some(default) => self.evaluate_at(position, default),
+                None => Ok(CellValue::Null),
+            },
+        }
+    }
+
+    // the positions of the rows in the frame of the row at `position`, the end is exclusive.
+    fn frame(&self, window: &WindowSpec, position: usize) -> Result<(usize, usize)> {
+        let frame = match &window.frame {
+            Some(frame) => frame,
+            // up to the last peer of the row, which is the whole partition without an order by.
+            None => return Ok((0, self.peers[position].1)),
+        };
+        let count = self.rows.len();
+        let start = match &frame.start {
+            FrameBound::UnboundedPreceding => 0,
+            FrameBound::Preceding(rows) => position.saturating_sub(self.frame_rows(rows)?),
+            FrameBound::CurrentRow => position,
+            FrameBound::Following(rows) => position.saturating_add(self.frame_rows(rows)?).min(count),
+            FrameBound::UnboundedFollowing => count,
+        };
+        let end = match &frame.end {
+            FrameBound::UnboundedPreceding => 0,
+            FrameBound::Preceding(rows) => (position + 1).saturating_sub(self.frame_rows(rows)?),
+            FrameBound::CurrentRow => position + 1,
+            FrameBound::Following(rows) => {
+                position.saturating_add(self.frame_rows(rows)?).saturating_add(1).min(count)
+            }
+            FrameBound::UnboundedFollowing => count,
+        };
+        Ok((start, end.max(start)))
+    }
+
+    fn frame_rows(&self, rows: &Expression) -> Result<usize> {
+        evaluate_row_count(Some(rows), "frame", self.input.parameters)?
+            .ok_or_else(|| MemoryError::InvalidRowCount("frame".to_string(), CellValue::Null.to_string()))
+    }
+}
+
+fn is_same_sort(left: &PartitionRow, right: &PartitionRow, order_by: &[OrderByItem]) -> Result<bool> {
+    for (index, item) in order_by.iter().enumerate() {
+        if compare_for_sort(&left.sort_values[index], &right.sort_values[index], item)? != Ordering::Equal {
+            return Ok(false);
+        }
+    }
+    Ok(true)
+}
+
+// a row of the query result, the values it is sorted on and the ones compared by distinct.
+struct ResultRow {
+    values: Vec<CellValue>,
+    sort_values: Vec<CellValue>,
+    distinct_key: Vec<CellValue>,
+}
+
+// a stable sort on the values `sort_values` gives for each row, one for each order by item.
+fn sort_rows<T>(rows: &mut [T], order_by: &[OrderByItem], sort_values: impl Fn(&T) -> &[CellValue]) -> Result<()> {
+    if order_by.is_empty() {
+        return Ok(());
+    }
+
+    // comparing values of different types fails, the first error is kept and returned.
+    let mut error = None;
+    rows.sort_by(|left, right| {
+        for (index, order_by_item) in order_by.iter().enumerate() {
+            let ordering = compare_for_sort(&sort_values(left)[index], &sort_values(right)[index], order_by_item);
+            match ordering {
+                Ok(Ordering::Equal) => continue,
+                Ok(ordering) => return ordering,
+                Err(err) => {
+                    error.get_or_insert(err);
+                    return Ordering::Equal;
+                }
+            }
+        }
+        Ordering::Equal
+    });
+
+    match error {
+        Some(err) => Err(err),
+        None => Ok(()),
+    }
+}
+
+// `value in (candidates)`: true if any candidate is equal, otherwise null if any comparison was null.
+fn is_in(
+    value: &CellValue,
+    candidates: impl IntoIterator<Item = Result<CellValue>>,
+    is_negated: bool,
+) -> Result<CellValue> {
+    let mut is_unknown = false;
+    for candidate in candidates {
+        match value.compare(&candidate?)? {
+            Some(Ordering::Equal) => return Ok(CellValue::Bool(!is_negated)),
+            Some(_) => {}
+            None => is_unknown = true,
+        }
+    }
+    Ok(if is_unknown {
+        CellValue::Null
+    } else {
+        CellValue::Bool(is_negated)
+    })
+}
+
+fn compare_for_sort(left: &CellValue, right: &CellValue, order_by_item: &OrderByItem) -> Result<Ordering> {
+    // like postgres, nulls come last in ascending order and first in descending order.
+    let nulls_first = order_by_item.nulls_first.unwrap_or(order_by_item.is_descending);
+    let ordering = match (left, right) {
+        (CellValue::Null, CellValue::Null) => return Ok(Ordering::Equal),
+        (CellValue::Null, _) if nulls_first => return Ok(Ordering::Less),
+        (CellValue::Null, _) => return Ok(Ordering::Greater),
+        (_, CellValue::Null) if nulls_first => return Ok(Ordering::Greater),
+        (_, CellValue::Null) => return Ok(Ordering::Less),
+        _ => left.compare(right)?.unwrap_or(Ordering::Equal),
+    };
+    if order_by_item.is_descending {
+        Ok(ordering.reverse())
+    } else {
+        Ok(ordering)
+    }
+}
+
+#[derive(Default, Debug)]
+// the column name and type are only read by users of `QueryResult`.
+#[allow(dead_code)]
+pub struct Cell {
+    pub value: CellValue,
+    pub column_name: String,
+    pub cell_type: CellType,
+}
+
+#[derive(Default, Clone, Debug)]
+pub enum CellValue {
+    #[default]
+    Null,
+    Int(i64),
+    Float(f64),
+    Text(String),
+    Bool(bool),
+}
+
+impl std::fmt::Display for CellValue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CellValue::Null => write!(f, "null"),
+            CellValue::Int(value) => write!(f, "{}", value),
+            CellValue::Float(value) => write!(f, "{}", value),
+            CellValue::Text(value) => write!(f, "'{}'", value),
+            CellValue::Bool(value) => write!(f, "{}", value),
+        }
+    }
+}
+
+// grouping equality: unlike `=` in a query, null equals null, and an int equals
+// a float with the same value.
+impl PartialEq for CellValue {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (CellValue::Null, CellValue::Null) => true,
+            (CellValue::Float(left), CellValue::Float(right)) if left.is_nan() && right.is_nan() => true,
+            _ => self.is_same_as(other),
+        }
+    }
+}
+
+impl Eq for CellValue {}
+
+impl Hash for CellValue {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        match self {
+            CellValue::Null => 0.hash(state),
+            // ints and floats hash alike so that equal numbers land in the same bucket.
+            CellValue::Int(_) | CellValue::Float(_) => {
+                1.hash(state);
+                let value = self.as_float();
+                // -0.0 equals 0.0 and every nan equals every other nan.
+                let value = if value == 0.0 {
+                    0.0
+                } else if value.is_nan() {
+                    f64::NAN
+                } else {
+                    value
+                };
+                value.to_bits().hash(state);
+            }
+            CellValue::Text(value) => {
+                2.hash(state);
+                value.hash(state);
+            }
+            CellValue::Bool(value) => {
+                3.hash(state);
+                value.hash(state);
+            }
+        }
+    }
+}
+
+impl CellValue {
+    pub fn from_literal(token: &Token) -> Result<Self> {
+        match token.kind {
+            TokenKind::Null => Ok(CellValue::Null),
+            TokenKind::Numeric => {
+                if let Ok(value) = token.value.parse::<i64>() {
+                    Ok(CellValue::Int(value))
+                } else if let Ok(value) = token.value.parse::<f64>() {
+                    Ok(CellValue::Float(value))
+                } else {
+                    Err(MemoryError::InvalidNumber(token.value.clone()))
+                }
+            }
+            // strings keep their quotes in the token.
+            TokenKind::String => {
+                let inner = &token.value[1..token.value.len() - 1];
+                Ok(CellValue::Text(inner.replace("''", "'")))
+            }
+            _ => Err(MemoryError::QueryNotValid),
+        }
+    }
+
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            CellValue::Null => "null",
+            CellValue::Int(_) => "int",
+            CellValue::Float(_) => "float",
+            CellValue::Text(_) => "text",
+            CellValue::Bool(_) => "bool",
+        }
+    }
+
+    /// Compares two values of compatible types, `None` means one of them is null.
+    /// Ints and floats are compared as numbers, any other mix of types needs a cast.
+    pub fn compare(&self, other: &CellValue) -> Result<Option<Ordering>> {
+        match (self, other) {
+            (CellValue::Null, _) | (_, CellValue::Null) => Ok(None),
+            (CellValue::Int(left), CellValue::Int(right)) => Ok(Some(left.cmp(right))),
+            (CellValue::Int(left), CellValue::Float(right)) => Ok((*left as f64).partial_cmp(right)),
+            (CellValue::Float(left), CellValue::Int(right)) => Ok(left.partial_cmp(&(*right as f64))),
+            (CellValue::Float(left), CellValue::Float(right)) => Ok(left.partial_cmp(right)),
+            (CellValue::Text(left), CellValue::Text(right)) => Ok(Some(left.cmp(right))),
+            (CellValue::Bool(left), CellValue::Bool(right)) => Ok(Some(left.cmp(right))),
+            _ => Err(MemoryError::TypeMismatch(format!(
+                "cannot compare {} with {}",
+                self.type_name(),
+                other.type_name()
+            ))),
+        }
+    }
+
+    // equality used for keys, values of different types are never the same.
+    pub fn is_same_as(&self, other: &CellValue) -> bool {
+        matches!(self.compare(other), Ok(Some(Ordering::Equal)))
+    }
+
+    fn as_bool(&self, operator_name: &str) -> Result<Option<bool>> {
+        match self {
+            CellValue::Bool(value) => Ok(Some(*value)),
+            CellValue::Null => Ok(None),
+            _ => Err(MemoryError::TypeMismatch(format!(
+                "argument of {} must be bool, not {}",
+                operator_name,
+                self.type_name()
+            ))),
+        }
+    }
+
+    fn as_text(&self, operator_name: &str) -> Result<Option<&str>> {
+        match self {
+            CellValue::Text(value) => Ok(Some(value)),
+            CellValue::Null => Ok(None),
+            _ => Err(MemoryError::TypeMismatch(format!(
+                "argument of {} must be text, not {}",
+                operator_name,
+                self.type_name()
+            ))),
+        }
+    }
+
+    fn negate(&self) -> Result<CellValue> {
+        match self {
+            CellValue::Null => Ok(CellValue::Null),
+            CellValue::Int(value) => value
+                .checked_neg()
+                .map(CellValue::Int)
+                .ok_or(MemoryError::IntegerOverflow),
+            CellValue::Float(value) => Ok(CellValue::Float(-value)),
+            _ => Err(MemoryError::TypeMismatch(format!("cannot negate {}", self.type_name()))),
+        }
+    }
+
+    fn arithmetic(&self, operator: BinaryOperator, other: &CellValue) -> Result<CellValue> {
+        match (self, other) {
+            (CellValue::Null, _) | (_, CellValue::Null) => Ok(CellValue::Null),
+            (CellValue::Int(left), CellValue::Int(right)) => {
+                let result = match operator {
+                    BinaryOperator::Plus => left.checked_add(*right),
+                    BinaryOperator::Minus => left.checked_sub(*right),
+                    BinaryOperator::Multiply => left.checked_mul(*right),
+                    _ => {
+                        if *right == 0 {
+                            return Err(MemoryError::DivisionByZero);
+                        }
+                        left.checked_div(*right)
+                    }
+                };
+                result.map(CellValue::Int).ok_or(MemoryError::IntegerOverflow)
+            }
+            (CellValue::Int(_), CellValue::Float(_))
+            | (CellValue::Float(_), CellValue::Int(_))
+            | (CellValue::Float(_), CellValue::Float(_)) => {
+                let left = self.as_float();
+                let right = other.as_float();
+                Ok(CellValue::Float(match operator {
+                    BinaryOperator::Plus => left + right,
+                    BinaryOperator::Minus => left - right,
+                    BinaryOperator::Multiply => left * right,
+                    _ => {
+                        if right == 0.0 {
+                            return Err(MemoryError::DivisionByZero);
+                        }
+                        left / right
+                    }
+                }))
+            }
+            _ => Err(MemoryError::TypeMismatch(format!(
+                "cannot apply {:?} to {} and {}",
+                operator,
+                self.type_name(),
+                other.type_name()
+            ))),
+        }
+    }
+
+    /// Converts the value to `cell_type` the way `cast(value as cell_type)` does, null stays null.
+    ///
+    /// | from \ to  | int     | float  | text       | bool                    |
+    /// |------------|---------|--------|------------|-------------------------|
+    /// | int        | -       | yes    | yes        | 0 is false, else true   |
+    /// | float      | rounded | -      | yes        | no                      |
+    /// | text       | parsed  | parsed | -          | true/false, t/f, yes/no |
+    /// | bool       | 1 or 0  | no     | true/false | -                       |
+    pub fn cast(&self, cell_type: &CellType) -> Result<CellValue> {
+        let result = match (self, cell_type) {
+            (CellValue::Null, _) => Some(CellValue::Null),
+            (CellValue::Int(_), CellType::Int)
+            | (CellValue::Float(_), CellType::Float)
+            | (CellValue::Text(_), CellType::Text)
+            | (CellValue::Bool(_), CellType::Bool) => Some(self.clone()),
+            (CellValue::Int(value), CellType::Float) => Some(CellValue::Float(*value as f64)),
+            (CellValue::Float(value), CellType::Int) => float_to_int(*value).map(CellValue::Int),
+            (CellValue::Text(value), CellType::Int) => value.trim().parse().ok().map(CellValue::Int),
+            (CellValue::Text(value), CellType::Float) => value.trim().parse().ok().map(CellValue::Float),
+            (CellValue::Text(value), CellType::Bool) => match value.trim().to_lowercase().as_str() {
+                "true" | "t" | "yes" | "y" | "on" | "1" => Some(CellValue::Bool(true)),
+                "false" | "f" | "no" | "n" | "off" | "0" => Some(CellValue::Bool(false)),
+                _ => None,
+            },
+            (CellValue::Int(value), CellType::Bool) => Some(CellValue::Bool(*value != 0)),
+            (CellValue::Bool(value), CellType::Int) => Some(CellValue::Int(*value as i64)),
+            (CellValue::Int(value), CellType::Text) => Some(CellValue::Text(value.to_string())),
+            (CellValue::Float(value), CellType::Text) => Some(CellValue::Text(value.to_string())),
+            (CellValue::Bool(value), CellType::Text) => Some(CellValue::Text(value.to_string())),
+            (CellValue::Float(_), CellType::Bool) | (CellValue::Bool(_), CellType::Float) => None,
+        };
+        result.ok_or_else(|| MemoryError::InvalidConversion(self.to_string(), cell_type.clone()))
+    }
+
+    /// Converts a value stored in a column of type `cell_type` by an insert or an update.
+    /// Only numbers are converted to each other, and any value can be stored as text:
+    ///
+    /// | from \ to  | int     | float | text | bool |
+    /// |------------|---------|-------|------|------|
+    /// | int        | -       | yes   | yes  | no   |
+    /// | float      | rounded | -     | yes  | no   |
+    /// | text       | no      | no    | -    | no   |
+    /// | bool       | no      | no    | yes  | -    |
+    ///
+    /// Comparisons and arithmetic only mix ints and floats, see `compare`.
+    pub fn coerce(self, cell_type: &CellType) -> Result<CellValue> {
+        let is_implicit = matches!(
+            (&self, cell_type),
+            (CellValue::Null, _)
+                | (_, CellType::Text)
+                | (CellValue::Int(_) | CellValue::Float(_), CellType::Int | CellType::Float)
+                | (CellValue::Bool(_), CellType::Bool)
+        );
+        if !is_implicit {
+            return Err(MemoryError::InvalidConversion(self.to_string(), cell_type.clone()));
+        }
+        self.cast(cell_type)
+    }
+
+    fn as_float(&self) -> f64 {
+        match self {
+            CellValue::Int(value) => *value as f64,
+            CellValue::Float(value) => *value,
+            _ => f64::NAN,
+        }
+    }
+}
+
+// rounds half away from zero, None when the value doesn't fit.
+fn float_to_int(value: f64) -> Option<i64> {
+    let value = value.round();
+    if value.is_finite() && value >= i64::MIN as f64 && value < i64::MAX as f64 {
+        Some(value as i64)
+    } else {
+        None
+    }
+}
+
+impl Memory {
+    pub fn get_table(&self, table_name: &str) -> Result<&Table> {
+        let table = self.tables.get(table_name);
+        table.ok_or_else(|| MemoryError::TableNotFound(table_name.to_string()))
+    }
+
+    pub fn get_table_mut(&mut self, table_name: &str) -> Result<&mut Table> {
+        let table = self.tables.get_mut(table_name);
+        table.ok_or_else(|| MemoryError::TableNotFound(table_name.to_string()))
+    }
+
+    fn insert_table(&mut self, table_name: &str, table: Table) {
+        self.tables.insert(table_name.to_string(), table);
+    }
+
+    // tables and views share their names.
+    fn check_name_is_free(&self, name: &str) -> Result<()> {
+        if self.tables.contains_key(name) {
+            return Err(MemoryError::TableAlreadyExists(name.to_string()));
+        }
+        if self.views.contains_key(name) {
+            return Err(MemoryError::ViewAlreadyExists(name.to_string()));
+        }
+        Ok(())
+    }
+
+    // the views reading `name`, and the views reading those, sorted by name.
+    fn dependent_views(&self, name: &str) -> Vec<String> {
+        let mut dependents = Vec::<String>::new();
+        let mut unvisited = vec![name.to_string()];
+        while let Some(relation) = unvisited.pop() {
+            for (view_name, view) in &self.views {
+                if view.relations.contains(&relation) && !dependents.contains(view_name) {
+                    dependents.push(view_name.clone());
+                    unvisited.push(view_name.clone());
+                }
+            }
+        }
+        dependents.sort();
+        dependents
+    }
+
+    // a table with the columns and rows of `query`, without any constraints.
+    fn create_table_as(&self, table_name: &str, query: &SelectStatement) -> Result<Table> {
+        let patterns = PatternCache::default();
+        let outer = RowContext {
+            memory: Some(self),
+            patterns: Some(&patterns),
+            ..Default::default()
+        };
+        let result = self.query(query, &outer)?;
+
+        let mut columns = Vec::<Column>::new();
+        for column in &result.columns {
+            if columns.iter().any(|other| other.name == column.name) {
+                return Err(MemoryError::DuplicateColumn(column.name.clone()));
+            }
+            columns.push(Column {
+                name: column.name.clone(),
+                column_type: column.column_type.clone(),
+                ..Default::default()
+            });
+        }
+        Ok(Table {
+            name: table_name.to_string(),
+            columns,
+            rows: result_values(result),
+            ..Default::default()
+        })
+    }
+}
+
+pub type Result<T> = std::result::Result<T, MemoryError>;
+
+#[derive(Debug)]
+pub enum MemoryError {
+    // the String is the table name
+    TableNotFound(String),
+    ColumnNotFound(String),
+    TableAlreadyExists(String),
+    InvalidType(String),
+    QueryNotValid,
+    // the number of columns and the number of values given
+    WrongNumberOfValues(usize, usize),
+    InvalidNumber(String),
+    TypeMismatch(String),
+    DivisionByZero,
+    IntegerOverflow,
+    MultiplePrimaryKeys(String),
+    // the key name and the duplicated value
+    UniqueViolation(String, String),
+    // the String is the column name
+    NotNullViolation(String),
+    // the String is the constraint name
+    CheckViolation(String),
+    DuplicateColumn(String),
+    DuplicateAssignment(String),
+    // the String is the position as written in the query
+    InvalidOrderByPosition(String),
+    OrderByNotInDistinct,
+    DistinctOnNotInOrderBy,
+    // the clause (limit or offset) and the value it was given
+    InvalidRowCount(String, String),
+    // the String is the function name
+    FunctionNotFound(String),
+    InvalidArguments(String),
+    AggregateNotAllowed(String),
+    // the String is the column name
+    ColumnNotGrouped(String),
+    AmbiguousColumn(String),
+    SubqueryNotAllowed,
+    // the usize is the number of columns the subquery returned
+    SubqueryColumns(usize),
+    MoreThanOneRow,
+    // the String is the table name, the usizes the number of columns and of names
+    TooManyColumnNames(String, usize, usize),
+    // the String is the operator, e.g. union
+    SetOperationColumns(String),
+    // the String is the table name and the usize the limit
+    RecursionDepthExceeded(String, usize),
+    RecursionRowsExceeded(String, usize),
+    // the String is what combines the values, e.g. union or case
+    IncompatibleTypes(String, CellType, CellType),
+    // the String is the value as it is displayed
+    InvalidConversion(String, CellType),
+    // the pattern or escape and what is wrong with it
+    InvalidPattern(String, String),
+    ViewAlreadyExists(String),
+    ViewNotFound(String),
+    // the name and the kind of object the statement expected, e.g. view
+    WrongObjectKind(String, String),
+    // the String is the dropped table or view and the Vec the views that read it
+    DependentViews(String, Vec<String>),
+    // the String is the function name
+    WindowNotAllowed(String),
+    NotAWindowFunction(String),
+    // the String is the list of conflict columns
+    ConflictKeyNotFound(String),
+    // the String is the table name
+    RowUpdatedTwice(String),
+    // the number of the parameter
+    UnboundParameter(usize),
+    // the number of parameters of the statement and the number of values given
+    WrongNumberOfParameters(usize, usize),
+    // the number of the parameter, the value and the type of the parameter
+    InvalidParameter(usize, String, CellType),
+    // the number of the parameter
+    UnknownParameterType(usize),
+    NotPreparable,
+    PreparedStatementNotFound(String),
+    PreparedStatementAlreadyExists(String),
+    NoRowsReturned,
+    // the String is the referenced table
+    NoReferencedKey(String),
+    // the number of referencing and of referenced columns
+    WrongNumberOfReferencedColumns(usize, usize),
+    // the constraint name, the referencing key's value and the referenced table
+    ForeignKeyViolation(String, String, String),
+    // the constraint name, the referenced key's value and the referencing table
+    StillReferenced(String, String, String),
+    // the String is the dropped table and the Vec the foreign keys referencing it
+    DependentForeignKeys(String, Vec<String>),
+    // the String is the column name
+    GeneratedAlways(String),
+    InvalidIdentity(String),
+    // the String is the name of the setting
+    SettingNotFound(String),
+}
+
+impl std::fmt::Display for MemoryError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MemoryError::TableNotFound(name) => f.write_fmt(format_args!("table '{}' not found", name)),
+            // TODO: add table name too
+            MemoryError::ColumnNotFound(name) => f.write_fmt(format_args!("column '{}' not found", name)),
+            MemoryError::TableAlreadyExists(name) => f.write_fmt(format_args!("table '{}' already exists", name)),
+            MemoryError::InvalidType(type_name) => f.write_fmt(format_args!("type '{}' is not valid", type_name)),
+            MemoryError::QueryNotValid => f.write_str("Query not valid"),
+            MemoryError::WrongNumberOfValues(expected, received) => f.write_fmt(format_args!(
+                "expected {} values but received {}",
+                expected, received
+            )),
+            MemoryError::InvalidNumber(value) => f.write_fmt(format_args!("'{}' is not a valid number", value)),
+            MemoryError::TypeMismatch(message) => f.write_str(message),
+            MemoryError::DivisionByZero => f.write_str("division by zero"),
+            MemoryError::IntegerOverflow => f.write_str("integer out of range"),
+            MemoryError::MultiplePrimaryKeys(table) => f.write_fmt(format_args!(
+                "multiple primary keys for table '{}' are not allowed",
+                table
+            )),
+            MemoryError::UniqueViolation(key, value) => f.write_fmt(format_args!(
+                "duplicate key value violates unique constraint '{}': {} already exists",
+                key, value
+            )),
+            MemoryError::NotNullViolation(column) => f.write_fmt(format_args!(
+                "null value in column '{}' violates not-null constraint",
+                column
+            )),
+            MemoryError::CheckViolation(name) => f.write_fmt(format_args!(
+                "new row violates check constraint '{}'",
+                name
+            )),
+            MemoryError::DuplicateColumn(name) => f.write_fmt(format_args!("column '{}' specified more than once", name)),
+            MemoryError::DuplicateAssignment(name) => {
+                f.write_fmt(format_args!("multiple assignments to the same column '{}'", name))
+            }
+            MemoryError::InvalidOrderByPosition(position) => f.write_fmt(format_args!(
+                "order by position {} is not in select list",
+                position
+            )),
+            MemoryError::OrderByNotInDistinct => {
+                f.write_str("for select distinct, order by expressions must appear in select list")
+            }
+            MemoryError::DistinctOnNotInOrderBy => {
+                f.write_str("select distinct on expressions must match initial order by expressions")
+            }
+            MemoryError::InvalidRowCount(clause, value) => f.write_fmt(format_args!(
+                "{} must be a non-negative integer, not {}",
+                clause, value
+            )),
+            MemoryError::FunctionNotFound(name) => f.write_fmt(format_args!("function '{}' does not exist", name)),
+            MemoryError::InvalidArguments(name) => f.write_fmt(format_args!("invalid arguments for function '{}'", name)),
+            MemoryError::AggregateNotAllowed(name) => f.write_fmt(format_args!(
+                "aggregate function '{}' is not allowed here",
+                name
+            )),
+            MemoryError::ColumnNotGrouped(name) => f.write_fmt(format_args!(
+                "column '{}' must appear in the group by clause or be used in an aggregate function",
+                name
+            )),
+            MemoryError::AmbiguousColumn(name) => f.write_fmt(format_args!("column reference '{}' is ambiguous", name)),
+            MemoryError::SubqueryNotAllowed => f.write_str("subqueries are not allowed here"),
+            MemoryError::SubqueryColumns(count) => f.write_fmt(format_args!(
+                "subquery must return only one column, got {}",
+                count
+            )),
+            MemoryError::MoreThanOneRow => f.write_str("more than one row returned by a subquery used as an expression"),
+            MemoryError::TooManyColumnNames(table, available, specified) => f.write_fmt(format_args!(
+                "table '{}' has {} columns available but {} columns specified",
+                table, available, specified
+            )),
+            MemoryError::SetOperationColumns(operator) => f.write_fmt(format_args!(
+                "each {} query must have the same number of columns",
+                operator
+            )),
+            MemoryError::RecursionDepthExceeded(table, limit) => f.write_fmt(format_args!(
+                "recursive query '{}' exceeded the maximum depth of {}",
+                table, limit
+            )),
+            MemoryError::RecursionRowsExceeded(table, limit) => f.write_fmt(format_args!(
+                "recursive query '{}' exceeded the maximum of {} rows",
+                table, limit
+            )),
+            MemoryError::IncompatibleTypes(operator, left, right) => f.write_fmt(format_args!(
+                "{} types {} and {} cannot be matched",
+                operator, left, right
+            )),
+            MemoryError::InvalidConversion(value, cell_type) => {
+                f.write_fmt(format_args!("cannot convert {} to {}", value, cell_type))
+            }
+            MemoryError::InvalidPattern(pattern, reason) => {
+                f.write_fmt(format_args!("invalid pattern '{}': {}", pattern, reason))
+            }
+            MemoryError::ViewAlreadyExists(name) => f.write_fmt(format_args!("view '{}' already exists", name)),
+            MemoryError::ViewNotFound(name) => f.write_fmt(format_args!("view '{}' not found", name)),
+            MemoryError::WrongObjectKind(name, kind) => f.write_fmt(format_args!("'{}' is not a {}", name, kind)),
+            MemoryError::DependentViews(name, views) => f.write_fmt(format_args!(
+                "cannot drop '{}' because the views {} depend on it, use cascade to drop them too",
+                name,
+                views.join(", ")
+            )),
+            MemoryError::WindowNotAllowed(name) => f.write_fmt(format_args!(
+                "window function '{}' is only allowed in the select list and order by",
+                name
+            )),
+            MemoryError::NotAWindowFunction(name) => {
+                f.write_fmt(format_args!("function '{}' is not a window function", name))
+            }
+            MemoryError::ConflictKeyNotFound(columns) => f.write_fmt(format_args!(
+                "there is no primary key or unique constraint on ({}) for on conflict",
+                columns
+            )),
+            MemoryError::RowUpdatedTwice(name) => f.write_fmt(format_args!(
+                "on conflict do update can't change a row of '{}' twice, the inserted rows have duplicate keys",
+                name
+            )),
+            MemoryError::UnboundParameter(index) => f.write_fmt(format_args!(
+                "there is no value for parameter ${}, only prepared statements have them",
+                index
+            )),
+            MemoryError::WrongNumberOfParameters(expected, received) => f.write_fmt(format_args!(
+                "expected {} parameters but received {}",
+                expected, received
+            )),
+            MemoryError::InvalidParameter(index, value, cell_type) => f.write_fmt(format_args!(
+                "parameter ${} is of type {}, it can't be {}",
+                index, cell_type, value
+            )),
+            MemoryError::UnknownParameterType(index) => {
+                f.write_fmt(format_args!("could not determine the type of parameter ${}", index))
+            }
+            MemoryError::NotPreparable => f.write_str("only select, insert, update and delete can be prepared"),
+            MemoryError::PreparedStatementNotFound(name) => {
+                f.write_fmt(format_args!("prepared statement '{}' does not exist", name))
+            }
+            MemoryError::PreparedStatementAlreadyExists(name) => {
+                f.write_fmt(format_args!("prepared statement '{}' already exists", name))
+            }
+            MemoryError::NoRowsReturned => f.write_str("the statement doesn't return rows"),
+            MemoryError::NoReferencedKey(table) => f.write_fmt(format_args!(
+                "there is no primary key or unique constraint on the referenced columns of table '{}'",
+                table
+            )),
+            MemoryError::WrongNumberOfReferencedColumns(columns, referenced) => f.write_fmt(format_args!(
+                "a foreign key of {} columns can't reference {} columns",
+                columns, referenced
+            )),
+            MemoryError::ForeignKeyViolation(name, key, table) => f.write_fmt(format_args!(
+                "insert or update violates foreign key constraint '{}': {} is not present in table '{}'",
+                name, key, table
+            )),
+            MemoryError::StillReferenced(name, key, table) => f.write_fmt(format_args!(
+                "update or delete violates foreign key constraint '{}': {} is still referenced from table '{}'",
+                name, key, table
+            )),
+            MemoryError::DependentForeignKeys(name, foreign_keys) => f.write_fmt(format_args!(
+                "cannot drop '{}' because the foreign keys {} reference it, use cascade to drop them too",
+                name,
+                foreign_keys.join(", ")
+            )),
+            MemoryError::GeneratedAlways(column) => f.write_fmt(format_args!(
+                "column '{}' is generated always as identity, its values can't be given",
+                column
+            )),
+            MemoryError::InvalidIdentity(column) => f.write_fmt(format_args!(
+                "identity column '{}' has to be an int without a default",
+                column
+            )),
+            MemoryError::SettingNotFound(name) => {
+                f.write_fmt(format_args!("unrecognized configuration parameter '{}'", name))
+            }
+        }
+    }
+}
+
+impl std::error::Error for MemoryError {}
+
+#[cfg(test)]
+mod tests {
+    use crate::database::{CellType, Database};
+    use crate::table::{CellValue, Memory, MemoryError};
+
+    fn run(memory: &mut Memory, query: &str) {
+        if let Err(err) = memory.run_query(query) {
+            panic!("'{}' failed: {}", query, err);
+        }
+    }
+
+    fn run_err(memory: &mut Memory, query: &str) -> MemoryError {
+        let err = memory.run_query(query).expect_err(query);
+        *err.downcast::<MemoryError>().expect("not a MemoryError")
+    }
+
+    // the selected rows with every cell formatted.
+    fn select(memory: &mut Memory, query: &str) -> Vec<Vec<String>> {
+        let result = memory.run_query(query).unwrap().unwrap();
+        result
+            .rows
+            .iter()
+            .map(|row| row.iter().map(|cell| cell.value.to_string()).collect())
+            .collect()
+    }
+
+    #[test]
+    fn test_where_and_update() {
+        let mut memory = Memory::default();
+        run(&mut memory, "create table users (id int, name text)");
+        run(&mut memory, "insert into users values (1, 'one')");
+        run(&mut memory, "insert into users values (2, 'two')");
+        run(&mut memory, "update users set name = 'three', id = id + 1 where id >= 2");
+        assert_eq!(
+            select(&mut memory, "select id, name from users where not id = 1"),
+            vec![vec!["3", "'three'"]]
+        );
+    }
+
+    #[test]
+    fn test_primary_key_column() {
+        let mut memory = Memory::default();
+        run(&mut memory, "create table users (id int primary key, name text)");
+        run(&mut memory, "insert into users values (1, 'one')");
+        match run_err(&mut memory, "insert into users values (1, 'two')") {
+            MemoryError::UniqueViolation(key, value) => {
+                assert_eq!(key, "users_pkey");
+                assert_eq!(value, "(id)=(1)");
+            }
+            err => panic!("unexpected error {:?}", err),
+        }
+        assert!(matches!(
+            run_err(&mut memory, "insert into users values (null, 'two')"),
+            MemoryError::NotNullViolation(_)
+        ));
+    }
+
+    #[test]
+    fn test_primary_key_table_constraint() {
+        let mut memory = Memory::default();
+        run(&mut memory, "create table pairs (a int, b text, primary key (a, b))");
+        run(&mut memory, "insert into pairs values (1, 'x')");
+        run(&mut memory, "insert into pairs values (1, 'y')");
+        assert!(matches!(
+            run_err(&mut memory, "insert into pairs values (1, 'x')"),
+            MemoryError::UniqueViolation(_, _)
+        ));
+        assert!(matches!(
+            run_err(&mut memory, "create table bad (a int primary key, b int, primary key (b))"),
+            MemoryError::MultiplePrimaryKeys(_)
+        ));
+    }
+
+    #[test]
+    fn test_update_primary_key_is_atomic() {
+        let mut memory = Memory::default();
+        run(&mut memory, "create table users (id int primary key)");
+        run(&mut memory, "insert into users values (1)");
+        run(&mut memory, "insert into users values (2)");
+        run(&mut memory, "insert into users values (4)");
+        assert!(matches!(
+            run_err(&mut memory, "update users set id = id + 1"),
+            MemoryError::UniqueViolation(_, _)
+        ));
+        assert_eq!(
+            select(&mut memory, "select id from users"),
+            vec![vec!["1"], vec!["2"], vec!["4"]]
+        );
+        // a column can only be set once.
+        assert!(matches!(
+            run_err(&mut memory, "update users set id = 5, id = 6 where id = 4"),
+            MemoryError::DuplicateAssignment(_)
+        ));
+        assert!(matches!(
+            run_err(&mut memory, "insert into users values (4) on conflict (id) do update set id = 5, id = 6"),
+            MemoryError::DuplicateAssignment(_)
+        ));
+        assert!(matches!(
+            run_err(&mut memory, "explain update users set id = 5, id = 6"),
+            MemoryError::DuplicateAssignment(_)
+        ));
+    }
+
+    #[test]
+    fn test_not_null_and_default() {
+        let mut memory = Memory::default();
+        run(&mut memory, "create table users (id int not null, name text default 'anon', age int)");
+        run(&mut memory, "insert into users (id) values (1)");
+        assert_eq!(
+            select(&mut memory, "select id, name, age from users"),
+            vec![vec!["1", "'anon'", "null"]]
+        );
+        assert!(matches!(
+            run_err(&mut memory, "insert into users (name) values ('x')"),
+            MemoryError::NotNullViolation(_)
+        ));
+        assert!(matches!(
+            run_err(&mut memory, "update users set id = null"),
+            MemoryError::NotNullViolation(_)
+        ));
+    }
+
+    #[test]
+    fn test_unique() {
+        let mut memory = Memory::default();
+        run(&mut memory, "create table users (id int, email text unique, a int, b int, unique (a, b))");
+        run(&mut memory, "insert into users values (1, 'a@x.com', 1, 1)");
+        run(&mut memory, "insert into users values (2, null, 1, 2)");
+        run(&mut memory, "insert into users values (3, null, 2, 1)");
+        match run_err(&mut memory, "insert into users values (4, 'a@x.com', 3, 3)") {
+            MemoryError::UniqueViolation(key, _) => assert_eq!(key, "users_email_key"),
+            err => panic!("unexpected error {:?}", err),
+        }
+        match run_err(&mut memory, "update users set b = 1 where id = 2") {
+            MemoryError::UniqueViolation(key, _) => assert_eq!(key, "users_a_b_key"),
+            err => panic!("unexpected error {:?}", err),
+        }
+    }
+
+    #[test]
+    fn test_check() {
+        let mut memory = Memory::default();
+        run(&mut memory, "create table items (price int check (price > 0), discount int, check (discount < price))");
+        run(&mut memory, "insert into items values (10, 5)");
+        run(&mut memory, "insert into items values (null, 5)");
+        match run_err(&mut memory, "insert into items values (0, null)") {
+            MemoryError::CheckViolation(name) => assert_eq!(name, "items_price_check"),
+            err => panic!("unexpected error {:?}", err),
+        }
+        match run_err(&mut memory, "update items set discount = 20 where price = 10") {
+            MemoryError::CheckViolation(name) => assert_eq!(name, "items_check"),
+            err => panic!("unexpected error {:?}", err),
+        }
+        assert!(matches!(
+            run_err(&mut memory, "create table bad (a int check (b > 0))"),
+            MemoryError::ColumnNotFound(_)
+        ));
+    }
+
+    fn scores(memory: &mut Memory) {
+        run(memory, "create table scores (name text, score int)");
+        run(memory, "insert into scores values ('b', 10)");
+        run(memory, "insert into scores values ('a', 9)");
+        run(memory, "insert into scores values ('c', null)");
+        run(memory, "insert into scores values ('d', 100)");
+    }
+
+    #[test]
+    fn test_order_by() {
+        let mut memory = Memory::default();
+        scores(&mut memory);
+        assert_eq!(
+            select(&mut memory, "select name from scores order by score"),
+            vec![vec!["'a'"], vec!["'b'"], vec!["'d'"], vec!["'c'"]]
+        );
+        assert_eq!(
+            select(&mut memory, "select name from scores order by score desc"),
+            vec![vec!["'c'"], vec!["'d'"], vec!["'b'"], vec!["'a'"]]
+        );
+        assert_eq!(
+            select(&mut memory, "select name from scores order by score desc nulls last, name"),
+            vec![vec!["'d'"], vec!["'b'"], vec!["'a'"], vec!["'c'"]]
+        );
+        assert_eq!(
+            select(&mut memory, "select name from scores order by score asc nulls first"),
+            vec![vec!["'c'"], vec!["'a'"], vec!["'b'"], vec!["'d'"]]
+        );
+    }
+
+    #[test]
+    fn test_order_by_ordinal_and_alias() {
+        let mut memory = Memory::default();
+        scores(&mut memory);
+        assert_eq!(
+            select(&mut memory, "select name, score * -1 as negated from scores where score > 9 order by negated"),
+            vec![vec!["'d'", "-100"], vec!["'b'", "-10"]]
+        );
+        assert_eq!(
+            select(&mut memory, "select score, name from scores order by 2 desc"),
+            vec![vec!["100", "'d'"], vec!["null", "'c'"], vec!["10", "'b'"], vec!["9", "'a'"]]
+        );
+        assert!(matches!(
+            run_err(&mut memory, "select name from scores order by 2"),
+            MemoryError::InvalidOrderByPosition(_)
+        ));
+    }
+
+    #[test]
+    fn test_limit_and_offset() {
+        let mut memory = Memory::default();
+        scores(&mut memory);
+        assert_eq!(
+            select(&mut memory, "select name from scores limit 2"),
+            vec![vec!["'b'"], vec!["'a'"]]
+        );
+        assert_eq!(
+            select(&mut memory, "select name from scores limit 2 offset 1"),
+            vec![vec!["'a'"], vec!["'c'"]]
+        );
+        assert_eq!(
+            select(&mut memory, "select name from scores order by name offset 1 rows fetch first 2 rows only"),
+            vec![vec!["'b'"], vec!["'c'"]]
+        );
+        assert_eq!(
+            select(&mut memory, "select name from scores order by name desc fetch next row only"),
+            vec![vec!["'d'"]]
+        );
+        assert_eq!(select(&mut memory, "select name from scores offset 10").len(), 0);
+        assert_eq!(select(&mut memory, "select name from scores limit all").len(), 4);
+        assert!(matches!(
+            run_err(&mut memory, "select name from scores limit -1"),
+            MemoryError::InvalidRowCount(_, _)
+        ));
+    }
+
+    #[test]
+    fn test_limit_stops_scan_early() {
+        let mut memory = Memory::default();
+        run(&mut memory, "create table numbers (n int)");
+        run(&mut memory, "insert into numbers values (1)");
+        run(&mut memory, "insert into numbers values (0)");
+        // the second row would fail with a division by zero if it was evaluated.
+        assert_eq!(
+            select(&mut memory, "select 1 / n from numbers limit 1"),
+            vec![vec!["1"]]
+        );
+    }
+
+    fn employees(memory: &mut Memory) {
+        run(memory, "create table employees (name text, dept text, salary int)");
+        run(memory, "insert into employees values ('ann', 'eng', 100)");
+        run(memory, "insert into employees values ('bob', 'eng', 80)");
+        run(memory, "insert into employees values ('cid', 'ops', 50)");
+        run(memory, "insert into employees values ('dan', 'eng', 80)");
+        run(memory, "insert into employees values ('eve', null, null)");
+    }
+
+    #[test]
+    fn test_group_by() {
+        let mut memory = Memory::default();
+        employees(&mut memory);
+        assert_eq!(
+            select(
+                &mut memory,
+                "select dept, count(*), count(distinct salary), sum(salary), avg(salary), min(name), max(salary) \
+                 from employees group by dept order by dept"
+            ),
+            vec![
+                vec!["'eng'", "3", "2", "260", "86.66666666666667", "'ann'", "100"],
+                vec!["'ops'", "1", "1", "50", "50", "'cid'", "50"],
+                vec!["null", "1", "0", "null", "null", "'eve'", "null"],
+            ]
+        );
+
+        // the average of ints is a float even when their sum is out of range.
+        run(&mut memory, "create table big (v int)");
+        run(&mut memory, "insert into big values (9223372036854775807)");
+        run(&mut memory, "insert into big values (1)");
+        assert_eq!(select(&mut memory, "select avg(v) from big"), [["4611686018427388000"]]);
+        assert!(memory.run_query("select sum(v) from big").is_err());
+    }
+
+    #[test]
+    fn test_having_and_aggregate_expressions() {
+        let mut memory = Memory::default();
+        employees(&mut memory);
+        assert_eq!(
+            select(
+                &mut memory,
+                "select dept, sum(salary) * 2 as doubled from employees \
+                 where salary > 50 group by dept having count(*) > 1 order by count(*) desc"
+            ),
+            vec![vec!["'eng'", "520"]]
+        );
+        assert_eq!(
+            select(&mut memory, "select count(*), count(dept), max(salary) - min(salary) from employees"),
+            vec![vec!["5", "4", "50"]]
+        );
+        let result = memory.run_query("select count(*), sum(salary) as total, max(salary) from employees").unwrap().unwrap();
+        let names: Vec<&str> = result.columns.iter().map(|column| column.name.as_str()).collect();
+        assert_eq!(names, ["count", "total", "max"]);
+        assert_eq!(
+            select(&mut memory, "select count(*), sum(salary) from employees where salary > 1000"),
+            vec![vec!["0", "null"]]
+        );
+    }
+
+    #[test]
+    fn test_group_by_errors() {
+        let mut memory = Memory::default();
+        employees(&mut memory);
+        assert!(matches!(
+            run_err(&mut memory, "select name, count(*) from employees group by dept"),
+            MemoryError::ColumnNotGrouped(_)
+        ));
+        assert!(matches!(
+            run_err(&mut memory, "select name from employees where count(*) > 1"),
+            MemoryError::AggregateNotAllowed(_)
+        ));
+        assert!(matches!(
+            run_err(&mut memory, "select sum(count(*)) from employees"),
+            MemoryError::AggregateNotAllowed(_)
+        ));
+        assert!(matches!(
+            run_err(&mut memory, "select sum(*) from employees"),
+            MemoryError::InvalidArguments(_)
+        ));
+        assert_eq!(
+            run_err(&mut memory, "select dept from employees group by dept having sum(salary)").to_string(),
+            "argument of having must be bool, not int"
+        );
+    }
+
+    fn departments(memory: &mut Memory) {
+        employees(memory);
+        run(memory, "create table depts (dept text, floor int)");
+        run(memory, "insert into depts values ('eng', 1)");
+        run(memory, "insert into depts values ('ops', 2)");
+        run(memory, "insert into depts values ('hr', 3)");
+    }
+
+    #[test]
+    fn test_inner_and_outer_joins() {
+        let mut memory = Memory::default();
+        departments(&mut memory);
+        assert_eq!(
+            select(
+                &mut memory,
+                "select e.name, d.floor from employees e join depts as d on e.dept = d.dept order by e.name"
+            ),
+            vec![vec!["'ann'", "1"], vec!["'bob'", "1"], vec!["'cid'", "2"], vec!["'dan'", "1"]]
+        );
+        assert_eq!(
+            select(
+                &mut memory,
+                "select name, floor from employees left outer join depts on employees.dept = depts.dept \
+                 where name > 'ann' order by name"
+            ),
+            vec![vec!["'bob'", "1"], vec!["'cid'", "2"], vec!["'dan'", "1"], vec!["'eve'", "null"]]
+        );
+        assert_eq!(
+            select(
+                &mut memory,
+                "select d.dept, count(name) from employees e right join depts d on e.dept = d.dept \
+                 group by d.dept order by d.dept"
+            ),
+            vec![vec!["'eng'", "3"], vec!["'hr'", "0"], vec!["'ops'", "1"]]
+        );
+        assert_eq!(
+            select(
+                &mut memory,
+                "select name, floor from employees e full join depts d on e.dept = d.dept order by name desc limit 2"
+            ),
+            vec![vec!["null", "3"], vec!["'eve'", "null"]]
+        );
+    }
+
+    #[test]
+    fn test_cross_join_and_using() {
+        let mut memory = Memory::default();
+        departments(&mut memory);
+        assert_eq!(
+            select(&mut memory, "select count(*) from employees cross join depts"),
+            vec![vec!["15"]]
+        );
+        assert_eq!(
+            select(&mut memory, "select count(*) from employees, depts where employees.dept = depts.dept"),
+            vec![vec!["4"]]
+        );
+        assert_eq!(
+            select(
+                &mut memory,
+                "select dept, count(employees.dept), count(depts.dept) from employees full join depts using (dept) \
+                 group by dept order by dept"
+            ),
+            vec![
+                vec!["'eng'", "3", "3"],
+                vec!["'hr'", "0", "1"],
+                vec!["'ops'", "1", "1"],
+                vec!["null", "0", "0"],
+            ]
+        );
+    }
+
+    #[test]
+    fn test_join_errors() {
+        let mut memory = Memory::default();
+        departments(&mut memory);
+        assert!(matches!(
+            run_err(&mut memory, "select dept from employees join depts on employees.dept = depts.dept"),
+            MemoryError::AmbiguousColumn(_)
+        ));
+        assert!(matches!(
+            run_err(&mut memory, "select employees.name from employees e join depts d on e.dept = d.dept"),
+            MemoryError::ColumnNotFound(_)
+        ));
+        assert!(matches!(
+            run_err(&mut memory, "select name from employees join depts using (floor)"),
+            MemoryError::ColumnNotFound(_)
+        ));
+    }
+
+    #[test]
+    fn test_scalar_and_correlated_subqueries() {
+        let mut memory = Memory::default();
+        departments(&mut memory);
+        assert_eq!(
+            select(&mut memory, "select name from employees where salary = (select max(salary) from employees)"),
+            vec![vec!["'ann'"]]
+        );
+        assert_eq!(
+            select(
+                &mut memory,
+                "select name from employees e \
+                 where salary > (select avg(salary) from employees where dept = e.dept)"
+            ),
+            vec![vec!["'ann'"]]
+        );
+        assert_eq!(
+            select(
+                &mut memory,
+                "select dept, (select count(*) from employees where employees.dept = depts.dept) as staff \
+                 from depts order by floor"
+            ),
+            vec![vec!["'eng'", "3"], vec!["'ops'", "1"], vec!["'hr'", "0"]]
+        );
+        assert_eq!(
+            select(&mut memory, "select (select name from employees where salary > 1000) from depts limit 1"),
+            vec![vec!["null"]]
+        );
+    }
+
+    #[test]
+    fn test_in_and_exists_subqueries() {
+        let mut memory = Memory::default();
+        departments(&mut memory);
+        assert_eq!(
+            select(
+                &mut memory,
+                "select name from employees where dept in (select dept from depts where floor < 2) order by name"
+            ),
+            vec![vec!["'ann'"], vec!["'bob'"], vec!["'dan'"]]
+        );
+        assert_eq!(
+            select(
+                &mut memory,
+                "select dept from depts where dept not in (select dept from employees where dept = 'eng')"
+            ),
+            vec![vec!["'ops'"], vec!["'hr'"]]
+        );
+        // the null dept of eve makes every `not in` unknown
+        assert_eq!(
+            select(&mut memory, "select dept from depts where dept not in (select dept from employees)"),
+            Vec::<Vec<String>>::new()
+        );
+        assert_eq!(
+            select(
+                &mut memory,
+                "select dept from depts d where not exists (select name from employees where dept = d.dept)"
+            ),
+            vec![vec!["'hr'"]]
+        );
+    }
+
+    #[test]
+    fn test_derived_tables() {
+        let mut memory = Memory::default();
+        departments(&mut memory);
+        assert_eq!(
+            select(
+                &mut memory,
+                "select t.dept, total from (select dept, sum(salary) as total from employees group by dept) as t \
+                 where total > 60"
+            ),
+            vec![vec!["'eng'", "260"]]
+        );
+        assert_eq!(
+            select(
+                &mut memory,
+                "select floor, total from (select dept, sum(salary) as total from employees group by dept) t \
+                 join depts using (dept) order by floor"
+            ),
+            vec![vec!["1", "260"], vec!["2", "50"]]
+        );
+    }
+
+    #[test]
+    fn test_subqueries_in_insert_and_update() {
+        let mut memory = Memory::default();
+        departments(&mut memory);
+        run(&mut memory, "insert into depts values ('qa', (select max(floor) from depts) + 1)");
+        run(
+            &mut memory,
+            "update depts set floor = (select count(*) from employees where employees.dept = depts.dept) \
+             where floor < 4",
+        );
+        assert_eq!(
+            select(&mut memory, "select dept, floor from depts"),
+            vec![vec!["'eng'", "3"], vec!["'ops'", "1"], vec!["'hr'", "0"], vec!["'qa'", "4"]]
+        );
+    }
+
+    #[test]
+    fn test_subquery_errors() {
+        let mut memory = Memory::default();
+        departments(&mut memory);
+        assert!(matches!(
+            run_err(&mut memory, "select (select name from employees) from depts"),
+            MemoryError::MoreThanOneRow
+        ));
+        assert!(matches!(
+            run_err(&mut memory, "select name from employees where salary in (select name, salary from employees)"),
+            MemoryError::SubqueryColumns(2)
+        ));
+        assert!(matches!(
+            run_err(&mut memory, "create table floors (n int check (n in (select floor from depts)))"),
+            MemoryError::SubqueryNotAllowed
+        ));
+        assert!(memory.run_query("select dept from (select dept from depts)").is_err());
+    }
+
+    #[test]
+    fn test_common_table_expressions() {
+        let mut memory = Memory::default();
+        departments(&mut memory);
+        assert_eq!(
+            select(
+                &mut memory,
+                "with eng as (select name, salary from employees where dept = 'eng'), \
+                 top as (select max(salary) as m from eng) \
+                 select name from eng, top where salary = m"
+            ),
+            vec![vec!["'ann'"]]
+        );
+        assert_eq!(
+            select(
+                &mut memory,
+                "with t (n, s) as (select name, salary from employees) select n from t where s = 50"
+            ),
+            vec![vec!["'cid'"]]
+        );
+        assert_eq!(
+            select(
+                &mut memory,
+                "with e as (select dept from employees) \
+                 select dept from depts where not exists (select dept from e where e.dept = depts.dept)"
+            ),
+            vec![vec!["'hr'"]]
+        );
+        assert!(matches!(
+            run_err(&mut memory, "with t (a, b) as (select name from employees) select a from t"),
+            MemoryError::TooManyColumnNames(_, 1, 2)
+        ));
+        assert_eq!(
+            select(
+                &mut memory,
+                "with t as (select dept from employees union select dept from depts) select count(*) from t"
+            ),
+            vec![vec!["4"]]
+        );
+    }
+
+    #[test]
+    fn test_recursive_common_table_expressions() {
+        let mut memory = Memory::default();
+        run(&mut memory, "create table staff (id int, boss int)");
+        for row in ["(1, null)", "(2, 1)", "(3, 1)", "(4, 2)", "(5, 4)", "(6, 9)"] {
+            run(&mut memory, &format!("insert into staff values {}", row));
+        }
+        assert_eq!(
+            select(
+                &mut memory,
+                "with recursive chain as ( \
+                 select id, 0 as depth from staff where id = 1 \
+                 union all \
+                 select staff.id, depth + 1 from staff join chain on staff.boss = chain.id) \
+                 select id, depth from chain order by id"
+            ),
+            vec![vec!["1", "0"], vec!["2", "1"], vec!["3", "1"], vec!["4", "2"], vec!["5", "3"]]
+        );
+
+        // a union without all stops at the cycle.
+        run(&mut memory, "create table edges (src int, dst int)");
+        for row in ["(1, 2)", "(2, 3)", "(3, 1)"] {
+            run(&mut memory, &format!("insert into edges values {}", row));
+        }
+        assert_eq!(
+            select(
+                &mut memory,
+                "with recursive reach (node) as ( \
+                 select src from edges where src = 1 union select dst from edges join reach on src = node) \
+                 select count(*) from reach"
+            ),
+            vec![vec!["3"]]
+        );
+    }
+
+    #[test]
+    fn test_recursion_limit() {
+        let mut memory = Memory::default();
+        run(&mut memory, "create table one (n int)");
+        run(&mut memory, "insert into one values (1)");
+        let query = "with recursive n (i) as (select n from one union all select i + 1 from n) select count(*) from n";
+        assert!(matches!(
+            run_err(&mut memory, query),
+            MemoryError::RecursionDepthExceeded(_, 1000)
+        ));
+
+        run(&mut memory, "set max_recursion_depth = 5000");
+        run(&mut memory, "set max_recursion_rows to 50");
+        assert!(matches!(
+            run_err(&mut memory, "set max_recursion_rows = -1"),
+            MemoryError::InvalidRowCount(_, _)
+        ));
+        assert!(matches!(
+            run_err(&mut memory, "set max_recursion = 10"),
+            MemoryError::SettingNotFound(_)
+        ));
+        assert!(matches!(
+            run_err(&mut memory, query),
+            MemoryError::RecursionRowsExceeded(_, 50)
+        ));
+        assert_eq!(
+            select(
+                &mut memory,
+                "with recursive n (i) as (select n from one union all select i + 1 from n where i < 50) \
+                 select sum(i) from n"
+            ),
+            vec![vec!["1275"]]
+        );
+    }
+
+    #[test]
+    fn test_union() {
+        let mut memory = Memory::default();
+        departments(&mut memory);
+        assert_eq!(
+            select(
+                &mut memory,
+                "select dept as name from depts union select dept from employees order by name"
+            ),
+            vec![vec!["'eng'"], vec!["'hr'"], vec!["'ops'"], vec!["null"]]
+        );
+        assert_eq!(
+            select(
+                &mut memory,
+                "select dept from depts union all select dept from employees order by 1 limit 4"
+            ),
+            vec![vec!["'eng'"], vec!["'eng'"], vec!["'eng'"], vec!["'eng'"]]
+        );
+
+        // ints and floats combine into floats, the names come from the left side.
+        let result = memory
+            .run_query("select floor from depts where floor = 1 union select avg(salary) from employees")
+            .unwrap()
+            .unwrap();
+        assert_eq!(result.columns[0].name, "floor");
+        assert_eq!(result.columns[0].column_type, CellType::Float);
+        let values: Vec<String> = result.rows.iter().map(|row| row[0].value.to_string()).collect();
+        assert_eq!(values, vec!["1", "77.5"]);
+    }
+
+    #[test]
+    fn test_intersect_and_except() {
+        let mut memory = Memory::default();
+        departments(&mut memory);
+        assert_eq!(
+            select(&mut memory, "select dept from employees intersect select dept from depts"),
+            vec![vec!["'eng'"], vec!["'ops'"]]
+        );
+        assert_eq!(
+            select(
+                &mut memory,
+                "select dept from employees intersect all select dept from employees where salary = 80"
+            ),
+            vec![vec!["'eng'"], vec!["'eng'"]]
+        );
+        assert_eq!(
+            select(&mut memory, "select dept from employees except select dept from depts"),
+            vec![vec!["null"]]
+        );
+        assert_eq!(
+            select(&mut memory, "select dept from employees except all select dept from depts"),
+            vec![vec!["'eng'"], vec!["'eng'"], vec!["null"]]
+        );
+        // intersect binds tighter than union and except.
+        assert_eq!(
+            select(
+                &mut memory,
+                "select dept from depts except select dept from employees intersect select dept from depts"
+            ),
+            vec![vec!["'hr'"]]
+        );
+        assert_eq!(
+            select(
+                &mut memory,
+                "select name from employees where salary = 80 \
+                 union select name from employees where salary = 50 \
+                 except select name from employees where salary < 60 order by name"
+            ),
+            vec![vec!["'bob'"], vec!["'dan'"]]
+        );
+    }
+
+    #[test]
+    fn test_set_operation_errors() {
+        let mut memory = Memory::default();
+        departments(&mut memory);
+        assert!(matches!(
+            run_err(&mut memory, "select dept, floor from depts union select dept from employees"),
+            MemoryError::SetOperationColumns(_)
+        ));
+        assert!(matches!(
+            run_err(&mut memory, "select dept from depts intersect select salary from employees"),
+            MemoryError::IncompatibleTypes(_, CellType::Text, CellType::Int)
+        ));
+    }
+
+    #[test]
+    fn test_distinct() {
+        let mut memory = Memory::default();
+        employees(&mut memory);
+        assert_eq!(
+            select(&mut memory, "select distinct dept from employees order by dept"),
+            vec![vec!["'eng'"], vec!["'ops'"], vec!["null"]]
+        );
+        assert_eq!(
+            select(&mut memory, "select distinct dept from employees limit 2 offset 1"),
+            vec![vec!["'ops'"], vec!["null"]]
+        );
+        assert_eq!(
+            select(&mut memory, "select distinct salary * 1.0 from employees where dept = 'eng'"),
+            vec![vec!["100"], vec!["80"]]
+        );
+        assert_eq!(
+            select(&mut memory, "select all dept from employees where salary = 80"),
+            vec![vec!["'eng'"], vec!["'eng'"]]
+        );
+
+        // the cells are compared one by one, not as joined strings.
+        run(&mut memory, "create table pairs (a text, b text)");
+        run(&mut memory, "insert into pairs values ('a', 'bc')");
+        run(&mut memory, "insert into pairs values ('ab', 'c')");
+        run(&mut memory, "insert into pairs values ('a', 'bc')");
+        assert_eq!(
+            select(&mut memory, "select distinct a, b from pairs"),
+            vec![vec!["'a'", "'bc'"], vec!["'ab'", "'c'"]]
+        );
+
+        // a sort key that is not selected has no single value for the rows merged into one.
+        assert_eq!(
+            select(&mut memory, "select distinct upper(a) from pairs order by upper(a) desc"),
+            vec![vec!["'AB'"], vec!["'A'"]]
+        );
+        assert!(matches!(
+            run_err(&mut memory, "select distinct a from pairs order by b"),
+            MemoryError::OrderByNotInDistinct
+        ));
+    }
+
+    #[test]
+    fn test_distinct_on() {
+        let mut memory = Memory::default();
+        employees(&mut memory);
+        assert_eq!(
+            select(
+                &mut memory,
+                "select distinct on (dept) dept, name, salary from employees order by dept, salary desc"
+            ),
+            vec![
+                vec!["'eng'", "'ann'", "100"],
+                vec!["'ops'", "'cid'", "50"],
+                vec!["null", "'eve'", "null"],
+            ]
+        );
+        assert_eq!(
+            select(&mut memory, "select distinct on (salary) name from employees where dept = 'eng'"),
+            vec![vec!["'ann'"], vec!["'bob'"]]
+        );
+        assert_eq!(
+            select(
+                &mut memory,
+                "select distinct on (count(*)) dept, count(*) from employees group by dept order by count(*)"
+            ),
+            vec![vec!["'ops'", "1"], vec!["'eng'", "3"]]
+        );
+        assert_eq!(
+            select(
+                &mut memory,
+                "select distinct on (dept, name) dept, name from employees order by name, dept, salary limit 2"
+            ),
+            vec![vec!["'eng'", "'ann'"], vec!["'eng'", "'bob'"]]
+        );
+
+        // the row kept for each value has to be the first in the order by.
+        assert!(matches!(
+            run_err(&mut memory, "select distinct on (dept) dept, name from employees order by salary"),
+            MemoryError::DistinctOnNotInOrderBy
+        ));
+        assert!(matches!(
+            run_err(&mut memory, "select distinct on (dept, name) name from employees order by dept, salary, name"),
+            MemoryError::DistinctOnNotInOrderBy
+        ));
+    }
+
+    #[test]
+    fn test_case() {
+        let mut memory = Memory::default();
+        employees(&mut memory);
+        assert_eq!(
+            select(
+                &mut memory,
+                "select name, case when salary >= 100 then 'high' when salary >= 80 then 'mid' else 'low' end \
+                 from employees"
+            ),
+            vec![
+                vec!["'ann'", "'high'"],
+                vec!["'bob'", "'mid'"],
+                vec!["'cid'", "'low'"],
+                vec!["'dan'", "'mid'"],
+                vec!["'eve'", "'low'"],
+            ]
+        );
+        assert_eq!(
+            select(&mut memory, "select case dept when 'eng' then 1 when 'ops' then 2 end from employees"),
+            vec![vec!["1"], vec!["1"], vec!["2"], vec!["1"], vec!["null"]]
+        );
+        assert_eq!(
+            select(
+                &mut memory,
+                "select dept, sum(case when salary > 60 then 1 else 0 end) from employees \
+                 where dept = 'eng' group by dept"
+            ),
+            vec![vec!["'eng'", "3"]]
+        );
+
+        // branches that are not taken are not evaluated.
+        assert_eq!(
+            select(&mut memory, "select case when 1 = 0 then 1 / 0 else 1 end from employees where name = 'ann'"),
+            vec![vec!["1"]]
+        );
+
+        assert!(matches!(
+            run_err(&mut memory, "select case when true then 1 else 'one' end from employees"),
+            MemoryError::IncompatibleTypes(_, CellType::Int, CellType::Text)
+        ));
+    }
+
+    #[test]
+    fn test_scalar_functions() {
+        let mut memory = Memory::default();
+        employees(&mut memory);
+        assert_eq!(
+            select(&mut memory, "select coalesce(dept, 'none'), nullif(salary, 80) from employees where name > 'c'"),
+            vec![vec!["'ops'", "50"], vec!["'eng'", "null"], vec!["'none'", "null"]]
+        );
+        assert_eq!(
+            select(&mut memory, "select greatest(1, null, 3, 2), least(null, 2.5, 3), greatest(null) from employees \
+                                 where name = 'ann'"),
+            vec![vec!["3", "2.5", "null"]]
+        );
+        assert_eq!(
+            select(&mut memory, "select coalesce(1, 1 / 0) from employees where name = 'ann'"),
+            vec![vec!["1"]]
+        );
+        assert!(matches!(
+            run_err(&mut memory, "select nullif(1) from employees"),
+            MemoryError::InvalidArguments(_)
+        ));
+        assert!(matches!(
+            run_err(&mut memory, "select coalesce(name, 1) from employees"),
+            MemoryError::IncompatibleTypes(_, CellType::Text, CellType::Int)
+        ));
+        assert!(matches!(
+            run_err(&mut memory, "select nvl(name, 'x') from employees"),
+            MemoryError::FunctionNotFound(_)
+        ));
+    }
+
+    #[test]
+    fn test_cast() {
+        let mut memory = Memory::default();
+        employees(&mut memory);
+        assert_eq!(
+            select(
+                &mut memory,
+                "select cast(salary as text), salary::float / 3, cast('42' as int) + 1, ' 2.5 '::float, \
+                 cast(2.5 as int), 'yes'::bool, cast(salary as bool) from employees where name = 'ann'"
+            ),
+            vec![vec!["'100'", "33.333333333333336", "43", "2.5", "3", "true", "true"]]
+        );
+        assert_eq!(
+            select(&mut memory, "select name from employees where salary::text = '80' order by name"),
+            vec![vec!["'bob'"], vec!["'dan'"]]
+        );
+        assert_eq!(
+            select(&mut memory, "select cast(salary as int)::text from employees where name = 'eve'"),
+            vec![vec!["null"]]
+        );
+
+        let err = run_err(&mut memory, "select cast(name as int) from employees");
+        assert!(matches!(err, MemoryError::InvalidConversion(_, CellType::Int)));
+        assert_eq!(err.to_string(), "cannot convert 'ann' to int");
+        assert!(matches!(
+            run_err(&mut memory, "select cast(1.5 as bool) from employees"),
+            MemoryError::InvalidConversion(_, CellType::Bool)
+        ));
+        assert!(matches!(
+            run_err(&mut memory, "select name from employees where salary = '100'"),
+            MemoryError::TypeMismatch(_)
+        ));
+    }
+
+    #[test]
+    fn test_implicit_coercion() {
+        let mut memory = Memory::default();
+        run(&mut memory, "create table measures (id int, amount float, label text, flag bool)");
+        run(&mut memory, "insert into measures values (2.6, 3, 4.5, 'true'::bool)");
+        run(&mut memory, "insert into measures (id, label) values (-1.5, 7)");
+        assert_eq!(
+            select(&mut memory, "select id, amount, label, flag from measures"),
+            vec![vec!["3", "3", "'4.5'", "true"], vec!["-2", "null", "'7'", "null"]]
+        );
+
+        run(&mut memory, "update measures set amount = id, label = flag where id = 3");
+        assert_eq!(
+            select(&mut memory, "select amount / 2, label from measures where id = 3"),
+            vec![vec!["1.5", "'true'"]]
+        );
+
+        let err = run_err(&mut memory, "insert into measures (id) values ('1')");
+        assert_eq!(err.to_string(), "cannot convert '1' to int");
+        assert!(matches!(
+            run_err(&mut memory, "insert into measures (flag) values (1)"),
+            MemoryError::InvalidConversion(_, CellType::Bool)
+        ));
+        assert!(matches!(
+            run_err(&mut memory, "update measures set amount = label"),
+            MemoryError::InvalidConversion(_, CellType::Float)
+        ));
+        assert_eq!(select(&mut memory, "select count(*) from measures"), vec![vec!["2"]]);
+    }
+
+    #[test]
+    fn test_like() {
+        let mut memory = Memory::default();
+        employees(&mut memory);
+        assert_eq!(
+            select(&mut memory, "select name from employees where name like '_a%' or name like '%e'"),
+            vec![vec!["'dan'"], vec!["'eve'"]]
+        );
+        assert_eq!(
+            select(&mut memory, "select name from employees where name not like '%n%' and dept like 'e__'"),
+            vec![vec!["'bob'"]]
+        );
+        assert_eq!(
+            select(&mut memory, "select name, dept ilike 'ENG' from employees where name ilike 'A%' or name = 'eve'"),
+            vec![vec!["'ann'", "true"], vec!["'eve'", "null"]]
+        );
+
+        run(&mut memory, "create table files (name text)");
+        run(&mut memory, "insert into files values ('100%_done')");
+        run(&mut memory, "insert into files values ('100 done')");
+        run(&mut memory, "insert into files values ('a\\b')");
+        assert_eq!(
+            select(&mut memory, "select name from files where name like '%\\%\\_%'"),
+            vec![vec!["'100%_done'"]]
+        );
+        assert_eq!(
+            select(&mut memory, "select name from files where name like '%!%%' escape '!'"),
+            vec![vec!["'100%_done'"]]
+        );
+        assert_eq!(
+            select(&mut memory, "select name from files where name like 'a\\b' escape ''"),
+            vec![vec!["'a\\b'"]]
+        );
+
+        let err = run_err(&mut memory, "select name from files where name like 'a\\'");
+        assert!(matches!(err, MemoryError::InvalidPattern(_, _)));
+        assert!(matches!(
+            run_err(&mut memory, "select name from files where name like 'a' escape 'ab'"),
+            MemoryError::InvalidPattern(_, _)
+        ));
+        assert!(matches!(
+            run_err(&mut memory, "select name from employees where salary like '1%'"),
+            MemoryError::TypeMismatch(_)
+        ));
+    }
+
+    #[test]
+    fn test_similar_to() {
+        let mut memory = Memory::default();
+        run(&mut memory, "create table users (email text)");
+        for email in ["ann@example.com", "bob.smith@example.org", "cid@mail.example.com", "not an email"] {
+            run(&mut memory, &format!("insert into users values ('{}')", email));
+        }
+        assert_eq!(
+            select(&mut memory, "select email from users where email similar to '[a-z.]+@example.(com|org)'"),
+            vec![vec!["'ann@example.com'"], vec!["'bob.smith@example.org'"]]
+        );
+        assert_eq!(
+            select(&mut memory, "select email from users where email not similar to '%@%'"),
+            vec![vec!["'not an email'"]]
+        );
+        assert_eq!(
+            select(&mut memory, "select email from users where email similar to '[^@ ]{3}@(mail.)?example.com'"),
+            vec![vec!["'ann@example.com'"], vec!["'cid@mail.example.com'"]]
+        );
+        // the whole text has to match, and `.` is not a wildcard.
+        assert_eq!(
+            select(
+                &mut memory,
+                "select count(*) from users where email similar to 'ann' or email similar to 'not.an.email'"
+            ),
+            vec![vec!["0"]]
+        );
+        assert!(matches!(
+            run_err(&mut memory, "select email from users where email similar to '(a|b'"),
+            MemoryError::InvalidPattern(_, _)
+        ));
+        assert!(matches!(
+            run_err(&mut memory, "select email from users where email similar to 'a{3,1}'"),
+            MemoryError::InvalidPattern(_, _)
+        ));
+        // nested repetitions multiply, their states are limited together.
+        assert!(matches!(
+            run_err(&mut memory, "select 'b' similar to '((a?){1000}){1000}b'"),
+            MemoryError::InvalidPattern(_, _)
+        ));
+        assert!(matches!(
+            run_err(&mut memory, "select 'b' similar to '((a{1000}){1000}){1000}'"),
+            MemoryError::InvalidPattern(_, _)
+        ));
+        // a long chain of optional parts is matched without recursing through it.
+        assert_eq!(select(&mut memory, "select 'b' similar to '((a?){200}){200}b'"), [["true"]]);
+    }
+
+    #[test]
+    fn test_in_list() {
+        let mut memory = Memory::default();
+        employees(&mut memory);
+        assert_eq!(
+            select(&mut memory, "select name from employees where salary in (50, 100.0, 70 + 10) order by name"),
+            vec![vec!["'ann'"], vec!["'bob'"], vec!["'cid'"], vec!["'dan'"]]
+        );
+        assert_eq!(
+            select(&mut memory, "select name from employees where dept not in ('eng')"),
+            vec![vec!["'cid'"]]
+        );
+        // a null in the list makes `not in` unknown for the values that aren't in it.
+        assert_eq!(
+            select(
+                &mut memory,
+                "select name, salary in (100, null), salary not in (100, null) from employees where dept = 'eng'"
+            ),
+            vec![
+                vec!["'ann'", "true", "false"],
+                vec!["'bob'", "null", "null"],
+                vec!["'dan'", "null", "null"],
+            ]
+        );
+        assert!(matches!(
+            run_err(&mut memory, "select name from employees where name in (1, 2)"),
+            MemoryError::TypeMismatch(_)
+        ));
+    }
+
+    #[test]
+    fn test_between() {
+        let mut memory = Memory::default();
+        employees(&mut memory);
+        assert_eq!(
+            select(&mut memory, "select name from employees where salary between 50 and 80 and dept = 'eng'"),
+            vec![vec!["'bob'"], vec!["'dan'"]]
+        );
+        assert_eq!(
+            select(&mut memory, "select name from employees where salary not between 60 and 90"),
+            vec![vec!["'ann'"], vec!["'cid'"]]
+        );
+        assert_eq!(
+            select(&mut memory, "select name from employees where name between 'b' and 'd'"),
+            vec![vec!["'bob'"], vec!["'cid'"]]
+        );
+        // a null bound only makes the result unknown when the other bound doesn't decide it.
+        assert_eq!(
+            select(
+                &mut memory,
+                "select salary between null and 90, salary between 90 and null from employees where name = 'cid'"
+            ),
+            vec![vec!["null", "false"]]
+        );
+    }
+
+    #[test]
+    fn test_is_null_and_is_distinct_from() {
+        let mut memory = Memory::default();
+        employees(&mut memory);
+        assert_eq!(select(&mut memory, "select name from employees where dept is null"), vec![vec!["'eve'"]]);
+        assert_eq!(
+            select(&mut memory, "select count(*) from employees where salary is not null"),
+            vec![vec!["4"]]
+        );
+        assert_eq!(
+            select(
+                &mut memory,
+                "select name, salary = 80 is null from employees where name < 'c' or name = 'eve'"
+            ),
+            vec![vec!["'ann'", "false"], vec!["'bob'", "false"], vec!["'eve'", "true"]]
+        );
+        assert_eq!(
+            select(
+                &mut memory,
+                "select name, salary is distinct from 80, dept is not distinct from null from employees \
+                 where name in ('ann', 'bob', 'eve')"
+            ),
+            vec![
+                vec!["'ann'", "true", "false"],
+                vec!["'bob'", "false", "false"],
+                vec!["'eve'", "true", "true"],
+            ]
+        );
+        assert_eq!(
+            select(
+                &mut memory,
+                "select e.name from employees e join employees f on e.dept is not distinct from f.dept \
+                 where e.name != f.name and e.salary is null or f.name = 'cid' and e.name = 'cid'"
+            ),
+            vec![vec!["'cid'"]]
+        );
+    }
+
+    #[test]
+    fn test_insert_select() {
+        let mut memory = Memory::default();
+        employees(&mut memory);
+        run(&mut memory, "create table payroll (id int primary key, name text, amount float)");
+        run(
+            &mut memory,
+            "insert into payroll (name, amount, id) \
+             select name, salary, salary from employees where dept = 'ops' or name = 'ann'",
+        );
+        assert_eq!(
+            select(&mut memory, "select id, name, amount / 3 from payroll order by id"),
+            vec![vec!["50", "'cid'", "16.666666666666668"], vec!["100", "'ann'", "33.333333333333336"]]
+        );
+
+        // a table can be copied into itself, the query doesn't see the rows it inserts.
+        run(&mut memory, "insert into payroll select id + 1, name, amount from payroll");
+        assert_eq!(select(&mut memory, "select count(*) from payroll"), vec![vec!["4"]]);
+
+        // the rows are stored all together or not at all.
+        assert!(matches!(
+            run_err(
+                &mut memory,
+                "insert into payroll (id, name) select salary, name from employees where dept = 'eng'"
+            ),
+            MemoryError::UniqueViolation(_, _)
+        ));
+        assert!(matches!(
+            run_err(&mut memory, "insert into payroll (id, name) select name, name from employees"),
+            MemoryError::InvalidConversion(_, CellType::Int)
+        ));
+        assert!(matches!(
+            run_err(&mut memory, "insert into payroll (id) select salary, name from employees"),
+            MemoryError::WrongNumberOfValues(1, 2)
+        ));
+        assert_eq!(select(&mut memory, "select count(*) from payroll"), vec![vec!["4"]]);
+    }
+
+    #[test]
+    fn test_create_table_as() {
+        let mut memory = Memory::default();
+        employees(&mut memory);
+        run(
+            &mut memory,
+            "create table dept_stats as \
+             select dept, count(*) as employees, avg(salary) as average from employees group by dept",
+        );
+        assert_eq!(
+            select(&mut memory, "select dept, employees, average from dept_stats order by employees desc, dept"),
+            vec![vec!["'eng'", "3", "86.66666666666667"], vec!["'ops'", "1", "50"], vec!["null", "1", "null"]]
+        );
+
+        // the types of the query's columns are the types of the new table's columns.
+        run(&mut memory, "insert into dept_stats values ('hr', 2.4, 7)");
+        assert_eq!(
+            select(&mut memory, "select employees, average from dept_stats where dept = 'hr'"),
+            vec![vec!["2", "7"]]
+        );
+
+        assert!(matches!(
+            run_err(&mut memory, "create table dept_stats as select name from employees"),
+            MemoryError::TableAlreadyExists(_)
+        ));
+        assert!(matches!(
+            run_err(&mut memory, "create table pairs as select e.name, f.name from employees e, employees f"),
+            MemoryError::DuplicateColumn(_)
+        ));
+        assert!(matches!(
+            run_err(&mut memory, "select name from pairs"),
+            MemoryError::TableNotFound(_)
+        ));
+    }
+
+    #[test]
+    fn test_views() {
+        let mut memory = Memory::default();
+        departments(&mut memory);
+        run(
+            &mut memory,
+            "create view engineers as select name, salary from employees where dept = 'eng'",
+        );
+        run(
+            &mut memory,
+            "create view dept_sizes (dept, size) as select dept, count(*) from employees group by dept",
+        );
+        assert_eq!(
+            select(&mut memory, "select name from engineers where salary < 100 order by name"),
+            vec![vec!["'bob'"], vec!["'dan'"]]
+        );
+        assert_eq!(
+            select(
+                &mut memory,
+                "select d.dept, s.size from depts d join dept_sizes s on s.dept = d.dept order by d.floor"
+            ),
+            vec![vec!["'eng'", "3"], vec!["'ops'", "1"]]
+        );
+
+        // the view reads the table as it is when it is queried.
+        run(&mut memory, "insert into employees values ('fay', 'eng', 90)");
+        assert_eq!(select(&mut memory, "select count(*) from engineers"), vec![vec!["4"]]);
+
+        // views can read views, and a with clause of the query doesn't change what the view reads.
+        run(&mut memory, "create view rich_engineers as select name from engineers where salary > 85");
+        assert_eq!(
+            select(
+                &mut memory,
+                "with employees as (select name, dept, salary + 1000 as salary from employees) \
+                 select name from rich_engineers order by name"
+            ),
+            vec![vec!["'ann'"], vec!["'fay'"]]
+        );
+
+        // a view that only filters and projects is read a row at a time, the limit of the query
+        // reading it stops it too. so do subqueries and views of views.
+        run(&mut memory, "create table t (a int)");
+        run(&mut memory, "insert into t values (1)");
+        run(&mut memory, "insert into t values (0)");
+        run(&mut memory, "create view inverses as select 1 / a as x from t");
+        run(&mut memory, "create view positive_inverses as select x from inverses where x > 0");
+        assert_eq!(select(&mut memory, "select x from inverses limit 1"), [["1"]]);
+        assert_eq!(select(&mut memory, "select x from positive_inverses limit 1"), [["1"]]);
+        assert_eq!(select(&mut memory, "select x from (select 1 / a as x from t) s limit 1"), [["1"]]);
+        assert!(matches!(
+            run_err(&mut memory, "select x from inverses limit 2"),
+            MemoryError::DivisionByZero
+        ));
+        let query = "explain analyze select x from inverses limit 1";
+        let lines: Vec<String> = select(&mut memory, query).into_iter().map(|row| row[0].clone()).collect();
+        assert!(lines[4].starts_with("'        ->  View Scan on inverses  (actual rows=1 loops=1"), "{}", lines[4]);
+        assert!(lines[5].starts_with("'              ->  Project  (actual rows=1 loops=1"), "{}", lines[5]);
+    }
+
+    #[test]
+    fn test_view_errors() {
+        let mut memory = Memory::default();
+        departments(&mut memory);
+        assert!(matches!(
+            run_err(&mut memory, "create view v as select name from missing"),
+            MemoryError::TableNotFound(_)
+        ));
+        assert!(matches!(
+            run_err(&mut memory, "create view v as select missing from employees"),
+            MemoryError::ColumnNotFound(_)
+        ));
+        assert!(matches!(
+            run_err(&mut memory, "create view v as select name from employees where missing = 1"),
+            MemoryError::ColumnNotFound(_)
+        ));
+        assert!(matches!(
+            run_err(&mut memory, "create view v (a, b) as select name from employees"),
+            MemoryError::TooManyColumnNames(_, 1, 2)
+        ));
+        assert!(matches!(
+            run_err(&mut memory, "create view employees as select 1 from depts"),
+            MemoryError::TableAlreadyExists(_)
+        ));
+
+        run(&mut memory, "create view names as select name from employees");
+        run(
+            &mut memory,
+            "create view floors as select floor from depts where dept in (select dept from employees)",
+        );
+        run(&mut memory, "create view short_names as select name from names where name < 'c'");
+        assert!(matches!(
+            run_err(&mut memory, "create table names (name text)"),
+            MemoryError::ViewAlreadyExists(_)
+        ));
+        assert!(matches!(
+            run_err(&mut memory, "insert into names values ('x')"),
+            MemoryError::TableNotFound(_)
+        ));
+        assert!(matches!(run_err(&mut memory, "drop view missing"), MemoryError::ViewNotFound(_)));
+        assert!(matches!(run_err(&mut memory, "drop table names"), MemoryError::WrongObjectKind(_, _)));
+
+        let err = run_err(&mut memory, "drop table employees");
+        assert!(matches!(
+            &err,
+            MemoryError::DependentViews(_, views) if views == &["floors", "names", "short_names"]
+        ));
+        assert!(matches!(
+            run_err(&mut memory, "drop view names restrict"),
+            MemoryError::DependentViews(_, _)
+        ));
+
+        run(&mut memory, "drop view short_names");
+        run(&mut memory, "drop table employees cascade");
+        assert!(matches!(run_err(&mut memory, "select name from names"), MemoryError::TableNotFound(_)));
+        assert!(matches!(run_err(&mut memory, "select floor from floors"), MemoryError::TableNotFound(_)));
+        run(&mut memory, "drop table depts");
+        assert!(matches!(run_err(&mut memory, "select dept from depts"), MemoryError::TableNotFound(_)));
+    }
+
+    #[test]
+    fn test_if_exists() {
+        let mut memory = Memory::default();
+        departments(&mut memory);
+        run(&mut memory, "create view floors as select name, floor from employees join depts using (dept)");
+
+        // a skipped statement says why, and leaves the existing table or view as it was.
+        let skipped = |memory: &mut Memory, query: &str| {
+            let result = memory.run_query(query).expect(query).expect("no notice");
+            result.rows[0][0].value.to_string()
+        };
+        assert_eq!(
+            skipped(&mut memory, "create table if not exists depts (name text)"),
+            "'table 'depts' already exists, skipping'"
+        );
+        assert_eq!(
+            skipped(&mut memory, "create table if not exists floors as select name from employees"),
+            "'view 'floors' already exists, skipping'"
+        );
+        assert_eq!(
+            skipped(&mut memory, "create view if not exists depts as select name from employees"),
+            "'table 'depts' already exists, skipping'"
+        );
+        assert_eq!(select(&mut memory, "select count(floor) from depts"), [["3"]]);
+        assert_eq!(
+            skipped(&mut memory, "drop table if exists missing"),
+            "'table 'missing' not found, skipping'"
+        );
+        assert_eq!(
+            skipped(&mut memory, "drop view if exists missing"),
+            "'view 'missing' not found, skipping'"
+        );
+        assert_eq!(
+            run_err(&mut memory, "drop view if exists depts").to_string(),
+            "'depts' is not a view"
+        );
+        assert_eq!(run_err(&mut memory, "drop table if exists floors").to_string(), "'floors' is not a table");
+
+        // otherwise the statements run as usual.
+        assert!(memory.run_query("create table if not exists teams (name text)").unwrap().is_none());
+        run(&mut memory, "insert into teams values ('core')");
+        assert!(memory.run_query("create view if not exists team_names as select name from teams").unwrap().is_none());
+        assert_eq!(select(&mut memory, "select name from team_names"), [["'core'"]]);
+        assert!(matches!(
+            run_err(&mut memory, "drop table if exists teams"),
+            MemoryError::DependentViews(_, _)
+        ));
+        run(&mut memory, "drop table if exists teams cascade");
+        assert!(matches!(run_err(&mut memory, "select name from team_names"), MemoryError::TableNotFound(_)));
+        run(&mut memory, "drop view if exists floors");
+        assert!(matches!(run_err(&mut memory, "drop view floors"), MemoryError::ViewNotFound(_)));
+
+        assert!(memory.run_query("create table if exists teams (name text)").is_err());
+        assert!(memory.run_query("create table if not teams (name text)").is_err());
+        assert!(memory.run_query("drop table if not exists depts").is_err());
+    }
+
+    #[test]
+    fn test_ranking_windows() {
+        let mut memory = Memory::default();
+        employees(&mut memory);
+        assert_eq!(
+            select(
+                &mut memory,
+                "select name, row_number() over (partition by dept order by salary desc), \
+                 rank() over (partition by dept order by salary desc), dense_rank() over (order by salary desc) \
+                 from employees order by name"
+            ),
+            vec![
+                vec!["'ann'", "1", "1", "2"],
+                vec!["'bob'", "2", "2", "3"],
+                vec!["'cid'", "1", "1", "4"],
+                vec!["'dan'", "3", "2", "3"],
+                vec!["'eve'", "1", "1", "1"],
+            ]
+        );
+        // the where clause runs before the windows, and the order by can sort on them.
+        assert_eq!(
+            select(
+                &mut memory,
+                "select name from employees where salary > 60 order by row_number() over (order by salary) desc"
+            ),
+            vec![vec!["'ann'"], vec!["'dan'"], vec!["'bob'"]]
+        );
+    }
+
+    #[test]
+    fn test_offset_windows() {
+        let mut memory = Memory::default();
+        employees(&mut memory);
+        assert_eq!(
+            select(
+                &mut memory,
+                "select name, lag(name) over (order by name), lead(salary, 2, 0) over (order by name), \
+                 first_value(name) over (partition by dept order by name) from employees order by name"
+            ),
+            vec![
+                vec!["'ann'", "null", "50", "'ann'"],
+                vec!["'bob'", "'ann'", "80", "'ann'"],
+                vec!["'cid'", "'bob'", "null", "'cid'"],
+                vec!["'dan'", "'cid'", "0", "'ann'"],
+                vec!["'eve'", "'dan'", "0", "'eve'"],
+            ]
+        );
+    }
+
+    #[test]
+    fn test_aggregate_windows() {
+        let mut memory = Memory::default();
+        employees(&mut memory);
+        // without a frame the rows sorting like the current one are part of it.
+        assert_eq!(
+            select(
+                &mut memory,
+                "select name, sum(salary) over (order by salary), count(*) over (), \
+                 max(salary) over (partition by dept) from employees order by name"
+            ),
+            vec![
+                vec!["'ann'", "310", "5", "100"],
+                vec!["'bob'", "210", "5", "100"],
+                vec!["'cid'", "50", "5", "50"],
+                vec!["'dan'", "210", "5", "100"],
+                vec!["'eve'", "310", "5", "null"],
+            ]
+        );
+        assert_eq!(
+            select(
+                &mut memory,
+                "select name, sum(salary) over (order by name rows between 1 preceding and current row), \
+                 avg(salary) over (order by name rows between current row and 1 following), \
+                 sum(salary) over (order by name rows 2 preceding), \
+                 count(salary) over (order by name rows between 1 following and unbounded following) \
+                 from employees order by name"
+            ),
+            vec![
+                vec!["'ann'", "100", "90", "100", "3"],
+                vec!["'bob'", "180", "65", "180", "2"],
+                vec!["'cid'", "130", "65", "230", "1"],
+                vec!["'dan'", "130", "80", "210", "0"],
+                vec!["'eve'", "80", "null", "130", "0"],
+            ]
+        );
+        assert_eq!(
+            select(
+                &mut memory,
+                "select dept, sum(salary), rank() over (order by sum(salary) desc) from employees \
+                 group by dept order by dept"
+            ),
+            vec![vec!["'eng'", "260", "2"], vec!["'ops'", "50", "3"], vec!["null", "null", "1"]]
+        );
+    }
+
+    #[test]
+    fn test_window_errors() {
+        let mut memory = Memory::default();
+        employees(&mut memory);
+        assert!(matches!(
+            run_err(&mut memory, "select name from employees where rank() over () = 1"),
+            MemoryError::WindowNotAllowed(_)
+        ));
+        assert!(matches!(
+            run_err(&mut memory, "select sum(rank() over ()) over () from employees"),
+            MemoryError::WindowNotAllowed(_)
+        ));
+        assert!(matches!(
+            run_err(&mut memory, "select coalesce(name) over () from employees"),
+            MemoryError::NotAWindowFunction(_)
+        ));
+        assert!(matches!(
+            run_err(&mut memory, "select rank(name) over () from employees"),
+            MemoryError::InvalidArguments(_)
+        ));
+        assert!(matches!(
+            run_err(&mut memory, "select lag(name, 'x') over () from employees"),
+            MemoryError::InvalidArguments(_)
+        ));
+        assert!(matches!(
+            run_err(&mut memory, "select name, sum(salary) over () from employees group by name"),
+            MemoryError::ColumnNotGrouped(_)
+        ));
+        assert!(matches!(
+            run_err(&mut memory, "select sum(salary) over (rows -1 preceding) from employees"),
+            MemoryError::InvalidRowCount(_, _)
+        ));
+        assert!(memory.run_query("select rank() over (rows unbounded following) from employees").is_err());
+    }
+
+    #[test]
+    fn test_insert_on_conflict() {
+        let mut memory = Memory::default();
+        run(&mut memory, "create table counters (name text primary key, hits int default 0, note text unique)");
+        run(&mut memory, "insert into counters values ('a', 1, 'x')");
+        run(&mut memory, "insert into counters values ('a', 5, 'y') on conflict do nothing");
+        run(&mut memory, "insert into counters values ('b', 5, 'x') on conflict do nothing");
+        run(
+            &mut memory,
+            "insert into counters values ('a', 5, 'y') on conflict (name) \
+             do update set hits = counters.hits + excluded.hits",
+        );
+        run(
+            &mut memory,
+            "insert into counters (name, note) values ('b', 'z') on conflict (name) do update set hits = 100",
+        );
+        assert_eq!(
+            select(&mut memory, "select name, hits, note from counters order by name"),
+            vec![vec!["'a'", "6", "'x'"], vec!["'b'", "0", "'z'"]]
+        );
+
+        run(
+            &mut memory,
+            "insert into counters select name, 1, note from counters on conflict (name) \
+             do update set hits = counters.hits + excluded.hits where counters.hits > 1",
+        );
+        assert_eq!(
+            select(&mut memory, "select name, hits from counters order by name"),
+            vec![vec!["'a'", "7"], vec!["'b'", "0"]]
+        );
+    }
+
+    #[test]
+    fn test_insert_on_conflict_errors() {
+        let mut memory = Memory::default();
+        run(&mut memory, "create table counters (name text primary key, hits int default 0, note text unique)");
+        run(&mut memory, "insert into counters values ('a', 1, 'x')");
+        run(&mut memory, "insert into counters values ('b', 1, 'y')");
+
+        // only the conflict target's key is handled.
+        assert!(matches!(
+            run_err(&mut memory, "insert into counters values ('c', 1, 'x') on conflict (name) do nothing"),
+            MemoryError::UniqueViolation(_, _)
+        ));
+        assert!(matches!(
+            run_err(&mut memory, "insert into counters values ('c', 1, 'z') on conflict (hits) do nothing"),
+            MemoryError::ConflictKeyNotFound(_)
+        ));
+        assert!(matches!(
+            run_err(
+                &mut memory,
+                "insert into counters values ('a', 1, 'x') on conflict (name) do update set hits = hits + 1"
+            ),
+            MemoryError::AmbiguousColumn(_)
+        ));
+        assert!(matches!(
+            run_err(
+                &mut memory,
+                "insert into counters select 'c', hits, null from counters on conflict (name) do update set hits = 0"
+            ),
+            MemoryError::RowUpdatedTwice(_)
+        ));
+        // the update of 'a' is undone when the one of 'b' fails.
+        assert!(matches!(
+            run_err(
+                &mut memory,
+                "insert into counters select name, hits, null from counters on conflict (name) \
+                 do update set note = 'w'"
+            ),
+            MemoryError::UniqueViolation(_, _)
+        ));
+        assert_eq!(
+            select(&mut memory, "select name, hits, note from counters order by name"),
+            vec![vec!["'a'", "1", "'x'"], vec!["'b'", "1", "'y'"]]
+        );
+        assert!(memory
+            .run_query("insert into counters values ('a', 1, 'x') on conflict do update set hits = 0")
+            .is_err());
+    }
+
+    #[test]
+    fn test_delete() {
+        let mut memory = Memory::default();
+        employees(&mut memory);
+        run(&mut memory, "delete from employees where salary = 80");
+        assert_eq!(
+            select(&mut memory, "select name from employees order by name"),
+            vec![vec!["'ann'"], vec!["'cid'"], vec!["'eve'"]]
+        );
+        run(&mut memory, "delete from employees where salary < (select max(salary) from employees)");
+        assert_eq!(
+            select(&mut memory, "select name from employees order by name"),
+            vec![vec!["'ann'"], vec!["'eve'"]]
+        );
+        run(&mut memory, "delete from employees");
+        assert!(select(&mut memory, "select name from employees").is_empty());
+        assert!(matches!(run_err(&mut memory, "delete from missing"), MemoryError::TableNotFound(_)));
+    }
+
+    #[test]
+    fn test_returning() {
+        let mut memory = Memory::default();
+        run(&mut memory, "create table users (id int primary key, name text, visits int default 0)");
+        assert!(memory.run_query("insert into users values (1, 'ann', 3)").unwrap().is_none());
+        assert_eq!(
+            select(&mut memory, "insert into users (id, name) values (2, 'bob') returning *"),
+            vec![vec!["2", "'bob'", "0"]]
+        );
+        let result = memory
+            .run_query("insert into users (id, name) select id + 10, name from users returning id, visits + 1 as later")
+            .unwrap()
+            .unwrap();
+        let names: Vec<&str> = result.columns.iter().map(|column| column.name.as_str()).collect();
+        assert_eq!(names, ["id", "later"]);
+        assert_eq!(result.rows.len(), 2);
+
+        // an upsert returns the rows it inserted or updated, not the ones it left alone.
+        assert_eq!(
+            select(
+                &mut memory,
+                "insert into users select id, name, 1 from users where id < 3 on conflict (id) \
+                 do update set visits = users.visits + 1 where users.visits > 0 returning id, visits"
+            ),
+            vec![vec!["1", "4"]]
+        );
+        assert_eq!(
+            select(&mut memory, "update users set name = 'cid' where id > 10 returning id, users.name"),
+            vec![vec!["11", "'cid'"], vec!["12", "'cid'"]]
+        );
+        assert_eq!(
+            select(&mut memory, "delete from users where id > 1 returning id, name"),
+            vec![vec!["2", "'bob'"], vec!["11", "'cid'"], vec!["12", "'cid'"]]
+        );
+        assert!(select(&mut memory, "delete from users where id > 1 returning *").is_empty());
+        assert!(matches!(
+            run_err(&mut memory, "update users set visits = 0 returning missing"),
+            MemoryError::ColumnNotFound(_)
+        ));
+
+        // a returning clause that fails leaves the tables as they were, including the rows a cascade changed.
+        run(&mut memory, "create table logins (user_id int references users on delete cascade)");
+        run(&mut memory, "insert into logins values (1)");
+        assert!(matches!(
+            run_err(&mut memory, "insert into users values (5, 'eve', 0) returning count(*)"),
+            MemoryError::AggregateNotAllowed(_)
+        ));
+        assert!(matches!(
+            run_err(&mut memory, "insert into users values (5, 'eve', 0) returning 1 / 0"),
+            MemoryError::DivisionByZero
+        ));
+        assert!(matches!(
+            run_err(&mut memory, "update users set visits = 100 returning rank() over ()"),
+            MemoryError::WindowNotAllowed(_)
+        ));
+        assert!(matches!(
+            run_err(&mut memory, "update users set visits = 100 returning id / (visits - 100)"),
+            MemoryError::DivisionByZero
+        ));
+        assert!(matches!(
+            run_err(&mut memory, "delete from users returning nope"),
+            MemoryError::ColumnNotFound(_)
+        ));
+        assert!(matches!(
+            run_err(&mut memory, "delete from users returning id / 0"),
+            MemoryError::DivisionByZero
+        ));
+        assert_eq!(select(&mut memory, "select id, name, visits from users"), [["1", "'ann'", "4"]]);
+        assert_eq!(select(&mut memory, "select user_id from logins"), [["1"]]);
+    }
+    #[test]
+    fn test_explain() {
+        let mut memory = Memory::default();
+        departments(&mut memory);
+        let query = "explain select dept, count(*) as total from employees where salary > 10 \
+            group by dept having count(*) > 1 order by dept desc limit 1";
+        let lines: Vec<String> = select(&mut memory, query).into_iter().map(|row| row[0].clone()).collect();
+        assert_eq!(
+            lines,
+            [
+                "'Limit'",
+                "'  Count: 1'",
+                "'  ->  Sort'",
+                "'        Sort Key: dept desc'",
+                "'        ->  Project'",
+                "'              Output: dept, count(*) as total'",
+                "'              Filter: (count(*) > 1)'",
+                "'              ->  Aggregate'",
+                "'                    Group Key: dept'",
+                "'                    Filter: (salary > 10)'",
+                "'                    ->  Seq Scan on employees'",
+            ]
+        );
+
+        let query = "explain select e.name, (select floor from depts d where d.dept = e.dept) \
+            from employees e left join depts using (dept)";
+        let lines: Vec<String> = select(&mut memory, query).into_iter().map(|row| row[0].clone()).collect();
+        assert_eq!(
+            lines,
+            [
+                "'Project'",
+                "'  Output: e.name, (subquery)'",
+                "'  ->  Nested Loop'",
+                "'        Join Type: Left'",
+                "'        Using: dept'",
+                "'        ->  Seq Scan on employees e'",
+                "'        ->  Seq Scan on depts'",
+                "'  SubPlan'",
+                "'    ->  Project'",
+                "'          Output: floor'",
+                "'          Filter: (d.dept = e.dept)'",
+                "'          ->  Seq Scan on depts d'",
+            ]
+        );
+
+        // the json form is a single row.
+        let query = "explain (format json) select name from employees union all select dept from depts";
+        let rows = select(&mut memory, query);
+        assert_eq!(rows.len(), 1);
+        assert!(rows[0][0].contains(r#""Node Type": "Union All""#));
+        assert!(rows[0][0].contains(r#""Relation Name": "depts""#));
+        assert!(!rows[0][0].contains("Actual Rows"));
+
+        // the query is checked, but not run.
+        assert!(matches!(
+            run_err(&mut memory, "explain select name from missing"),
+            MemoryError::TableNotFound(_)
+        ));
+        assert!(memory.run_query("explain (format yaml) select name from employees").is_err());
+        assert!(memory.run_query("explain create table t (a int)").is_err());
+    }
+
+    #[test]
+    fn test_explain_writes() {
+        let mut memory = Memory::default();
+        departments(&mut memory);
+        let explain = |memory: &mut Memory, query: &str| -> Vec<String> {
+            select(memory, query).into_iter().map(|row| row[0].clone()).collect()
+        };
+        assert_eq!(
+            explain(&mut memory, "explain insert into depts values ('qa', 4) returning dept"),
+            ["'Insert on depts'", "'  Returning: dept'", "'  ->  Result'"]
+        );
+        assert_eq!(
+            explain(
+                &mut memory,
+                "explain insert into depts select distinct dept, 0 from employees where dept is not null"
+            ),
+            [
+                "'Insert on depts'",
+                "'  ->  Unique'",
+                "'        ->  Project'",
+                "'              Output: dept, 0'",
+                "'              Filter: dept is not null'",
+                "'              ->  Seq Scan on employees'",
+            ]
+        );
+        assert_eq!(
+            explain(
+                &mut memory,
+                "explain update depts set floor = (select max(floor) from depts) + 1 where dept = 'hr'"
+            ),
+            [
+                "'Update on depts'",
+                "'  ->  Seq Scan on depts'",
+                "'        Filter: (dept = 'hr')'",
+                "'  SubPlan'",
+                "'    ->  Project'",
+                "'          Output: max(floor)'",
+                "'          ->  Aggregate'",
+                "'                ->  Seq Scan on depts'",
+            ]
+        );
+        assert_eq!(
+            explain(&mut memory, "explain delete from depts"),
+            ["'Delete on depts'", "'  ->  Seq Scan on depts'"]
+        );
+
+        // the statement is checked like it is when it runs.
+        assert!(matches!(
+            run_err(&mut memory, "explain delete from missing"),
+            MemoryError::TableNotFound(_)
+        ));
+        assert!(matches!(
+            run_err(&mut memory, "explain update depts set missing = 1"),
+            MemoryError::ColumnNotFound(_)
+        ));
+        assert!(matches!(
+            run_err(&mut memory, "explain delete from depts where missing > 1"),
+            MemoryError::ColumnNotFound(_)
+        ));
+        assert!(matches!(
+            run_err(&mut memory, "explain delete from depts returning count(*)"),
+            MemoryError::AggregateNotAllowed(_)
+        ));
+    }
+
+    #[test]
+    fn test_explain_analyze_writes() {
+        let mut memory = Memory::default();
+        departments(&mut memory);
+        let explain = |memory: &mut Memory, query: &str| -> Vec<String> {
+            select(memory, query).into_iter().map(|row| row[0].clone()).collect()
+        };
+        let lines = explain(&mut memory, "explain analyze delete from employees where salary > 60");
+        let expected = [
+            "'Delete on employees  (actual rows=3 loops=1",
+            "'  ->  Seq Scan on employees  (actual rows=3 loops=1",
+            "'        Filter: (salary > 60)'",
+            "'Execution Time: ",
+        ];
+        assert_eq!(lines.len(), expected.len());
+        for (line, expected) in lines.iter().zip(expected.iter()) {
+            assert!(line.starts_with(expected), "{} doesn't start with {}", line, expected);
+        }
+        let lines = explain(&mut memory, "explain analyze insert into depts values ('qa', 4)");
+        assert!(lines[0].starts_with("'Insert on depts  (actual rows=1 loops=1"));
+        assert!(lines[1].starts_with("'  ->  Result  (actual rows=1 loops=1"));
+
+        // the writes are undone, including the ones of foreign key actions and the identity values used.
+        run(&mut memory, "create table teams (id int generated always as identity primary key, name text)");
+        run(
+            &mut memory,
+            "create table members (name text, team int references teams (id) on delete cascade)",
+        );
+        run(&mut memory, "insert into teams (name) values ('red')");
+        run(&mut memory, "insert into members values ('ann', 1)");
+        explain(&mut memory, "explain analyze delete from teams");
+        explain(&mut memory, "explain analyze insert into teams (name) values ('blue')");
+        assert_eq!(select(&mut memory, "select count(*) from employees"), [["5"]]);
+        assert_eq!(select(&mut memory, "select count(*) from depts"), [["3"]]);
+        assert_eq!(select(&mut memory, "select name from members"), [["'ann'"]]);
+        run(&mut memory, "insert into teams (name) values ('blue')");
+        assert_eq!(select(&mut memory, "select id from teams where name = 'blue'"), [["2"]]);
+
+        // a failed write is undone too.
+        assert!(matches!(
+            run_err(&mut memory, "explain analyze insert into teams values (3, 'green')"),
+            MemoryError::GeneratedAlways(_)
+        ));
+        assert_eq!(select(&mut memory, "select count(*) from teams"), [["2"]]);
+    }
+
+    #[test]
+    fn test_explain_analyze() {
+        let mut memory = Memory::default();
+        departments(&mut memory);
+        let query = "explain analyze with recursive raises(pay) as (\
+                select min(salary) from employees union select pay + 10 from raises where pay < 80\
+            ) \
+            select pay, row_number() over (order by pay) from raises";
+        let lines: Vec<String> = select(&mut memory, query).into_iter().map(|row| row[0].clone()).collect();
+        let expected = [
+            "'Project  (actual rows=4 loops=1",
+            "'  Output: pay, row_number() over (order by pay)'",
+            "'  ->  WindowAgg  (actual rows=4 loops=1",
+            "'        ->  CTE Scan on raises  (actual rows=4 loops=1",
+            "'  CTE raises'",
+            "'    ->  Recursive Union  (actual rows=4 loops=1",
+            "'          ->  Project  (actual rows=1 loops=1",
+            "'                Output: min(salary)'",
+            "'                ->  Aggregate  (actual rows=1 loops=1",
+            "'                      ->  Seq Scan on employees  (actual rows=5 loops=1",
+            "'          ->  Project  (actual rows=3 loops=4",
+            "'                Output: (pay + 10)'",
+            "'                Filter: (pay < 80)'",
+            "'                ->  CTE Scan on raises  (actual rows=4 loops=4",
+            "'Execution Time: ",
+        ];
+        assert_eq!(lines.len(), expected.len());
+        for (line, expected) in lines.iter().zip(expected.iter()) {
+            assert!(line.starts_with(expected), "{} doesn't start with {}", line, expected);
+        }
+
+        // a subquery runs once for every row.
+        let query = "explain (analyze, format json) select name from employees e \
+            where exists (select floor from depts d where d.dept = e.dept)";
+        let rows = select(&mut memory, query);
+        assert!(rows[0][0].contains(r#""Actual Rows": 5,"#));
+        assert!(rows[0][0].contains(r#""Actual Loops": 5,"#));
+        assert!(rows[0][0].contains(r#""Execution Time": "#));
+
+        // analyzing runs the query, which leaves the tables as they were.
+        assert_eq!(select(&mut memory, "select count(*) from depts"), [["3"]]);
+
+        // the same subquery written twice is two subplans, each run once for every row.
+        let query = "explain analyze select name, (select max(floor) from depts), (select max(floor) from depts) \
+            from employees";
+        let lines: Vec<String> = select(&mut memory, query).into_iter().map(|row| row[0].clone()).collect();
+        let subplans: Vec<&String> = lines.iter().filter(|line| line.starts_with("'    ->  Project")).collect();
+        assert_eq!(subplans.len(), 2);
+        for line in subplans {
+            assert!(line.starts_with("'    ->  Project  (actual rows=5 loops=5"), "{}", line);
+        }
+    }
+
+    fn text(value: &str) -> CellValue {
+        CellValue::Text(value.to_string())
+    }
+
+    #[test]
+    fn test_prepared_statements() {
+        let mut memory = Memory::default();
+        departments(&mut memory);
+
+        let insert = memory.prepare("insert into employees (name, dept, salary) values ($1, $2, $3)").unwrap();
+        insert.execute(&mut memory, &[text("fay"), text("ops"), CellValue::Int(70)]).unwrap();
+        insert.execute(&mut memory, &[text("o'neil"), CellValue::Null, CellValue::Int(60)]).unwrap();
+        // the values are checked before the statement runs.
+        assert!(matches!(
+            insert.execute(&mut memory, &[text("gus"), text("ops"), text("a lot")]),
+            Err(MemoryError::InvalidParameter(3, _, CellType::Int))
+        ));
+        assert!(matches!(
+            insert.execute(&mut memory, &[text("gus")]),
+            Err(MemoryError::WrongNumberOfParameters(3, 1))
+        ));
+        // a statement that returns no rows isn't run by `query`.
+        assert!(matches!(
+            insert.query(&mut memory, &[text("gus"), text("ops"), CellValue::Int(1)]),
+            Err(MemoryError::NoRowsReturned)
+        ));
+        assert_eq!(
+            select(&mut memory, "select name, salary from employees where salary < 80 order by name"),
+            [["'cid'", "50"], ["'fay'", "70"], ["'o'neil'", "60"]]
+        );
+        let insert = memory
+            .prepare("insert into employees (name, dept, salary) values ($1, 'ops', $2) returning name, salary / 2")
+            .unwrap();
+        let result = insert.query(&mut memory, &[text("gus"), CellValue::Float(3.0)]).unwrap();
+        assert_eq!(result.rows[0][1].value.to_string(), "1");
+
+        let names = |result: crate::database::QueryResult| -> Vec<String> {
+            result.rows.iter().map(|row| row[0].value.to_string()).collect()
+        };
+        let query = memory
+            .prepare("select name from employees where salary > $1 and dept = $2 order by name limit $3")
+            .unwrap();
+        let result = query.query(&mut memory, &[CellValue::Float(79.5), text("eng"), CellValue::Int(2)]).unwrap();
+        assert_eq!(names(result), ["'ann'", "'bob'"]);
+        let result = query.query(&mut memory, &[CellValue::Int(50), text("ops"), CellValue::Null]).unwrap();
+        assert_eq!(names(result), ["'fay'"]);
+        assert!(matches!(
+            query.query(&mut memory, &[CellValue::Int(50), text("ops"), text("all")]),
+            Err(MemoryError::InvalidParameter(3, _, CellType::Int))
+        ));
+
+        // the types come from what the parameters are compared to, in subqueries too.
+        let query = memory
+            .prepare(
+                "select name from employees \
+                where (salary > 90) = $1 or dept in (select dept from depts where floor = $2) order by name",
+            )
+            .unwrap();
+        let result = query.query(&mut memory, &[CellValue::Bool(true), CellValue::Int(2)]).unwrap();
+        assert_eq!(names(result), ["'ann'", "'cid'", "'fay'", "'gus'"]);
+        assert!(matches!(
+            query.query(&mut memory, &[CellValue::Bool(true), text("2")]),
+            Err(MemoryError::InvalidParameter(2, _, CellType::Int))
+        ));
+        // a parameter that is only selected can have any type.
+        let query = memory.prepare("select $1 from depts where floor = 1").unwrap();
+        assert_eq!(names(query.query(&mut memory, &[text("it's")]).unwrap()), ["'it's'"]);
+        assert_eq!(names(query.query(&mut memory, &[CellValue::Float(2.0)]).unwrap()), ["2"]);
+
+        // frame offsets and values rows can be parameters too.
+        let query = memory
+            .prepare(
+                "select count(*) over (order by name rows between $1 preceding and current row) \
+                 from employees where name in (select n from (values ($2), ('bob'), ('cid')) as v (n))",
+            )
+            .unwrap();
+        assert_eq!(names(query.query(&mut memory, &[CellValue::Int(1), text("ann")]).unwrap()), ["1", "2", "2"]);
+
+        let err = memory.prepare("select name from missing where dept = $1").err().unwrap();
+        assert!(matches!(err.downcast_ref::<MemoryError>(), Some(MemoryError::TableNotFound(_))));
+        assert!(memory.prepare("create table teams (name text)").is_err());
+        assert!(matches!(run_err(&mut memory, "select $1 from depts"), MemoryError::UnboundParameter(1)));
+        assert!(memory.run_query("select $0 from depts").is_err());
+    }
+
+    #[test]
+    fn test_prepare_and_execute() {
+        let mut memory = Memory::default();
+        departments(&mut memory);
+
+        run(
+            &mut memory,
+            "prepare raise (float) as update employees set salary = salary * $1 where dept = $2 returning name, salary",
+        );
+        assert_eq!(select(&mut memory, "execute raise(1.5, 'ops')"), [["'cid'", "75"]]);
+        assert_eq!(
+            select(&mut memory, "execute raise(2, (select dept from depts where floor = 2))"),
+            [["'cid'", "150"]]
+        );
+        assert!(matches!(
+            run_err(&mut memory, "execute raise('a lot', 'ops')"),
+            MemoryError::InvalidParameter(1, _, CellType::Float)
+        ));
+        assert!(matches!(run_err(&mut memory, "execute raise(2)"), MemoryError::WrongNumberOfParameters(2, 1)));
+        assert!(matches!(
+            run_err(&mut memory, "prepare raise as select name from employees"),
+            MemoryError::PreparedStatementAlreadyExists(_)
+        ));
+
+        run(
+            &mut memory,
+            "prepare cheapest as select name from employees where salary is not null order by salary limit 1",
+        );
+        assert_eq!(select(&mut memory, "execute cheapest"), [["'bob'"]]);
+        run(&mut memory, "deallocate prepare raise");
+        assert!(matches!(run_err(&mut memory, "execute raise(1, 'ops')"), MemoryError::PreparedStatementNotFound(_)));
+        assert!(matches!(run_err(&mut memory, "deallocate raise"), MemoryError::PreparedStatementNotFound(_)));
+        run(&mut memory, "deallocate all");
+        assert!(matches!(run_err(&mut memory, "execute cheapest"), MemoryError::PreparedStatementNotFound(_)));
+
+        assert!(memory.run_query("prepare teams as create table teams (name text)").is_err());
+        assert!(memory.run_query("prepare teams as").is_err());
+        assert!(memory.run_query("prepare (int) as select name from employees").is_err());
+
+        // parameters only compared to each other don't have a type.
+        run(&mut memory, "prepare doubled as select name from employees where salary = $1 + $1");
+        assert_eq!(select(&mut memory, "execute doubled(50)"), [["'ann'"]]);
+        run(&mut memory, "prepare same (int) as select name from employees where $2 = $1 and salary = $1");
+        assert!(matches!(
+            run_err(&mut memory, "execute same(50, 'a')"),
+            MemoryError::InvalidParameter(2, _, CellType::Int)
+        ));
+        // every parameter before the last one has to be used or declared.
+        assert!(matches!(
+            run_err(&mut memory, "prepare skipped as select $2"),
+            MemoryError::UnknownParameterType(1)
+        ));
+        run(&mut memory, "prepare declared (text) as select $2");
+        assert_eq!(select(&mut memory, "execute declared('a', 1)"), [["1"]]);
+    }
+
+    #[test]
+    fn test_select_without_from_and_values() {
+        let mut memory = Memory::default();
+        departments(&mut memory);
+
+        assert_eq!(select(&mut memory, "select 1 + 2, upper('x')"), [["3", "'X'"]]);
+        assert_eq!(select(&mut memory, "select 'it''s', ''''"), [["'it's'", "'''"]]);
+        assert_eq!(select(&mut memory, "select count(*) where 1 = 2"), [["0"]]);
+        assert_eq!(
+            select(&mut memory, "select name from employees where salary = (select 2 * 50)"),
+            [["'ann'"]]
+        );
+
+        // a values query names its columns column1, column2..., ints and floats mix into floats.
+        let result = memory.run_query("values (1, 'a'), (2.5, 'b') order by column2 desc").unwrap().unwrap();
+        let names: Vec<&str> = result.columns.iter().map(|column| column.name.as_str()).collect();
+        assert_eq!(names, ["column1", "column2"]);
+        assert_eq!(result.rows[0][0].value, CellValue::Float(2.5));
+        assert_eq!(result.rows[1][0].value, CellValue::Float(1.0));
+        assert_eq!(
+            select(&mut memory, "select dept from depts union values ('qa') order by dept"),
+            [["'eng'"], ["'hr'"], ["'ops'"], ["'qa'"]]
+        );
+
+        // in a from clause the values need an alias, which can rename their columns.
+        assert_eq!(
+            select(
+                &mut memory,
+                "select t.id, t.name, d.floor from (values (1, 'eng'), (2, 'hr')) as t (id, name) \
+                 join depts d on d.dept = t.name order by t.id desc"
+            ),
+            [["2", "'hr'", "3"], ["1", "'eng'", "1"]]
+        );
+        assert_eq!(
+            select(&mut memory, "select v.column2 from (values (1, null), (2, 'b')) v where column1 = 2"),
+            [["'b'"]]
+        );
+        assert!(matches!(
+            run_err(&mut memory, "select x from (values (1)) as t (x, y)"),
+            MemoryError::TooManyColumnNames(_, 1, 2)
+        ));
+        assert!(matches!(
+            run_err(&mut memory, "values (1), ('a')"),
+            MemoryError::IncompatibleTypes(_, CellType::Int, CellType::Text)
+        ));
+        assert!(memory.run_query("values (1, 2), (3)").is_err());
+        assert!(memory.run_query("select column1 from (values (1))").is_err());
+
+        let lines: Vec<String> =
+            select(&mut memory, "explain values (1), (2)").into_iter().map(|row| row[0].clone()).collect();
+        assert_eq!(
+            lines,
+            ["'Project'", "'  Output: column1'", "'  ->  Values Scan on *values*'", "'        Rows: 2'"]
+        );
+        let lines: Vec<String> =
+            select(&mut memory, "explain analyze select 1").into_iter().map(|row| row[0].clone()).collect();
+        assert!(lines[2].starts_with("'  ->  Result  (actual rows=1 loops=1"));
+
+        run(&mut memory, "create table pairs as values (1, 'one'), (2, 'two')");
+        assert_eq!(select(&mut memory, "select column2 from pairs where column1 = 2"), [["'two'"]]);
+        run(&mut memory, "insert into pairs select 3, 'three'");
+        assert_eq!(select(&mut memory, "select count(*) from pairs"), [["3"]]);
+    }
+
+    #[test]
+    fn test_foreign_keys() {
+        let mut memory = Memory::default();
+        run(&mut memory, "create table teams (id int primary key, name text unique)");
+        for team in ["(1, 'core')", "(2, 'web')", "(3, 'ops')"] {
+            run(&mut memory, &format!("insert into teams values {}", team));
+        }
+        run(
+            &mut memory,
+            "create table members (name text, team int references teams on delete cascade on update cascade)",
+        );
+        run(
+            &mut memory,
+            "create table tasks (title text, team_name text default 'ops', \
+             foreign key (team_name) references teams (name) on update restrict on delete set default)",
+        );
+        for member in ["('ann', 1)", "('bob', 2)", "('cid', null)"] {
+            run(&mut memory, &format!("insert into members values {}", member));
+        }
+        run(&mut memory, "insert into tasks values ('deploy', 'web')");
+        run(&mut memory, "insert into tasks values ('page', 'ops')");
+
+        // the referenced row has to exist, unless the key is null.
+        assert!(matches!(
+            run_err(&mut memory, "insert into members values ('dan', 4)"),
+            MemoryError::ForeignKeyViolation(_, _, _)
+        ));
+        assert!(matches!(
+            run_err(&mut memory, "update members set team = 4 where name = 'ann'"),
+            MemoryError::ForeignKeyViolation(_, _, _)
+        ));
+        assert_eq!(select(&mut memory, "select count(*) from members where team = 1"), [["1"]]);
+
+        run(&mut memory, "update teams set id = 20 where id = 2");
+        assert_eq!(select(&mut memory, "select team from members where name = 'bob'"), [["20"]]);
+        run(&mut memory, "delete from teams where id = 1");
+        assert_eq!(select(&mut memory, "select name from members order by name"), [["'bob'"], ["'cid'"]]);
+
+        assert!(matches!(
+            run_err(&mut memory, "update teams set name = 'front' where name = 'web'"),
+            MemoryError::StillReferenced(_, _, _)
+        ));
+        run(&mut memory, "delete from teams where name = 'web'");
+        assert_eq!(select(&mut memory, "select team_name from tasks"), [["'ops'"], ["'ops'"]]);
+        assert_eq!(select(&mut memory, "select count(*) from members"), [["1"]]);
+        // the default of the tasks doesn't reference a team anymore, nothing is deleted.
+        assert!(matches!(
+            run_err(&mut memory, "delete from teams where name = 'ops'"),
+            MemoryError::ForeignKeyViolation(_, _, _)
+        ));
+        assert_eq!(select(&mut memory, "select name from teams"), [["'ops'"]]);
+        assert_eq!(select(&mut memory, "select count(*) from tasks where team_name = 'ops'"), [["2"]]);
+
+        // without an action the key can still move to another row.
+        run(&mut memory, "create table leads (name text, team int references teams (id))");
+        run(&mut memory, "insert into teams values (4, 'qa')");
+        run(&mut memory, "insert into leads values ('eve', 3)");
+        run(&mut memory, "update teams set id = id - 1");
+        assert!(matches!(
+            run_err(&mut memory, "delete from teams where id = 3"),
+            MemoryError::StillReferenced(_, _, _)
+        ));
+
+        assert!(matches!(run_err(&mut memory, "drop table teams"), MemoryError::DependentForeignKeys(_, _)));
+        run(&mut memory, "drop table teams cascade");
+        run(&mut memory, "insert into leads values ('fay', 99)");
+    }
+
+    #[test]
+    fn test_foreign_key_to_own_table() {
+        let mut memory = Memory::default();
+        run(
+            &mut memory,
+            "create table nodes (id int primary key, parent int not null references nodes on delete cascade)",
+        );
+        // the row is stored before its reference is checked, so it can reference itself.
+        run(&mut memory, "insert into nodes values (1, 1)");
+        run(&mut memory, "insert into nodes values (2, 1)");
+        run(&mut memory, "insert into nodes values (3, 2)");
+        run(&mut memory, "insert into nodes values (4, 4)");
+        run(&mut memory, "delete from nodes where id = 2");
+        assert_eq!(select(&mut memory, "select id from nodes order by id"), [["1"], ["4"]]);
+
+        run(&mut memory, "create table links (node int references nodes on delete set null on update set null)");
+        run(&mut memory, "insert into links values (4)");
+        assert!(matches!(
+            run_err(&mut memory, "update nodes set parent = 5 where id = 4"),
+            MemoryError::ForeignKeyViolation(_, _, _)
+        ));
+        run(&mut memory, "update nodes set id = 5, parent = 5 where id = 4");
+        assert_eq!(select(&mut memory, "select node from links"), [["null"]]);
+
+        assert!(matches!(
+            run_err(&mut memory, "create table bad (node text references nodes)"),
+            MemoryError::IncompatibleTypes(_, CellType::Text, CellType::Int)
+        ));
+        assert!(matches!(
+            run_err(&mut memory, "create table bad (node int references nodes (parent))"),
+            MemoryError::NoReferencedKey(_)
+        ));
+        assert!(matches!(
+            run_err(&mut memory, "create table bad (node int, foreign key (node) references missing)"),
+            MemoryError::TableNotFound(_)
+        ));
+        assert!(matches!(
+            run_err(&mut memory, "create table bad (a int, b int, foreign key (a, b) references nodes)"),
+            MemoryError::WrongNumberOfReferencedColumns(2, 1)
+        ));
+        assert!(memory.run_query("create table bad (node int references nodes on insert cascade)").is_err());
+    }
+
+    #[test]
+    fn test_identity_columns() {
+        let mut memory = Memory::default();
+        run(
+            &mut memory,
+            "create table tickets (id int generated always as identity primary key, title text not null)",
+        );
+        run(&mut memory, "insert into tickets (title) values ('crash')");
+        assert_eq!(
+            select(&mut memory, "insert into tickets (title) values ('typo') returning id"),
+            [["2"]]
+        );
+        assert!(matches!(
+            run_err(&mut memory, "insert into tickets values (3, 'slow')"),
+            MemoryError::GeneratedAlways(_)
+        ));
+        assert!(matches!(
+            run_err(&mut memory, "update tickets set id = 5 where title = 'crash'"),
+            MemoryError::GeneratedAlways(_)
+        ));
+        // a failed insert still uses up its value.
+        assert!(matches!(
+            run_err(&mut memory, "insert into tickets (title) values (null)"),
+            MemoryError::NotNullViolation(_)
+        ));
+        run(&mut memory, "insert into tickets (title) select 'leak'");
+        assert_eq!(
+            select(&mut memory, "select id, title from tickets order by id"),
+            [["1", "'crash'"], ["2", "'typo'"], ["4", "'leak'"]]
+        );
+
+        // serial and by default columns take the values they are given, without moving the counter.
+        run(&mut memory, "create table notes (id serial, position int generated by default as identity, body text)");
+        run(&mut memory, "insert into notes (body) values ('a')");
+        run(&mut memory, "insert into notes values (10, 20, 'b')");
+        run(&mut memory, "insert into notes (body, position) values ('c', 30)");
+        assert_eq!(
+            select(&mut memory, "select id, position, body from notes"),
+            [["1", "1", "'a'"], ["10", "20", "'b'"], ["2", "30", "'c'"]]
+        );
+        assert!(matches!(
+            run_err(&mut memory, "insert into notes (id, body) values (null, 'd')"),
+            MemoryError::NotNullViolation(_)
+        ));
+        run(&mut memory, "update notes set id = 3 where body = 'c'");
+
+        assert!(matches!(
+            run_err(&mut memory, "create table bad (id text generated always as identity)"),
+            MemoryError::InvalidIdentity(_)
+        ));
+        assert!(matches!(
+            run_err(&mut memory, "create table bad (id serial default 1)"),
+            MemoryError::InvalidIdentity(_)
+        ));
+        assert!(memory.run_query("create table bad (id int generated as identity)").is_err());
+        assert!(memory.run_query("select cast(1 as serial)").is_err());
+    }
+
+}